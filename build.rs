@@ -0,0 +1,130 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+// the front end is only embedded when built with --features embed-front-end
+fn main() {
+    #[cfg(feature = "embed-front-end")]
+    front_end::embed();
+}
+
+#[cfg(feature = "embed-front-end")]
+mod front_end {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use sha2::{Digest, Sha256};
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+    use std::path::{Path, PathBuf};
+
+    // the dist directory of transfery-vue, relative to the manifest, named apart from
+    // TRANSFERY_FRONT_END_DIR which is read at runtime
+    const DIR_VAR: &str = "TRANSFERY_BUILD_FRONT_END_DIR";
+    const DEFAULT_DIR: &str = "dist";
+
+    fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        let mut entries = std::fs::read_dir(dir)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", dir.display(), e))
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<PathBuf>>();
+
+        // keeps the generated file stable between builds
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                collect_files(&path, files);
+            } else if path.extension().and_then(|extension| extension.to_str()) != Some("gz") {
+                files.push(path);
+            }
+        }
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    pub fn embed() {
+        println!("cargo:rerun-if-env-changed={}", DIR_VAR);
+
+        let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+
+        let dir = manifest_dir.join(std::env::var(DIR_VAR).unwrap_or(DEFAULT_DIR.to_string()));
+
+        println!("cargo:rerun-if-changed={}", dir.display());
+
+        // checks like clippy --all-features don't need the front end, only releases do
+        if !dir.join("index.html").is_file() {
+            let message = format!(
+                "{} has no index.html, build the front end or set {}",
+                dir.display(),
+                DIR_VAR
+            );
+
+            if std::env::var("PROFILE").as_deref() == Ok("release") {
+                panic!("{}", message);
+            }
+
+            println!("cargo:warning={}, nothing is embedded", message);
+
+            std::fs::write(
+                out_dir.join("front_end.rs"),
+                "pub static ASSETS: &[Asset] = &[];\n",
+            )
+            .unwrap();
+
+            return;
+        }
+
+        let mut files = Vec::new();
+        collect_files(&dir, &mut files);
+
+        let gzip_dir = out_dir.join("front-end");
+        let mut assets = String::new();
+
+        for (index, file) in files.iter().enumerate() {
+            let data = std::fs::read(file).unwrap();
+            let path = file
+                .strip_prefix(&dir)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let hash = Sha256::digest(&data);
+            let etag = hash.iter().take(8).fold(String::new(), |mut etag, byte| {
+                write!(etag, "{:02x}", byte).unwrap();
+                etag
+            });
+
+            // images and fonts are already compressed, their gzip variant isn't kept
+            let compressed = gzip(&data);
+            let gzip = if compressed.len() < data.len() * 9 / 10 {
+                std::fs::create_dir_all(&gzip_dir).unwrap();
+
+                let gzip_path = gzip_dir.join(format!("{}.gz", index));
+                std::fs::write(&gzip_path, compressed).unwrap();
+
+                format!("Some(include_bytes!({:?}))", gzip_path)
+            } else {
+                "None".to_string()
+            };
+
+            writeln!(
+                assets,
+                "    Asset {{ path: {:?}, etag: \"\\\"{}\\\"\", data: include_bytes!({:?}), gzip: {} }},",
+                path, etag, file, gzip
+            )
+            .unwrap();
+        }
+
+        let content = format!("pub static ASSETS: &[Asset] = &[\n{}];\n", assets);
+
+        std::fs::write(out_dir.join("front_end.rs"), content).unwrap();
+    }
+}
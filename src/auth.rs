@@ -89,11 +89,12 @@ where
 
                 if let Ok(certificate) = crypto.decrypt(&certificate) {
                     if let Ok(certificate) = serde_json::from_str::<Certificate>(&certificate) {
+                        // the fingerprint and certificate are credentials, only compare them
                         tracing::debug!(
-                            "certificate details:\nfingerprint: {}\ncurrent timestamp: {}\ncertificate: {:#?}",
-                            fingerprint,
+                            "certificate fingerprint matched: {}, current timestamp: {}, expiration timestamp: {}",
+                            fingerprint == certificate.fingerprint,
                             get_current_timestamp(),
-                            certificate
+                            certificate.timestamp
                         );
                         if fingerprint == certificate.fingerprint
                             && get_current_timestamp() < certificate.timestamp
@@ -0,0 +1,80 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+
+use super::models::bundle::{self, BundleFileItem};
+use super::Database;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl Database {
+    pub async fn insert_bundle_file(
+        &self,
+        BundleFileItem {
+            message_id,
+            path,
+            file_name,
+        }: BundleFileItem,
+    ) -> Result<i64> {
+        let insert_item = bundle::ActiveModel {
+            message_id: Set(message_id),
+            path: Set(path),
+            file_name: Set(file_name),
+            ..Default::default()
+        };
+
+        let id = bundle::Entity::insert(insert_item)
+            .exec(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to insert bundle file"))?
+            .last_insert_id;
+
+        Ok(id)
+    }
+
+    pub async fn query_bundle_files(&self, message_id: i64) -> Result<Vec<bundle::Model>> {
+        let items = bundle::Entity::find()
+            .filter(bundle::Column::MessageId.eq(message_id))
+            .order_by_asc(bundle::Column::Path)
+            .all(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to query bundle files"))?;
+
+        Ok(items)
+    }
+
+    pub async fn query_bundle_files_all(&self) -> Result<Vec<bundle::Model>> {
+        let items = bundle::Entity::find()
+            .all(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to query bundle files all")
+            })?;
+
+        Ok(items)
+    }
+
+    pub async fn remove_bundle_files(&self, message_id: i64) -> Result<()> {
+        bundle::Entity::delete_many()
+            .filter(bundle::Column::MessageId.eq(message_id))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to remove bundle files"))?;
+
+        Ok(())
+    }
+
+    pub async fn remove_bundle_all(&self) -> Result<()> {
+        bundle::Entity::delete_many()
+            .exec(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to remove bundle all"))?;
+
+        Ok(())
+    }
+}
@@ -5,21 +5,23 @@
 :license: MIT, see LICENSE for more details.
 */
 
+use sea_orm::sea_query::{Expr, Query, Table};
 use sea_orm::{
-    ConnectionTrait, DatabaseConnection, DbBackend, EntityName, EntityTrait, PaginatorTrait,
-    Schema, Set, Statement,
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbBackend, EntityName,
+    EntityTrait, IdenStatic, PaginatorTrait, QueryFilter, QueryOrder, Schema, Set, Statement,
 };
 use tokio::fs;
 
 use super::Database;
-use crate::client::database::models::{auth, device, message, token};
-use crate::crypto::Crypto;
+use crate::client::database::models::{auth, bundle, device, message, token};
+use crate::crypto::{Crypto, SecretKey};
 use crate::env::DatabaseEnv;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
+use crate::utils::get_current_timestamp;
 
 impl Database {
-    pub async fn new(config: &DatabaseEnv) -> Result<Self> {
+    pub async fn new(config: &DatabaseEnv, crypto: Option<Crypto>) -> Result<Self> {
         match config {
             DatabaseEnv::MySql(config) => {
                 let connection = sea_orm::Database::connect(format!(
@@ -45,6 +47,39 @@ impl Database {
                 Ok(Self {
                     connection,
                     _name: config.database.clone(),
+                    crypto,
+                })
+            }
+            DatabaseEnv::Postgres(config) => {
+                // connect to the maintenance database first, the configured one may not exist yet
+                let connection = sea_orm::Database::connect(format!(
+                    "postgres://{}:{}@{}/postgres",
+                    config.username, config.password, config.endpoint
+                ))
+                .await
+                .map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        "failed to connect to Postgres partial",
+                    )
+                })?;
+
+                Database::create_database_if_not_exists(&connection, &config.database).await?;
+
+                let connection = sea_orm::Database::connect(format!(
+                    "postgres://{}:{}@{}/{}",
+                    config.username, config.password, config.endpoint, config.database
+                ))
+                .await
+                .map_err(|e| {
+                    Error::context(InternalServerError, e, "failed to connect to Postgres")
+                })?;
+
+                Ok(Self {
+                    connection,
+                    _name: config.database.clone(),
+                    crypto,
                 })
             }
             DatabaseEnv::Sqlite(config) => {
@@ -58,12 +93,13 @@ impl Database {
                 Ok(Self {
                     connection,
                     _name: config.path.clone(),
+                    crypto,
                 })
             }
         }
     }
 
-    pub async fn _close(self) -> Result<()> {
+    pub async fn close(self) -> Result<()> {
         self.connection.close().await.map_err(|e| {
             Error::context(
                 InternalServerError,
@@ -75,20 +111,59 @@ impl Database {
         Ok(())
     }
 
+    pub async fn ping(&self) -> Result<()> {
+        self.connection
+            .ping()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to ping database"))
+    }
+
     pub async fn create_database_if_not_exists(
         connection: &DatabaseConnection,
         name: &str,
     ) -> Result<()> {
-        if connection.get_database_backend() == DbBackend::MySql {
-            let sql = format!("create database if not exists `{}`", name);
+        match connection.get_database_backend() {
+            DbBackend::MySql => {
+                let sql = format!("create database if not exists `{}`", name);
 
-            connection
-                .execute(Statement::from_string(
-                    connection.get_database_backend(),
-                    sql,
-                ))
-                .await
-                .map_err(|e| Error::context(InternalServerError, e, "failed to create database"))?;
+                connection
+                    .execute(Statement::from_string(
+                        connection.get_database_backend(),
+                        sql,
+                    ))
+                    .await
+                    .map_err(|e| {
+                        Error::context(InternalServerError, e, "failed to create database")
+                    })?;
+            }
+            // postgres has no create database if not exists
+            DbBackend::Postgres => {
+                let statement = Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    "select 1 from pg_database where datname = $1",
+                    [name.into()],
+                );
+
+                let is_exists = connection
+                    .query_one(statement)
+                    .await
+                    .map_err(|e| {
+                        Error::context(InternalServerError, e, "failed to query database")
+                    })?
+                    .is_some();
+
+                if !is_exists {
+                    let sql = format!("create database \"{}\"", name.replace('"', "\"\""));
+
+                    connection
+                        .execute(Statement::from_string(DbBackend::Postgres, sql))
+                        .await
+                        .map_err(|e| {
+                            Error::context(InternalServerError, e, "failed to create database")
+                        })?;
+                }
+            }
+            _ => {}
         }
 
         Ok(())
@@ -96,7 +171,10 @@ impl Database {
 
     pub async fn init(&self) -> Result<()> {
         self.create_table_message_if_not_exists().await?;
+        self.migrate_table_message().await?;
+        self.create_table_bundle_if_not_exists().await?;
         self.create_table_auth_if_not_exists().await?;
+        self.migrate_table_auth().await?;
         self.create_table_device_if_not_exists().await?;
         self.create_table_token_if_not_exists().await?;
         self.create_secret_key_if_not_exists().await?;
@@ -108,7 +186,16 @@ impl Database {
     where
         E: EntityTrait,
     {
-        let result = E::find().all(&self.connection).await;
+        // only select a constant, so that tables missing newly added columns are still found
+        let statement = Query::select()
+            .expr(Expr::val(1))
+            .from(E::default())
+            .limit(1)
+            .to_owned();
+
+        let backend = self.connection.get_database_backend();
+
+        let result = self.connection.query_one(backend.build(&statement)).await;
 
         if result.is_ok() {
             true
@@ -117,6 +204,56 @@ impl Database {
         }
     }
 
+    async fn is_column_exists<E>(&self, column: E::Column) -> bool
+    where
+        E: EntityTrait,
+    {
+        let statement = Query::select()
+            .column(column)
+            .from(E::default())
+            .limit(1)
+            .to_owned();
+
+        let backend = self.connection.get_database_backend();
+
+        let result = self.connection.query_one(backend.build(&statement)).await;
+
+        result.is_ok()
+    }
+
+    async fn add_column_if_not_exists<E>(&self, entity: E, column: E::Column) -> Result<()>
+    where
+        E: EntityTrait + EntityName,
+    {
+        if !self.is_column_exists::<E>(column).await {
+            let backend = self.connection.get_database_backend();
+
+            let column_def = Schema::new(backend).get_column_def::<E>(column);
+
+            let table_alter_statement = Table::alter()
+                .table(entity)
+                .add_column(&mut column_def.to_owned())
+                .to_owned();
+
+            self.connection
+                .execute(backend.build(&table_alter_statement))
+                .await
+                .map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        format!(
+                            "failed to add column {} to table {}",
+                            column.as_str(),
+                            entity.table_name()
+                        ),
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
     async fn create_table_if_not_exists<E>(&self, entity: E) -> Result<()>
     where
         E: EntityTrait + EntityName,
@@ -147,12 +284,37 @@ impl Database {
         Ok(())
     }
 
+    // columns added after the table was first released
+    pub async fn migrate_table_message(&self) -> Result<()> {
+        self.add_column_if_not_exists(message::Entity, message::Column::HasThumbnail)
+            .await?;
+        self.add_column_if_not_exists(message::Entity, message::Column::Hash)
+            .await?;
+        self.add_column_if_not_exists(message::Entity, message::Column::IsMissing)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_table_bundle_if_not_exists(&self) -> Result<()> {
+        self.create_table_if_not_exists(bundle::Entity).await?;
+
+        Ok(())
+    }
+
     pub async fn create_table_auth_if_not_exists(&self) -> Result<()> {
         self.create_table_if_not_exists(auth::Entity).await?;
 
         Ok(())
     }
 
+    pub async fn migrate_table_auth(&self) -> Result<()> {
+        self.add_column_if_not_exists(auth::Entity, auth::Column::RetiredTimestamp)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn create_table_device_if_not_exists(&self) -> Result<()> {
         self.create_table_if_not_exists(device::Entity).await?;
 
@@ -194,14 +356,60 @@ impl Database {
         Ok(count > 0)
     }
 
-    pub async fn get_secret_key(&self) -> Result<String> {
-        let auth::Model { secret_key, .. } = auth::Entity::find()
-            .one(&self.connection)
+    // keys that haven't passed their grace period yet
+    pub async fn get_secret_keys(&self) -> Result<Vec<SecretKey>> {
+        let items = auth::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(auth::Column::RetiredTimestamp.is_null())
+                    .add(auth::Column::RetiredTimestamp.gt(get_current_timestamp())),
+            )
+            .order_by_asc(auth::Column::Id)
+            .all(&self.connection)
             .await
-            .map_err(|e| Error::context(InternalServerError, e, "failed to get secret key"))?
-            .ok_or_else(|| Error::new(InternalServerError, "secret key not found"))?;
+            .map_err(|e| Error::context(InternalServerError, e, "failed to get secret keys"))?;
 
-        Ok(secret_key)
+        Ok(items.into_iter().map(SecretKey::from).collect())
+    }
+
+    // older keys keep decrypting for the grace period, returns the id of the new key
+    pub async fn rotate_secret_key(&self, grace_period: i64) -> Result<i64> {
+        let current_timestamp = get_current_timestamp();
+
+        auth::Entity::delete_many()
+            .filter(auth::Column::RetiredTimestamp.lte(current_timestamp))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to remove retired secret keys",
+                )
+            })?;
+
+        auth::Entity::update_many()
+            .filter(auth::Column::RetiredTimestamp.is_null())
+            .col_expr(
+                auth::Column::RetiredTimestamp,
+                Expr::value(current_timestamp + grace_period),
+            )
+            .exec(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to retire secret keys"))?;
+
+        let insert_item = auth::ActiveModel {
+            secret_key: Set(Crypto::gen_secret_key()?),
+            ..Default::default()
+        };
+
+        let id = auth::Entity::insert(insert_item)
+            .exec(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to create secret key"))?
+            .last_insert_id;
+
+        Ok(id)
     }
 
     pub async fn _drop_database_if_exists(self) -> Result<()> {
@@ -219,25 +427,32 @@ impl Database {
                         Error::context(InternalServerError, e, "failed to drop database")
                     })?;
 
-                self._close().await?;
+                self.close().await?;
+            }
+            // a database can't drop itself in postgres, its tables are dropped instead
+            DbBackend::Postgres => {
+                for sql in ["drop schema public cascade", "create schema public"] {
+                    self.connection
+                        .execute(Statement::from_string(DbBackend::Postgres, sql))
+                        .await
+                        .map_err(|e| {
+                            Error::context(InternalServerError, e, "failed to drop database")
+                        })?;
+                }
+
+                self.close().await?;
             }
             DbBackend::Sqlite => {
                 if fs::metadata(&self._name).await.is_ok() {
                     let path = self._name.clone();
 
-                    self._close().await?;
+                    self.close().await?;
 
                     fs::remove_file(&path).await.map_err(|e| {
                         Error::context(InternalServerError, e, "failed to remove sqlite file")
                     })?;
                 }
             }
-            _ => {
-                return Err(Error::new(
-                    InternalServerError,
-                    "unsupported database backend",
-                ));
-            }
         }
 
         Ok(())
@@ -5,14 +5,22 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use sea_orm::sea_query::Expr;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set};
+use sea_orm::sea_query::{Expr, LikeExpr};
+use sea_orm::{
+    ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
 
-use super::models::message::{self, MessageItem};
+use super::models::message::{self, MessageItem, MessageItemType};
 use super::Database;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
 
+// private contents are stored encrypted when a key is set, older ones are kept as they are
+const ENCRYPTED_CONTENT_PREFIX: &str = "encrypted:";
+
+const LIKE_ESCAPE: char = '!';
+
 impl Database {
     pub async fn query_message_items(
         &self,
@@ -39,7 +47,7 @@ impl Database {
             .await
             .map_err(|e| Error::context(InternalServerError, e, "failed to query message items"))?;
 
-        Ok(items)
+        Ok(self.decrypt_message_items(items))
     }
 
     pub async fn query_message_items_after_id(
@@ -65,9 +73,292 @@ impl Database {
             )
         })?;
 
+        Ok(self.decrypt_message_items(items))
+    }
+
+    pub async fn query_message_files_by_ids(
+        &self,
+        ids: Vec<i64>,
+        access_private: bool,
+    ) -> Result<Vec<message::Model>> {
+        let query = {
+            let mut query = message::Entity::find()
+                .filter(message::Column::Id.is_in(ids))
+                .filter(
+                    message::Column::TypeField
+                        .is_in([MessageItemType::File, MessageItemType::Bundle]),
+                )
+                .filter(message::Column::IsComplete.eq(true))
+                .order_by_asc(message::Column::Timestamp)
+                .order_by_asc(message::Column::Id);
+
+            if !access_private {
+                query = query.filter(message::Column::IsPrivate.eq(false));
+            }
+
+            query
+        };
+
+        let items = query.all(&self.connection).await.map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to query message files by ids",
+            )
+        })?;
+
+        Ok(self.decrypt_message_items(items))
+    }
+
+    pub async fn query_message_files_by_timestamp(
+        &self,
+        start: i64,
+        end: i64,
+        access_private: bool,
+    ) -> Result<Vec<message::Model>> {
+        let query = {
+            let mut query = message::Entity::find()
+                .filter(message::Column::Timestamp.between(start, end))
+                .filter(
+                    message::Column::TypeField
+                        .is_in([MessageItemType::File, MessageItemType::Bundle]),
+                )
+                .filter(message::Column::IsComplete.eq(true))
+                .order_by_asc(message::Column::Timestamp)
+                .order_by_asc(message::Column::Id);
+
+            if !access_private {
+                query = query.filter(message::Column::IsPrivate.eq(false));
+            }
+
+            query
+        };
+
+        let items = query.all(&self.connection).await.map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to query message files by timestamp",
+            )
+        })?;
+
+        Ok(self.decrypt_message_items(items))
+    }
+
+    pub async fn query_message_item(&self, id: i64) -> Result<Option<message::Model>> {
+        let item = message::Entity::find_by_id(id)
+            .one(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to query message item"))?;
+
+        Ok(item.map(|item| self.decrypt_message_item(item)))
+    }
+
+    // oldest first, so that a purge stopped halfway leaves the newer messages
+    pub async fn query_message_items_before(&self, timestamp: i64) -> Result<Vec<message::Model>> {
+        let items = message::Entity::find()
+            .filter(message::Column::Timestamp.lt(timestamp))
+            .order_by_asc(message::Column::Timestamp)
+            .order_by_asc(message::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to query message items before timestamp",
+                )
+            })?;
+
+        Ok(self.decrypt_message_items(items))
+    }
+
+    // the new message refers to the object of a file with the hash, it's inserted in the same
+    // transaction as the lookup and the file is locked, so that removing the file meanwhile
+    // counts the new reference and keeps the object
+    pub async fn insert_message_file_by_hash(
+        &self,
+        hash: &str,
+        content: &str,
+        timestamp: i64,
+        is_private: bool,
+    ) -> Result<Option<message::Model>> {
+        let transaction =
+            self.connection.begin().await.map_err(|e| {
+                Error::context(InternalServerError, e, "failed to begin transaction")
+            })?;
+
+        let mut query = message::Entity::find()
+            .filter(message::Column::Hash.eq(hash))
+            .filter(message::Column::TypeField.eq(MessageItemType::File))
+            .filter(message::Column::IsComplete.eq(true));
+
+        // a public message must not reveal the object of a private file
+        if !is_private {
+            query = query.filter(message::Column::IsPrivate.eq(false));
+        }
+
+        let existing = query
+            .lock_exclusive()
+            .one(&transaction)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to query message file by hash",
+                )
+            })?;
+
+        // the transaction is rolled back when dropped
+        let existing = match existing {
+            Some(existing) => existing,
+            None => return Ok(None),
+        };
+
+        let content = match is_private {
+            true => self.encrypt_message_content(content)?,
+            false => content.to_string(),
+        };
+
+        let insert_item = message::ActiveModel {
+            content: Set(content),
+            timestamp: Set(timestamp),
+            is_private: Set(is_private),
+            type_field: Set(MessageItemType::File),
+            file_name: Set(existing.file_name),
+            is_complete: Set(Some(true)),
+            has_thumbnail: Set(existing.has_thumbnail),
+            hash: Set(existing.hash),
+            ..Default::default()
+        };
+
+        let id = message::Entity::insert(insert_item)
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to insert message file by hash",
+                )
+            })?
+            .last_insert_id;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to commit transaction"))?;
+
+        self.query_message_item(id).await
+    }
+
+    // the oldest complete file or bundle whose object has the name,
+    // objects are named by a directory and the file name, older ones by the file name alone
+    pub async fn query_message_file_by_object_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<message::Model>> {
+        let pattern = format!("%/{}", escape_like(name));
+
+        let items = message::Entity::find()
+            .filter(
+                message::Column::TypeField.is_in([MessageItemType::File, MessageItemType::Bundle]),
+            )
+            .filter(message::Column::IsComplete.eq(true))
+            .filter(
+                Condition::any()
+                    .add(message::Column::FileName.eq(name))
+                    .add(
+                        Expr::col(message::Column::FileName)
+                            .like(LikeExpr::new(pattern).escape(LIKE_ESCAPE)),
+                    ),
+            )
+            .order_by_asc(message::Column::Timestamp)
+            .order_by_asc(message::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to query message file by object name",
+                )
+            })?;
+
+        // like ignores the case in some databases
+        let item = items.into_iter().find(|item| {
+            item.file_name
+                .as_deref()
+                .and_then(|file_name| file_name.rsplit('/').next())
+                == Some(name)
+        });
+
+        Ok(item.map(|item| self.decrypt_message_item(item)))
+    }
+
+    // deduplicated messages share the same object
+    pub async fn count_message_file_references(&self, file_name: &str) -> Result<u64> {
+        let count = message::Entity::find()
+            .filter(message::Column::FileName.eq(file_name))
+            .count(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to count message file references",
+                )
+            })?;
+
+        Ok(count)
+    }
+
+    pub async fn count_message_items_by_type(&self, type_field: MessageItemType) -> Result<u64> {
+        let count = message::Entity::find()
+            .filter(message::Column::TypeField.eq(type_field))
+            .count(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to count message items"))?;
+
+        Ok(count)
+    }
+
+    pub async fn query_message_files_all(&self) -> Result<Vec<message::Model>> {
+        let items = message::Entity::find()
+            .filter(
+                message::Column::TypeField.is_in([MessageItemType::File, MessageItemType::Bundle]),
+            )
+            .all(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to query message files all")
+            })?;
+
         Ok(items)
     }
 
+    pub async fn query_message_files_complete(&self) -> Result<Vec<message::Model>> {
+        let items = message::Entity::find()
+            .filter(
+                message::Column::TypeField.is_in([MessageItemType::File, MessageItemType::Bundle]),
+            )
+            .filter(message::Column::IsComplete.eq(true))
+            .order_by_asc(message::Column::Timestamp)
+            .order_by_asc(message::Column::Id)
+            .all(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to query message files complete",
+                )
+            })?;
+
+        Ok(self.decrypt_message_items(items))
+    }
+
     pub async fn query_message_latest(&self) -> Result<Option<message::Model>> {
         let message = message::Entity::find()
             .order_by_desc(message::Column::Timestamp)
@@ -79,12 +370,17 @@ impl Database {
                 Error::context(InternalServerError, e, "failed to query message latest")
             })?;
 
-        Ok(message)
+        Ok(message.map(|message| self.decrypt_message_item(message)))
     }
 
     pub async fn insert_message_item(&self, item: MessageItem) -> Result<i64> {
+        let content = match item.is_private {
+            true => self.encrypt_message_content(&item.content)?,
+            false => item.content,
+        };
+
         let insert_item = message::ActiveModel {
-            content: Set(item.content),
+            content: Set(content),
             timestamp: Set(item.timestamp),
             is_private: Set(item.is_private),
             type_field: Set(item.type_field),
@@ -120,6 +416,43 @@ impl Database {
         Ok(())
     }
 
+    pub async fn update_thumbnail(&self, id: i64) -> Result<()> {
+        message::Entity::update_many()
+            .filter(message::Column::Id.eq(id))
+            .col_expr(message::Column::HasThumbnail, Expr::value(true))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to update message thumbnail")
+            })?;
+
+        Ok(())
+    }
+
+    pub async fn update_hash(&self, id: i64, hash: &str) -> Result<()> {
+        message::Entity::update_many()
+            .filter(message::Column::Id.eq(id))
+            .col_expr(message::Column::Hash, Expr::value(hash))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to update message hash"))?;
+
+        Ok(())
+    }
+
+    pub async fn update_missing(&self, id: i64) -> Result<()> {
+        message::Entity::update_many()
+            .filter(message::Column::Id.eq(id))
+            .col_expr(message::Column::IsMissing, Expr::value(true))
+            .exec(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to update message missing")
+            })?;
+
+        Ok(())
+    }
+
     pub async fn update_complete(&self, id: i64) -> Result<()> {
         message::Entity::update_many()
             .filter(message::Column::Id.eq(id))
@@ -132,4 +465,52 @@ impl Database {
 
         Ok(())
     }
+
+    fn encrypt_message_content(&self, content: &str) -> Result<String> {
+        match &self.crypto {
+            Some(crypto) => Ok(format!(
+                "{}{}",
+                ENCRYPTED_CONTENT_PREFIX,
+                crypto.encrypt(content)?
+            )),
+            None => Ok(content.to_string()),
+        }
+    }
+
+    fn decrypt_message_items(&self, items: Vec<message::Model>) -> Vec<message::Model> {
+        items
+            .into_iter()
+            .map(|item| self.decrypt_message_item(item))
+            .collect()
+    }
+
+    // a content that only looks encrypted was typed by the user, so it's returned as it is
+    fn decrypt_message_item(&self, mut item: message::Model) -> message::Model {
+        let encrypted = match item.content.strip_prefix(ENCRYPTED_CONTENT_PREFIX) {
+            Some(encrypted) if item.is_private => encrypted,
+            _ => return item,
+        };
+
+        match self.crypto.as_ref().map(|crypto| crypto.decrypt(encrypted)) {
+            Some(Ok(content)) => item.content = content,
+            Some(Err(e)) => tracing::debug!("content of message {} kept: {}", item.id, e),
+            None => tracing::warn!("message {} is encrypted but no key is set", item.id),
+        }
+
+        item
+    }
+}
+
+fn escape_like(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | LIKE_ESCAPE) {
+            result.push(LIKE_ESCAPE);
+        }
+
+        result.push(c);
+    }
+
+    result
 }
@@ -7,6 +7,9 @@
 
 use sea_orm::DatabaseConnection;
 
+use crate::crypto::Crypto;
+
+mod bundle;
 mod device;
 mod init;
 mod message;
@@ -19,4 +22,6 @@ mod token;
 pub struct Database {
     connection: DatabaseConnection,
     _name: String,
+    // private message contents are encrypted at rest when set
+    crypto: Option<Crypto>,
 }
@@ -10,6 +10,8 @@ use sea_orm::{
     ActiveModelBehavior, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait,
 };
 
+use crate::crypto::SecretKey;
+
 #[derive(Clone, Debug, DeriveEntityModel)]
 #[sea_orm(table_name = "auth")]
 pub struct Model {
@@ -17,9 +19,28 @@ pub struct Model {
     pub id: i64,
     #[sea_orm(unique, column_name = "secretKey")]
     pub secret_key: String,
+    // set when a newer key replaces this one
+    #[sea_orm(column_name = "retiredTimestamp")]
+    pub retired_timestamp: Option<i64>,
 }
 
 #[derive(Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl From<Model> for SecretKey {
+    fn from(
+        Model {
+            id,
+            secret_key,
+            retired_timestamp,
+        }: Model,
+    ) -> Self {
+        Self {
+            id,
+            key: secret_key,
+            retired_timestamp,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sea_orm::entity::prelude::DeriveEntityModel;
+use sea_orm::{
+    ActiveModelBehavior, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, DeriveEntityModel, Serialize, Deserialize, PartialEq)]
+#[sea_orm(table_name = "bundle")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(column_name = "messageId")]
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+    // relative path inside the uploaded folder
+    pub path: String,
+    #[sea_orm(column_name = "fileName")]
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+}
+
+#[derive(Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BundleFileItem {
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+    pub path: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+}
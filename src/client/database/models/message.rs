@@ -32,6 +32,15 @@ pub struct Model {
     #[sea_orm(column_name = "isComplete")]
     #[serde(rename = "isComplete")]
     pub is_complete: Option<bool>,
+    #[sea_orm(column_name = "hasThumbnail")]
+    #[serde(rename = "hasThumbnail")]
+    pub has_thumbnail: Option<bool>,
+    // sha256 of the uploaded file, used to deduplicate later uploads
+    pub hash: Option<String>,
+    // the object is gone from storage, set by the consistency checker
+    #[sea_orm(column_name = "isMissing")]
+    #[serde(rename = "isMissing")]
+    pub is_missing: Option<bool>,
 }
 
 #[derive(Clone, Debug, EnumIter, DeriveRelation)]
@@ -45,6 +54,8 @@ pub enum MessageItemType {
     Text,
     #[serde(rename = "file")]
     File,
+    #[serde(rename = "bundle")]
+    Bundle,
 }
 
 impl MessageItemType {
@@ -52,13 +63,15 @@ impl MessageItemType {
         match self {
             Self::Text => self.to_str().to_string(),
             Self::File => self.to_str().to_string(),
+            Self::Bundle => self.to_str().to_string(),
         }
     }
 
-    fn to_str(&self) -> &str {
+    pub fn to_str(&self) -> &str {
         match self {
             Self::Text => "text",
             Self::File => "file",
+            Self::Bundle => "bundle",
         }
     }
 }
@@ -68,6 +81,7 @@ impl Into<sea_orm::Value> for MessageItemType {
         match self {
             Self::Text => sea_orm::Value::String(Some(Box::new(self.to_string()))),
             Self::File => sea_orm::Value::String(Some(Box::new(self.to_string()))),
+            Self::Bundle => sea_orm::Value::String(Some(Box::new(self.to_string()))),
         }
     }
 }
@@ -79,8 +93,9 @@ impl TryGetable for MessageItemType {
         match value.as_str() {
             "text" => Ok(MessageItemType::Text),
             "file" => Ok(MessageItemType::File),
+            "bundle" => Ok(MessageItemType::Bundle),
             _ => Err(TryGetError::DbErr(DbErr::Type(format!(
-                "message item type value should be one of text and file: {}",
+                "message item type value should be one of text, file and bundle: {}",
                 value
             )))),
         }
@@ -93,6 +108,7 @@ impl ValueType for MessageItemType {
             Value::String(Some(value)) => match value.as_str() {
                 "text" => Ok(MessageItemType::Text),
                 "file" => Ok(MessageItemType::File),
+                "bundle" => Ok(MessageItemType::Bundle),
                 _ => Err(ValueTypeErr),
             },
             _ => Err(ValueTypeErr),
@@ -154,4 +170,22 @@ impl MessageItem {
             is_complete: Some(is_complete),
         }
     }
+
+    // file_name of a bundle is the prefix shared by all of its files
+    pub fn new_bundle(
+        content: &str,
+        timestamp: i64,
+        is_private: bool,
+        file_name: &str,
+        is_complete: bool,
+    ) -> Self {
+        Self {
+            content: content.to_string(),
+            timestamp,
+            is_private,
+            type_field: MessageItemType::Bundle,
+            file_name: Some(file_name.to_string()),
+            is_complete: Some(is_complete),
+        }
+    }
 }
@@ -6,6 +6,7 @@
 */
 
 pub mod auth;
+pub mod bundle;
 pub mod device;
 pub mod message;
 pub mod token;
@@ -10,8 +10,11 @@ use sea_orm::{
     ActiveModelBehavior, DerivePrimaryKey, DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait,
 };
 use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
 
-#[derive(Clone, Debug, DeriveEntityModel, Serialize)]
+use crate::utils::REDACTED;
+
+#[derive(Clone, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "token")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -27,12 +30,25 @@ pub struct Model {
     pub expiration_timestamp: i64,
 }
 
+// tokens are credentials, only their names reach the log
+impl Debug for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Model")
+            .field("id", &self.id)
+            .field("token", &REDACTED)
+            .field("name", &self.name)
+            .field("last_use_timestamp", &self.last_use_timestamp)
+            .field("expiration_timestamp", &self.expiration_timestamp)
+            .finish()
+    }
+}
+
 #[derive(Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
 impl ActiveModelBehavior for ActiveModel {}
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct TokenItem {
     id: i64,
     pub token: String,
@@ -43,10 +59,32 @@ pub struct TokenItem {
     expiration_timestamp: i64,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+impl Debug for TokenItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenItem")
+            .field("id", &self.id)
+            .field("token", &REDACTED)
+            .field("name", &self.name)
+            .field("last_use_timestamp", &self.last_use_timestamp)
+            .field("expiration_timestamp", &self.expiration_timestamp)
+            .finish()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct TokenNewItem {
     pub token: String,
     pub name: String,
     #[serde(rename = "expirationTimestamp")]
     pub expiration_timestamp: i64,
 }
+
+impl Debug for TokenNewItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenNewItem")
+            .field("token", &REDACTED)
+            .field("name", &self.name)
+            .field("expiration_timestamp", &self.expiration_timestamp)
+            .finish()
+    }
+}
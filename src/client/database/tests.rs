@@ -7,11 +7,14 @@
 
 use strum::IntoEnumIterator;
 
+use super::models::bundle::BundleFileItem;
 use super::models::device::{self, DeviceItem};
 use super::models::message::{self, MessageItem};
 use super::models::token::{self, TokenNewItem};
 use super::Database;
 use crate::client::database::models::device::DeviceUpdateItem;
+use crate::crypto::tests::get_crypto;
+use crate::crypto::SecretKey;
 use crate::env::tests::{get_env, DBType, STType};
 use crate::env::Env;
 use crate::error::Result;
@@ -23,7 +26,7 @@ pub async fn get_database(db_type: DBType) -> Database {
         database: config, ..
     } = get_env(db_type, STType::LocalStorage);
 
-    Database::new(&config).await.unwrap()
+    Database::new(&config, None).await.unwrap()
 }
 
 pub async fn reset(database: Database) {
@@ -33,6 +36,7 @@ pub async fn reset(database: Database) {
 #[tokio::test]
 async fn test_database_new() {
     get_database(DBType::MySql).await;
+    get_database(DBType::Postgres).await;
     get_database(DBType::Sqlite).await;
 
     sleep_async(1).await;
@@ -40,12 +44,18 @@ async fn test_database_new() {
 
 #[tokio::test]
 async fn test_database_create_database_if_not_exists() {
-    let database = get_database(DBType::MySql).await;
+    async fn check(db_type: DBType) -> Result<()> {
+        let database = get_database(db_type).await;
+
+        let result =
+            Database::create_database_if_not_exists(&database.connection, &database._name).await;
+        reset(database).await;
 
-    let result =
-        Database::create_database_if_not_exists(&database.connection, &database._name).await;
-    reset(database).await;
-    result.unwrap();
+        result
+    }
+
+    check(DBType::MySql).await.unwrap();
+    check(DBType::Postgres).await.unwrap();
 
     sleep_async(1).await;
 }
@@ -140,6 +150,24 @@ async fn test_database_create_table_token_if_not_exists() {
     sleep_async(1).await;
 }
 
+#[tokio::test]
+async fn test_database_create_table_bundle_if_not_exists() {
+    async fn check(db_type: DBType) -> Result<()> {
+        let database = get_database(db_type).await;
+
+        let result = database.create_table_bundle_if_not_exists().await;
+        reset(database).await;
+
+        result
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await.unwrap();
+    }
+
+    sleep_async(1).await;
+}
+
 #[tokio::test]
 async fn test_database_create_secret_key_if_not_exists() {
     async fn inner(database: &Database) -> Result<()> {
@@ -201,12 +229,52 @@ async fn test_database_is_secret_key_exist() {
 }
 
 #[tokio::test]
-async fn test_database_get_secret_key() {
-    async fn inner(database: &Database) -> Result<String> {
+async fn test_database_get_secret_keys() {
+    async fn inner(database: &Database) -> Result<Vec<SecretKey>> {
+        database.create_table_auth_if_not_exists().await?;
+        database.create_secret_key_if_not_exists().await?;
+
+        database.get_secret_keys().await
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let secret_keys = result.unwrap();
+
+        assert_eq!(secret_keys.len(), 1);
+        assert_eq!(secret_keys[0].key.len(), 44);
+        assert_eq!(secret_keys[0].retired_timestamp, None);
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_rotate_secret_key() {
+    async fn rotate(database: &Database, grace_period: i64) -> Result<(i64, Vec<SecretKey>)> {
+        let id = database.rotate_secret_key(grace_period).await?;
+        let secret_keys = database.get_secret_keys().await?;
+
+        Ok((id, secret_keys))
+    }
+
+    #[allow(clippy::type_complexity)]
+    async fn inner(database: &Database) -> Result<((i64, Vec<SecretKey>), (i64, Vec<SecretKey>))> {
         database.create_table_auth_if_not_exists().await?;
         database.create_secret_key_if_not_exists().await?;
 
-        database.get_secret_key().await
+        let result_expired = rotate(database, 0).await?;
+        let result_grace = rotate(database, 1000 * 3600).await?;
+
+        Ok((result_expired, result_grace))
     }
 
     async fn check(db_type: DBType) {
@@ -215,7 +283,17 @@ async fn test_database_get_secret_key() {
         let result = inner(&database).await;
         reset(database).await;
 
-        assert_eq!(result.unwrap().len(), 44);
+        let ((id, secret_keys), (new_id, new_secret_keys)) = result.unwrap();
+
+        assert_eq!(secret_keys.len(), 1);
+        assert_eq!(secret_keys[0].id, id);
+
+        // the old key still decrypts during the grace period
+        assert_eq!(new_secret_keys.len(), 2);
+        assert_eq!(new_secret_keys[0].id, id);
+        assert!(new_secret_keys[0].retired_timestamp.is_some());
+        assert_eq!(new_secret_keys[1].id, new_id);
+        assert_eq!(new_secret_keys[1].retired_timestamp, None);
     }
 
     for db_type in DBType::iter() {
@@ -423,6 +501,221 @@ async fn test_database_query_message_items_after_id() {
     sleep_async(1).await;
 }
 
+#[tokio::test]
+async fn test_database_query_message_files_by_ids() {
+    async fn inner(database: &Database) -> Result<(Vec<message::Model>, Vec<message::Model>)> {
+        let timestamp = get_current_timestamp();
+
+        let item_text = MessageItem::new_text("test_query_files_by_ids.txt", timestamp, false);
+        let item_public = MessageItem::new_file(
+            "test_query_files_by_ids_public.txt",
+            timestamp,
+            false,
+            "test_query_files_by_ids_public.txt",
+            true,
+        );
+        let item_private = MessageItem::new_file(
+            "test_query_files_by_ids_private.txt",
+            timestamp,
+            true,
+            "test_query_files_by_ids_private.txt",
+            true,
+        );
+        let item_incomplete = MessageItem::new_file(
+            "test_query_files_by_ids_incomplete.txt",
+            timestamp,
+            false,
+            "test_query_files_by_ids_incomplete.txt",
+            false,
+        );
+
+        database.create_table_message_if_not_exists().await?;
+
+        let mut ids = Vec::new();
+
+        for item in [item_text, item_public, item_private, item_incomplete] {
+            ids.push(database.insert_message_item(item).await?);
+        }
+
+        let result_unauthorized = database
+            .query_message_files_by_ids(ids.clone(), false)
+            .await?;
+        let result_authorized = database.query_message_files_by_ids(ids, true).await?;
+
+        Ok((result_unauthorized, result_authorized))
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let (result_unauthorized, result_authorized) = result.unwrap();
+        assert_eq!(result_unauthorized.len(), 1);
+        assert_eq!(
+            result_unauthorized.first().unwrap().content,
+            "test_query_files_by_ids_public.txt"
+        );
+        assert_eq!(result_authorized.len(), 2);
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_query_message_file_by_object_name() {
+    async fn inner(database: &Database) -> Result<Vec<Option<i64>>> {
+        let timestamp = get_current_timestamp();
+
+        database.create_table_message_if_not_exists().await?;
+
+        for file_name in [
+            "a/test_by_object_name.txt",
+            "b/test_by_object_name.txt",
+            "c/xtest_by_object_name.txt",
+            "test_by_object_name_old.txt",
+            "d/test_by_object_name_100%.txt",
+        ] {
+            let item = MessageItem::new_file(file_name, timestamp, false, file_name, true);
+            database.insert_message_item(item).await?;
+        }
+
+        let mut ids = Vec::new();
+
+        for name in [
+            "test_by_object_name.txt",
+            "xtest_by_object_name.txt",
+            "test_by_object_name_old.txt",
+            "test_by_object_name_100%.txt",
+            "test_by_object_name_1%.txt",
+            "est_by_object_name.txt",
+        ] {
+            let item = database.query_message_file_by_object_name(name).await?;
+            ids.push(item.map(|item| item.id));
+        }
+
+        Ok(ids)
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        // the oldest one wins, and wildcards are matched literally
+        assert_eq!(
+            result.unwrap(),
+            vec![Some(1), Some(3), Some(4), Some(5), None, None]
+        );
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_query_message_files_by_timestamp() {
+    async fn inner(database: &Database) -> Result<Vec<message::Model>> {
+        let item_early = MessageItem::new_file(
+            "test_query_files_by_timestamp_early.txt",
+            100,
+            false,
+            "test_query_files_by_timestamp_early.txt",
+            true,
+        );
+        let item_in_range = MessageItem::new_file(
+            "test_query_files_by_timestamp_in_range.txt",
+            200,
+            false,
+            "test_query_files_by_timestamp_in_range.txt",
+            true,
+        );
+        let item_late = MessageItem::new_file(
+            "test_query_files_by_timestamp_late.txt",
+            300,
+            false,
+            "test_query_files_by_timestamp_late.txt",
+            true,
+        );
+
+        database.create_table_message_if_not_exists().await?;
+        database.insert_message_item(item_early).await?;
+        database.insert_message_item(item_in_range).await?;
+        database.insert_message_item(item_late).await?;
+        database
+            .query_message_files_by_timestamp(150, 250, false)
+            .await
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let result = result.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result.first().unwrap().content,
+            "test_query_files_by_timestamp_in_range.txt"
+        );
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_query_message_items_before() {
+    async fn inner(database: &Database) -> Result<Vec<message::Model>> {
+        let item_early = MessageItem::new_text("test_query_items_before_early", 100, false);
+        let item_file = MessageItem::new_file(
+            "test_query_items_before_file.txt",
+            150,
+            true,
+            "test_query_items_before_file.txt",
+            true,
+        );
+        let item_late = MessageItem::new_text("test_query_items_before_late", 300, false);
+
+        database.create_table_message_if_not_exists().await?;
+        database.insert_message_item(item_late).await?;
+        database.insert_message_item(item_file).await?;
+        database.insert_message_item(item_early).await?;
+        database.query_message_items_before(200).await
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let result = result.unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].content, "test_query_items_before_early");
+        assert_eq!(result[1].content, "test_query_items_before_file.txt");
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
 #[tokio::test]
 async fn test_database_query_message_latest() {
     async fn inner(database: &Database, item: MessageItem) -> Result<Option<message::Model>> {
@@ -481,6 +774,103 @@ async fn test_database_update_complete() {
     sleep_async(1).await;
 }
 
+#[tokio::test]
+async fn test_database_update_thumbnail() {
+    async fn inner(database: &Database) -> Result<Vec<message::Model>> {
+        let content = "test_database_update_thumbnail.png";
+        let item = MessageItem::new_file(content, get_current_timestamp(), false, content, true);
+
+        database.create_table_message_if_not_exists().await?;
+        let id = database.insert_message_item(item).await?;
+        database.update_thumbnail(id).await?;
+        database.query_message_items(0, 1, false).await
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        assert_eq!(result.unwrap().first().unwrap().has_thumbnail, Some(true));
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_update_hash() {
+    async fn inner(database: &Database) -> Result<(Option<message::Model>, u64)> {
+        let content = "test_database_update_hash.txt";
+        let hash = "7509e5bda0c762d2bac7f90d758b5b2263fa01ccbc542ab5e3df163be08e6ca9";
+
+        let item_1 = MessageItem::new_file(content, get_current_timestamp(), false, content, true);
+        let item_2 = MessageItem::new_file(content, get_current_timestamp(), false, content, true);
+
+        database.create_table_message_if_not_exists().await?;
+        let id = database.insert_message_item(item_1).await?;
+        database.insert_message_item(item_2).await?;
+        database.update_hash(id, hash).await?;
+
+        let item = database
+            .insert_message_file_by_hash(hash, content, get_current_timestamp(), false)
+            .await?;
+        let count = database.count_message_file_references(content).await?;
+
+        Ok((item, count))
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let (item, count) = result.unwrap();
+        let item = item.unwrap();
+        assert_eq!(item.id, 3);
+        assert_eq!(
+            item.file_name.as_deref(),
+            Some("test_database_update_hash.txt")
+        );
+        assert_eq!(count, 3);
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_migrate_table_message() {
+    async fn inner(database: &Database) -> Result<()> {
+        database.create_table_message_if_not_exists().await?;
+        // running twice should be a no-op
+        database.migrate_table_message().await?;
+        database.migrate_table_message().await
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+        result.unwrap();
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
 #[tokio::test]
 async fn test_database_insert_device() {
     async fn inner(database: &Database) -> Result<()> {
@@ -678,6 +1068,97 @@ async fn test_database_update_token() {
     sleep_async(1).await;
 }
 
+#[tokio::test]
+async fn test_database_count_token_items() {
+    async fn inner(database: &Database) -> Result<(u64, u64, Option<i64>, Option<i64>)> {
+        database.create_table_token_if_not_exists().await?;
+
+        let empty_timestamp = database.query_token_last_use_timestamp().await?;
+
+        let timestamp = get_current_timestamp();
+
+        for (token, expiration_timestamp) in [
+            ("test_token_active", timestamp + 1000 * 60),
+            ("test_token_active_other", timestamp + 1000 * 60),
+            ("test_token_expired", timestamp - 1000 * 60),
+        ] {
+            database
+                .insert_token(TokenNewItem {
+                    token: token.to_string(),
+                    name: "test name".to_string(),
+                    expiration_timestamp,
+                })
+                .await?;
+        }
+
+        database
+            .update_token("test_token_expired", timestamp + 1000)
+            .await?;
+
+        Ok((
+            database.count_token_items(true).await?,
+            database.count_token_items(false).await?,
+            empty_timestamp,
+            database.query_token_last_use_timestamp().await?,
+        ))
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let timestamp = get_current_timestamp();
+
+        let (active, expired, empty_timestamp, last_use_timestamp) = result.unwrap();
+        assert_eq!(active, 2);
+        assert_eq!(expired, 1);
+        assert_eq!(empty_timestamp, None);
+        assert!(last_use_timestamp.unwrap() > timestamp);
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_query_token_item() {
+    async fn inner(database: &Database) -> Result<(Option<token::Model>, Option<token::Model>)> {
+        database.create_table_token_if_not_exists().await?;
+        database
+            .insert_token(TokenNewItem {
+                token: "test_token".to_string(),
+                name: "test name".to_string(),
+                expiration_timestamp: get_current_timestamp(),
+            })
+            .await?;
+
+        Ok((
+            database.query_token_item("test_token").await?,
+            database.query_token_item("missing_token").await?,
+        ))
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let (token_item, missing_item) = result.unwrap();
+        assert_eq!(token_item.unwrap().name, "test name");
+        assert!(missing_item.is_none());
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
 #[tokio::test]
 async fn test_database_query_token_items() {
     async fn inner(database: &Database, new_token_item: TokenNewItem) -> Result<Vec<token::Model>> {
@@ -740,3 +1221,114 @@ async fn test_database_remove_token() {
 
     sleep_async(1).await;
 }
+
+#[tokio::test]
+async fn test_database_insert_bundle_file() {
+    async fn inner(database: &Database) -> Result<(usize, usize)> {
+        let prefix = "test_database_insert_bundle_file";
+
+        database.create_table_bundle_if_not_exists().await?;
+
+        for path in ["b.txt", "a/a.txt"] {
+            database
+                .insert_bundle_file(BundleFileItem {
+                    message_id: 1,
+                    path: path.to_string(),
+                    file_name: format!("{}/{}", prefix, path),
+                })
+                .await?;
+        }
+
+        let files = database.query_bundle_files(1).await?;
+
+        assert_eq!(files.first().unwrap().path, "a/a.txt");
+        assert_eq!(
+            files.first().unwrap().file_name,
+            "test_database_insert_bundle_file/a/a.txt"
+        );
+
+        database.remove_bundle_files(1).await?;
+
+        let files_removed = database.query_bundle_files(1).await?;
+
+        Ok((files.len(), files_removed.len()))
+    }
+
+    async fn check(db_type: DBType) {
+        let database = get_database(db_type).await;
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        assert_eq!(result.unwrap(), (2, 0));
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_database_encrypt_private_content() {
+    async fn inner(database: &Database) -> Result<Vec<String>> {
+        let content = "test_database_encrypt_private_content";
+        let timestamp = get_current_timestamp();
+
+        let database_plain = Database {
+            crypto: None,
+            ..database.clone()
+        };
+
+        database.create_table_message_if_not_exists().await?;
+        let private_id = database
+            .insert_message_item(MessageItem::new_text(content, timestamp, true))
+            .await?;
+        let public_id = database
+            .insert_message_item(MessageItem::new_text(content, timestamp, false))
+            .await?;
+        // typed by the user, not encrypted
+        let typed_id = database_plain
+            .insert_message_item(MessageItem::new_text("encrypted:abc", timestamp, true))
+            .await?;
+
+        let mut contents = Vec::new();
+
+        for id in [private_id, public_id, typed_id] {
+            contents.push(database.query_message_item(id).await?.unwrap().content);
+        }
+
+        contents.push(
+            database_plain
+                .query_message_item(private_id)
+                .await?
+                .unwrap()
+                .content,
+        );
+
+        Ok(contents)
+    }
+
+    async fn check(db_type: DBType) {
+        let database = Database {
+            crypto: Some(get_crypto()),
+            ..get_database(db_type).await
+        };
+
+        let result = inner(&database).await;
+        reset(database).await;
+
+        let contents = result.unwrap();
+        assert_eq!(contents[0], "test_database_encrypt_private_content");
+        assert_eq!(contents[1], "test_database_encrypt_private_content");
+        assert_eq!(contents[2], "encrypted:abc");
+        assert!(contents[3].starts_with("encrypted:"));
+    }
+
+    for db_type in DBType::iter() {
+        check(db_type).await;
+    }
+
+    sleep_async(1).await;
+}
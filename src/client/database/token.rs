@@ -6,7 +6,7 @@
 */
 
 use sea_orm::sea_query::Expr;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect, Set};
 
 use super::models::token::{self, TokenNewItem};
 use super::Database;
@@ -48,6 +48,63 @@ impl Database {
         Ok(token_items)
     }
 
+    // counted by the database, so that scrapes don't load every token
+    pub async fn count_token_items(&self, is_active: bool) -> Result<u64> {
+        let current_timestamp = get_current_timestamp();
+
+        let condition = match is_active {
+            true => token::Column::ExpirationTimestamp.gt(current_timestamp),
+            false => token::Column::ExpirationTimestamp.lte(current_timestamp),
+        };
+
+        let count = token::Entity::find()
+            .filter(condition)
+            .count(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to count token items"))?;
+
+        Ok(count)
+    }
+
+    pub async fn query_token_last_use_timestamp(&self) -> Result<Option<i64>> {
+        let timestamp = token::Entity::find()
+            .select_only()
+            .column_as(token::Column::LastUseTimestamp.max(), "last_use_timestamp")
+            .into_tuple::<Option<i64>>()
+            .one(&self.connection)
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to query token last use timestamp",
+                )
+            })?;
+
+        Ok(timestamp.flatten())
+    }
+
+    pub async fn query_token_item(&self, token: &str) -> Result<Option<token::Model>> {
+        let token_item = token::Entity::find()
+            .filter(token::Column::Token.eq(token))
+            .one(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to query token item"))?;
+
+        Ok(token_item)
+    }
+
+    // revoked tokens are removed from the table
+    pub async fn is_token_exist(&self, token: &str) -> Result<bool> {
+        let count = token::Entity::find()
+            .filter(token::Column::Token.eq(token))
+            .count(&self.connection)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to count token"))?;
+
+        Ok(count > 0)
+    }
+
     pub async fn remove_token(&self, token: String) -> Result<()> {
         token::Entity::delete_many()
             .filter(token::Column::Token.eq(token))
@@ -11,12 +11,23 @@ pub mod storage;
 pub use database::Database;
 pub use storage::Storage;
 
+use crate::crypto::Crypto;
 use crate::env::Env;
 
 pub async fn get_storage(env: &Env) -> Storage {
-    Storage::new(&env.storage).await.unwrap()
+    Storage::new(&env.storage, get_encryption_crypto(env))
+        .await
+        .unwrap()
 }
 
 pub async fn get_database(env: &Env) -> Database {
-    Database::new(&env.database).await.unwrap()
+    Database::new(&env.database, get_encryption_crypto(env))
+        .await
+        .unwrap()
+}
+
+fn get_encryption_crypto(env: &Env) -> Option<Crypto> {
+    env.encryption_key
+        .as_ref()
+        .map(|key| Crypto::new(key).unwrap())
 }
@@ -0,0 +1,121 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use async_zip::base::write::ZipFileWriter;
+use async_zip::{Compression, ZipDateTime, ZipDateTimeBuilder, ZipEntryBuilder};
+use axum::body::Body;
+use axum::http::{header, StatusCode};
+use axum::response::Response;
+use chrono::{DateTime, Datelike, Timelike};
+use tokio::io::{duplex, AsyncWrite};
+use tokio::sync::oneshot;
+use tokio_util::compat::FuturesAsyncWriteCompatExt;
+use tokio_util::io::ReaderStream;
+
+use super::models::{ArchiveEntry, Disposition};
+use super::utils::ResultReader;
+use super::Storage;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+// the archive is written while it's read, this only bounds the memory in between
+const ARCHIVE_BUFFER_SIZE: usize = 1024 * 1024; // 1 MB
+
+impl Storage {
+    pub fn get_archive_response(
+        &self,
+        archive_name: &str,
+        entries: Vec<ArchiveEntry>,
+    ) -> Result<Response> {
+        let (writer, reader) = duplex(ARCHIVE_BUFFER_SIZE);
+
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let storage = self.clone();
+
+        tokio::spawn(async move {
+            let result = storage.write_archive(writer, entries).await;
+
+            if let Err(e) = &result {
+                tracing::error!("failed to write archive: {}", e);
+            }
+
+            result_sender.send(result).ok();
+        });
+
+        let stream = ReaderStream::new(ResultReader::new(reader, result_receiver));
+
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/zip")
+            .header(
+                header::CONTENT_DISPOSITION,
+                Disposition::Attachment.header_value(archive_name),
+            )
+            .body(Body::from_stream(stream))
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to build response for archive",
+                )
+            })
+    }
+
+    async fn write_archive<W>(&self, writer: W, entries: Vec<ArchiveEntry>) -> Result<()>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut zip_writer = ZipFileWriter::with_tokio(writer);
+
+        for ArchiveEntry {
+            name,
+            object,
+            timestamp,
+        } in entries
+        {
+            // most uploads are already compressed, so entries are stored as is
+            let entry = ZipEntryBuilder::new(name.into(), Compression::Stored)
+                .last_modification_date(to_zip_date_time(timestamp))
+                .unix_permissions(0o644);
+
+            let mut entry_writer = zip_writer
+                .write_entry_stream(entry)
+                .await
+                .map_err(|e| {
+                    Error::context(InternalServerError, e, "failed to create archive entry")
+                })?
+                .compat_write();
+
+            self.copy_object(&object, &mut entry_writer).await?;
+
+            entry_writer.into_inner().close().await.map_err(|e| {
+                Error::context(InternalServerError, e, "failed to close archive entry")
+            })?;
+        }
+
+        zip_writer
+            .close()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to close archive"))?;
+
+        Ok(())
+    }
+}
+
+fn to_zip_date_time(timestamp: i64) -> ZipDateTime {
+    let date_time = DateTime::from_timestamp_millis(timestamp).unwrap_or_default();
+
+    ZipDateTimeBuilder::new()
+        .year(date_time.year())
+        .month(date_time.month())
+        .day(date_time.day())
+        .hour(date_time.hour())
+        .minute(date_time.minute())
+        .second(date_time.second())
+        .build()
+}
@@ -0,0 +1,94 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::async_trait;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use tokio::io::AsyncWrite;
+
+use super::models::Part;
+use crate::error::Result;
+
+// everything a storage has to provide, objects are stored as they're given
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn init(&self) -> Result<()>;
+
+    // fails if the storage can't be reached or written
+    async fn check(&self) -> Result<()>;
+
+    async fn create_multipart_upload_id(&self, object: &str) -> Result<String>;
+
+    async fn multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        part_data: &[u8],
+        part_number: u16,
+    ) -> Result<Part>;
+
+    async fn complete_multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<()>;
+
+    // drops the uploaded parts, the object is never created
+    async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()>;
+
+    async fn put_object(&self, object: &str, data: &[u8]) -> Result<()>;
+
+    async fn get_download_response(&self, object: &str, headers: &HeaderMap) -> Result<Response>;
+
+    async fn get_preview_response(&self, object: &str, headers: &HeaderMap) -> Result<Response>;
+
+    async fn get_thumbnail_response(
+        &self,
+        thumbnail_name: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response>;
+
+    async fn get_object(&self, object: &str) -> Result<Vec<u8>>;
+
+    // returns the number of bytes written
+    async fn copy_object(
+        &self,
+        object: &str,
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<u64>;
+
+    async fn is_object_exists(&self, object: &str) -> Result<bool>;
+
+    // size in bytes as stored, None if the object doesn't exist
+    async fn get_object_size(&self, object: &str) -> Result<Option<u64>>;
+
+    async fn list_object_names(&self) -> Result<Vec<String>>;
+
+    // names with their sizes in bytes as stored
+    async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>>;
+
+    // total size of the stored objects in bytes
+    async fn get_usage(&self) -> Result<u64>;
+
+    async fn remove_object(&self, object: &str) -> Result<()>;
+
+    async fn remove_object_if_exists(&self, object: &str) -> Result<()> {
+        if self.is_object_exists(object).await? {
+            self.remove_object(object).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> Result<()>;
+
+    async fn remove_objects_all(&self) -> Result<()>;
+
+    // removes the storage itself, only used to reset tests
+    async fn _destroy(&self) -> Result<()>;
+}
@@ -0,0 +1,439 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::Response;
+use std::borrow::Cow;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{duplex, AsyncWrite, AsyncWriteExt};
+use tokio::sync::oneshot;
+use tokio_util::io::ReaderStream;
+
+use super::models::Disposition;
+use super::thumbnail::THUMBNAIL_CACHE_CONTROL;
+use super::utils::{get_preview_content_security_policy, guess_mime_type, ResultReader};
+use super::Storage;
+use crate::crypto::{Crypto, SEAL_OVERHEAD};
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+// every frame starts with it, objects stored before encryption was enabled don't
+const FRAME_MAGIC: &[u8; 4] = b"TFE1";
+// magic, part number, chunk index, last flag, sealed length and object size
+const FRAME_HEADER_SIZE: usize = 23;
+const CHUNK_SIZE: usize = 64 * 1024; // 64 KB
+
+// decrypted objects are written while they're read, this only bounds the memory in between
+const DECRYPT_BUFFER_SIZE: usize = 1024 * 1024; // 1 MB
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FrameHeader {
+    part_number: u16,
+    index: u32,
+    is_last: bool,
+    sealed_len: u32,
+    // parts are encrypted on their own, this is what tells a missing trailing part apart
+    object_size: u64,
+}
+
+impl FrameHeader {
+    fn to_bytes(self) -> [u8; FRAME_HEADER_SIZE] {
+        let mut bytes = [0u8; FRAME_HEADER_SIZE];
+
+        bytes[..4].copy_from_slice(FRAME_MAGIC);
+        bytes[4..6].copy_from_slice(&self.part_number.to_be_bytes());
+        bytes[6..10].copy_from_slice(&self.index.to_be_bytes());
+        bytes[10] = self.is_last as u8;
+        bytes[11..15].copy_from_slice(&self.sealed_len.to_be_bytes());
+        bytes[15..23].copy_from_slice(&self.object_size.to_be_bytes());
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < FRAME_HEADER_SIZE || &bytes[..4] != FRAME_MAGIC {
+            return Err(Error::new(InternalServerError, "invalid encrypted frame"));
+        }
+
+        Ok(Self {
+            part_number: u16::from_be_bytes([bytes[4], bytes[5]]),
+            index: u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+            is_last: bytes[10] == 1,
+            sealed_len: u32::from_be_bytes([bytes[11], bytes[12], bytes[13], bytes[14]]),
+            object_size: u64::from_be_bytes([
+                bytes[15], bytes[16], bytes[17], bytes[18], bytes[19], bytes[20], bytes[21],
+                bytes[22],
+            ]),
+        })
+    }
+
+    // frames must keep their order, and a part may only follow the last frame of another
+    fn is_next_of(&self, previous: Option<&FrameHeader>) -> bool {
+        match previous {
+            None => self.index == 0,
+            Some(previous) if previous.object_size != self.object_size => false,
+            Some(previous) if previous.is_last => {
+                self.part_number > previous.part_number && self.index == 0
+            }
+            Some(previous) => {
+                self.part_number == previous.part_number && self.index == previous.index + 1
+            }
+        }
+    }
+}
+
+// the frame is bound to its object, so frames can't be swapped between objects
+fn gen_aad(header: &[u8], object: &str) -> Vec<u8> {
+    let mut aad = header.to_vec();
+    aad.extend_from_slice(object.as_bytes());
+
+    aad
+}
+
+pub fn encrypt_part(
+    crypto: &Crypto,
+    object: &str,
+    object_size: u64,
+    part_number: u16,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    // an empty part still needs a last frame
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(CHUNK_SIZE).collect()
+    };
+
+    let chunk_count = chunks.len();
+
+    let mut output =
+        Vec::with_capacity(data.len() + chunk_count * (FRAME_HEADER_SIZE + SEAL_OVERHEAD));
+
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let header = FrameHeader {
+            part_number,
+            index: index as u32,
+            is_last: index + 1 == chunk_count,
+            sealed_len: (chunk.len() + SEAL_OVERHEAD) as u32,
+            object_size,
+        }
+        .to_bytes();
+
+        let sealed = crypto.encrypt_bytes(chunk, &gen_aad(&header, object))?;
+
+        output.extend_from_slice(&header);
+        output.extend_from_slice(&sealed);
+    }
+
+    Ok(output)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DecryptState {
+    Unknown,
+    Plain,
+    Encrypted,
+}
+
+// decrypts frames written into it, objects without frames are passed through as they are
+pub struct DecryptWriter<'a, W> {
+    crypto: &'a Crypto,
+    object: &'a str,
+    inner: W,
+    state: DecryptState,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+    previous: Option<FrameHeader>,
+    size: u64,
+}
+
+impl<'a, W> DecryptWriter<'a, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(crypto: &'a Crypto, object: &'a str, inner: W) -> Self {
+        Self {
+            crypto,
+            object,
+            inner,
+            state: DecryptState::Unknown,
+            input: Vec::new(),
+            output: Vec::new(),
+            position: 0,
+            previous: None,
+            size: 0,
+        }
+    }
+
+    // returns the size of the decrypted object
+    pub async fn finish(mut self) -> Result<u64> {
+        if self.state == DecryptState::Unknown {
+            self.state = DecryptState::Plain;
+        }
+
+        self.process()?;
+
+        // the size is only known to be complete once every part has been read
+        if self.state == DecryptState::Encrypted
+            && (!self.input.is_empty()
+                || !self
+                    .previous
+                    .is_some_and(|frame| frame.is_last && frame.object_size == self.size))
+        {
+            return Err(Error::new(
+                InternalServerError,
+                format!("encrypted object {} is truncated", self.object),
+            ));
+        }
+
+        self.inner
+            .write_all(&self.output[self.position..])
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to write decrypted data")
+            })?;
+
+        self.inner.flush().await.map_err(|e| {
+            Error::context(InternalServerError, e, "failed to flush decrypted data")
+        })?;
+
+        Ok(self.size)
+    }
+
+    fn process(&mut self) -> Result<()> {
+        if self.state == DecryptState::Unknown {
+            if self.input.len() < FRAME_MAGIC.len() {
+                return Ok(());
+            }
+
+            self.state = if self.input.starts_with(FRAME_MAGIC) {
+                DecryptState::Encrypted
+            } else {
+                DecryptState::Plain
+            };
+        }
+
+        if self.state == DecryptState::Plain {
+            self.size += self.input.len() as u64;
+            self.output.append(&mut self.input);
+
+            return Ok(());
+        }
+
+        while self.input.len() >= FRAME_HEADER_SIZE {
+            let header = FrameHeader::from_bytes(&self.input)?;
+
+            let frame_size = FRAME_HEADER_SIZE + header.sealed_len as usize;
+
+            if self.input.len() < frame_size {
+                break;
+            }
+
+            if !header.is_next_of(self.previous.as_ref()) {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("encrypted object {} has frames out of order", self.object),
+                ));
+            }
+
+            let data = self.crypto.decrypt_bytes(
+                &self.input[FRAME_HEADER_SIZE..frame_size],
+                &gen_aad(&self.input[..FRAME_HEADER_SIZE], self.object),
+            )?;
+
+            self.size += data.len() as u64;
+
+            if self.size > header.object_size {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("encrypted object {} is larger than its size", self.object),
+                ));
+            }
+            self.output.extend_from_slice(&data);
+            self.input.drain(..frame_size);
+            self.previous = Some(header);
+        }
+
+        Ok(())
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.position < self.output.len() {
+            let written =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.output[self.position..]))?;
+
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+
+            self.position += written;
+        }
+
+        self.output.clear();
+        self.position = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'a, W> AsyncWrite for DecryptWriter<'a, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_drain(cx))?;
+
+        this.input.extend_from_slice(buf);
+
+        this.process()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_drain(cx))?;
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_drain(cx))?;
+
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+impl Storage {
+    pub(super) fn encrypt_part<'a>(
+        &self,
+        object: &str,
+        object_size: Option<u64>,
+        part_number: u16,
+        data: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>> {
+        match &self.crypto {
+            Some(crypto) => {
+                let object_size = object_size.ok_or_else(|| {
+                    Error::new(
+                        InternalServerError,
+                        "file size is required when encryption is enabled",
+                    )
+                })?;
+
+                Ok(Cow::Owned(encrypt_part(
+                    crypto,
+                    object,
+                    object_size,
+                    part_number,
+                    data,
+                )?))
+            }
+            None => Ok(Cow::Borrowed(data)),
+        }
+    }
+
+    // ranges can't be served from frames, the whole object is always sent
+    pub(super) async fn get_decrypted_response(
+        &self,
+        object: &str,
+        disposition: Disposition,
+    ) -> Result<Response> {
+        if !self.is_object_exists(object).await? {
+            return Err(Error::new(
+                InternalServerError,
+                format!("file {} not found", object),
+            ));
+        }
+
+        let (mut writer, reader) = duplex(DECRYPT_BUFFER_SIZE);
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let storage = self.clone();
+        let object_owned = object.to_string();
+
+        tokio::spawn(async move {
+            // a truncated or tampered object is only noticed once it's been partly sent
+            let result = storage
+                .copy_object(&object_owned, &mut writer)
+                .await
+                .map(|_| ());
+
+            if let Err(e) = &result {
+                tracing::error!("failed to write decrypted object: {}", e);
+            }
+
+            // the reader waits for the result once the writer is dropped
+            drop(writer);
+            result_sender.send(result).ok();
+        });
+
+        let response = match disposition {
+            Disposition::Attachment => {
+                Response::builder().header(header::CONTENT_TYPE, "application/octet-stream")
+            }
+            Disposition::Inline => {
+                // objects are decrypted while they're sent, so only the extension is used for detection
+                let content_type = guess_mime_type(object, None);
+                let content_security_policy = get_preview_content_security_policy(&content_type);
+
+                Response::builder()
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::CONTENT_SECURITY_POLICY, content_security_policy)
+                    .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            }
+        };
+
+        response
+            .status(StatusCode::OK)
+            .header(
+                header::CONTENT_DISPOSITION,
+                disposition.header_value(object),
+            )
+            .body(Body::from_stream(ReaderStream::new(ResultReader::new(
+                reader,
+                result_receiver,
+            ))))
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to build response for decrypted object",
+                )
+            })
+    }
+
+    pub(super) async fn get_decrypted_thumbnail_response(
+        &self,
+        thumbnail_name: &str,
+    ) -> Result<Response> {
+        let mut response = self
+            .get_decrypted_response(thumbnail_name, Disposition::Inline)
+            .await?;
+
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(THUMBNAIL_CACHE_CONTROL),
+        );
+
+        Ok(response)
+    }
+}
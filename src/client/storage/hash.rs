@@ -0,0 +1,57 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use sha2::{Digest, Sha256};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::AsyncWrite;
+
+use super::Storage;
+use crate::error::Result;
+
+impl Storage {
+    // hex encoded sha256, computed on the stored object so clients can't forge it
+    pub async fn hash_object(&self, object: &str) -> Result<String> {
+        let mut writer = HashWriter::default();
+
+        self.copy_object(object, &mut writer).await?;
+
+        Ok(writer.finalize())
+    }
+}
+
+#[derive(Default)]
+struct HashWriter {
+    hasher: Sha256,
+}
+
+impl HashWriter {
+    fn finalize(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl AsyncWrite for HashWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.hasher.update(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,127 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::async_trait;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use tokio::io::AsyncWrite;
+
+use super::super::backend::StorageBackend;
+use super::super::models::Part;
+use super::utils::LocalStorageUtils;
+use super::LocalStorage;
+use crate::error::Result;
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn init(&self) -> Result<()> {
+        LocalStorage::init(self).await
+    }
+
+    async fn check(&self) -> Result<()> {
+        LocalStorage::check(self).await
+    }
+
+    async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
+        LocalStorage::create_multipart_upload_id(self, object).await
+    }
+
+    async fn multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        part_data: &[u8],
+        part_number: u16,
+    ) -> Result<Part> {
+        LocalStorage::multipart_upload(self, object, upload_id, part_data, part_number).await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<()> {
+        LocalStorage::complete_multipart_upload(self, object, upload_id, parts).await
+    }
+
+    async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()> {
+        LocalStorage::abort_multipart_upload(self, object, upload_id).await
+    }
+
+    async fn put_object(&self, object: &str, data: &[u8]) -> Result<()> {
+        LocalStorage::put_object(self, object, data).await
+    }
+
+    async fn get_download_response(&self, object: &str, headers: &HeaderMap) -> Result<Response> {
+        LocalStorage::get_download_response(self, object, headers).await
+    }
+
+    async fn get_preview_response(&self, object: &str, headers: &HeaderMap) -> Result<Response> {
+        LocalStorage::get_preview_response(self, object, headers).await
+    }
+
+    async fn get_thumbnail_response(
+        &self,
+        thumbnail_name: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        LocalStorage::get_thumbnail_response(self, thumbnail_name, headers).await
+    }
+
+    async fn get_object(&self, object: &str) -> Result<Vec<u8>> {
+        LocalStorage::get_object(self, object).await
+    }
+
+    async fn copy_object(
+        &self,
+        object: &str,
+        mut writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<u64> {
+        LocalStorage::copy_object(self, object, &mut writer).await
+    }
+
+    async fn is_object_exists(&self, object: &str) -> Result<bool> {
+        Ok(LocalStorage::is_object_exists(self, object))
+    }
+
+    async fn get_object_size(&self, object: &str) -> Result<Option<u64>> {
+        LocalStorage::get_object_size(self, object).await
+    }
+
+    async fn list_object_names(&self) -> Result<Vec<String>> {
+        LocalStorage::list_object_names(self).await
+    }
+
+    async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        LocalStorage::list_object_sizes(self).await
+    }
+
+    async fn get_usage(&self) -> Result<u64> {
+        LocalStorage::get_usage(self).await
+    }
+
+    async fn remove_object(&self, object: &str) -> Result<()> {
+        LocalStorage::remove_object(self, object).await
+    }
+
+    async fn remove_object_if_exists(&self, object: &str) -> Result<()> {
+        LocalStorage::remove_object_if_exists(self, object).await
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        LocalStorage::remove_prefix(self, prefix).await
+    }
+
+    async fn remove_objects_all(&self) -> Result<()> {
+        LocalStorage::remove_objects_all(self).await
+    }
+
+    async fn _destroy(&self) -> Result<()> {
+        self.remove_dir().await
+    }
+}
@@ -6,18 +6,121 @@
 */
 
 use axum::body::Body;
-use axum::http::{header, StatusCode};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::response::Response;
+use httpdate::HttpDate;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs;
+use tokio::io::{self, AsyncReadExt, AsyncSeekExt, AsyncWrite};
 use tokio_util::io::ReaderStream;
 
+use super::super::models::Disposition;
+use super::super::thumbnail::THUMBNAIL_CACHE_CONTROL;
+use super::super::utils::{get_preview_content_security_policy, guess_mime_type, MIME_SNIFF_SIZE};
+use super::models::RangeRequest;
 use super::utils::LocalStorageUtils;
 use super::LocalStorage;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
 
 impl LocalStorage {
-    pub async fn get_download_response(&self, file_name: &str) -> Result<Response> {
+    pub async fn get_download_response(
+        &self,
+        file_name: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        self.get_file_response(file_name, headers, Disposition::Attachment)
+            .await
+    }
+
+    pub async fn get_preview_response(
+        &self,
+        file_name: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        self.get_file_response(file_name, headers, Disposition::Inline)
+            .await
+    }
+
+    pub async fn get_thumbnail_response(
+        &self,
+        file_name: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        let mut response = self
+            .get_file_response(file_name, headers, Disposition::Inline)
+            .await?;
+
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(THUMBNAIL_CACHE_CONTROL),
+        );
+
+        Ok(response)
+    }
+
+    pub async fn get_object(&self, file_name: &str) -> Result<Vec<u8>> {
+        let data = fs::read(self.get_path(file_name)).await.map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                format!("failed to read file {}", file_name),
+            )
+        })?;
+
+        Ok(data)
+    }
+
+    pub fn is_object_exists(&self, file_name: &str) -> bool {
+        self.get_path(file_name).exists()
+    }
+
+    pub async fn get_object_size(&self, file_name: &str) -> Result<Option<u64>> {
+        match fs::metadata(self.get_path(file_name)).await {
+            Ok(metadata) if metadata.is_file() => Ok(Some(metadata.len())),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::context(
+                InternalServerError,
+                e,
+                format!("failed to read metadata of file {}", file_name),
+            )),
+        }
+    }
+
+    pub async fn copy_object<W>(&self, file_name: &str, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut file = fs::File::open(self.get_path(file_name))
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    format!("failed to open file {}", file_name),
+                )
+            })?;
+
+        let size = io::copy(&mut file, writer).await.map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                format!("failed to copy file {}", file_name),
+            )
+        })?;
+
+        Ok(size)
+    }
+
+    async fn get_file_response(
+        &self,
+        file_name: &str,
+        headers: &HeaderMap,
+        disposition: Disposition,
+    ) -> Result<Response> {
         let file_path = self.get_path(file_name);
 
         if !file_path.exists() {
@@ -31,7 +134,83 @@ impl LocalStorage {
             .await
             .map_err(|e| Error::context(InternalServerError, e, "failed to get file metadata"))?;
 
-        let file = fs::File::open(&file_path).await.map_err(|e| {
+        let file_size = file_metadata.len();
+
+        let modified = file_metadata.modified().map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to get file modification time",
+            )
+        })?;
+
+        let etag = gen_etag(file_size, modified);
+        let last_modified = HttpDate::from(modified);
+
+        if is_not_modified(headers, &etag, last_modified) {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, &etag)
+                .header(header::LAST_MODIFIED, last_modified.to_string())
+                .header(header::ACCEPT_RANGES, "bytes")
+                .body(Body::empty())
+                .map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        "failed to build not modified response for download file",
+                    )
+                });
+        }
+
+        let range = match headers
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(value) if is_range_fresh(headers, &etag, last_modified) => {
+                RangeRequest::from_header(value, file_size)
+            }
+            _ => RangeRequest::Full,
+        };
+
+        let response = match disposition {
+            Disposition::Attachment => {
+                Response::builder().header(header::CONTENT_TYPE, "application/octet-stream")
+            }
+            Disposition::Inline => {
+                let head = read_file_head(&file_path).await?;
+                let content_type = guess_mime_type(file_name, Some(&head));
+                let content_security_policy = get_preview_content_security_policy(&content_type);
+
+                Response::builder()
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::CONTENT_SECURITY_POLICY, content_security_policy)
+                    .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            }
+        }
+        .header(
+            header::CONTENT_DISPOSITION,
+            disposition.header_value(file_name),
+        )
+        .header(header::ETAG, &etag)
+        .header(header::LAST_MODIFIED, last_modified.to_string())
+        .header(header::ACCEPT_RANGES, "bytes");
+
+        if range == RangeRequest::Unsatisfiable {
+            return response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", file_size))
+                .body(Body::empty())
+                .map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        "failed to build range not satisfiable response for download file",
+                    )
+                });
+        }
+
+        let mut file = fs::File::open(&file_path).await.map_err(|e| {
             Error::context(
                 InternalServerError,
                 e,
@@ -39,27 +218,120 @@ impl LocalStorage {
             )
         })?;
 
-        let stream = ReaderStream::new(file);
+        let response = match range {
+            RangeRequest::Partial(byte_range) => {
+                file.seek(SeekFrom::Start(byte_range.start))
+                    .await
+                    .map_err(|e| {
+                        Error::context(InternalServerError, e, "failed to seek file to range start")
+                    })?;
 
-        let body = Body::from_stream(stream);
+                let stream = ReaderStream::new(file.take(byte_range.len()));
 
-        let response = Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, "application/octet-stream")
-            .header(
-                header::CONTENT_DISPOSITION,
-                format!("attachment; filename=\"{}\"", file_name),
+                response
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(
+                        header::CONTENT_RANGE,
+                        format!(
+                            "bytes {}-{}/{}",
+                            byte_range.start, byte_range.end, file_size
+                        ),
+                    )
+                    .header(header::CONTENT_LENGTH, byte_range.len())
+                    .body(Body::from_stream(stream))
+            }
+            _ => {
+                let stream = ReaderStream::new(file);
+
+                response
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_LENGTH, file_size)
+                    .body(Body::from_stream(stream))
+            }
+        }
+        .map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to build response for download file",
             )
-            .header(header::CONTENT_LENGTH, file_metadata.len())
-            .body(body)
-            .map_err(|e| {
-                Error::context(
-                    InternalServerError,
-                    e,
-                    "failed to build response for download file",
-                )
-            })?;
+        })?;
 
         Ok(response)
     }
 }
+
+async fn read_file_head(file_path: &Path) -> Result<Vec<u8>> {
+    let file = fs::File::open(file_path)
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to open file for sniffing"))?;
+
+    let mut head = Vec::with_capacity(MIME_SNIFF_SIZE);
+
+    file.take(MIME_SNIFF_SIZE as u64)
+        .read_to_end(&mut head)
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to read file head"))?;
+
+    Ok(head)
+}
+
+fn gen_etag(file_size: u64, modified: SystemTime) -> String {
+    let modified = modified
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    format!("\"{:x}-{:x}\"", file_size, modified)
+}
+
+fn is_etag_matched(value: &str, etag: &str, weak: bool) -> bool {
+    value.split(',').map(|tag| tag.trim()).any(|tag| {
+        if tag == "*" {
+            return true;
+        }
+
+        match tag.strip_prefix("W/") {
+            Some(tag) => weak && tag == etag,
+            None => tag == etag,
+        }
+    })
+}
+
+// If-None-Match takes precedence over If-Modified-Since
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: HttpDate) -> bool {
+    if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        return match value.to_str() {
+            Ok(value) => is_etag_matched(value, etag, true),
+            Err(_) => false,
+        };
+    }
+
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<HttpDate>().ok())
+        .map(|since| last_modified <= since)
+        .unwrap_or(false)
+}
+
+// a range is only honored when If-Range still matches the current file
+fn is_range_fresh(headers: &HeaderMap, etag: &str, last_modified: HttpDate) -> bool {
+    let value = match headers.get(header::IF_RANGE) {
+        Some(value) => match value.to_str() {
+            Ok(value) => value.trim(),
+            Err(_) => return false,
+        },
+        None => return true,
+    };
+
+    if value.starts_with('"') || value.starts_with("W/") {
+        // If-Range requires strong comparison
+        is_etag_matched(value, etag, false)
+    } else {
+        value
+            .parse::<HttpDate>()
+            .map(|date| date == last_modified)
+            .unwrap_or(false)
+    }
+}
@@ -10,7 +10,10 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::fs;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 
+use super::upload::PARTS_DIR_PREFIX;
+use super::utils::LocalStorageUtils;
 use super::LocalStorage;
 use crate::env::LocalStorageEnv;
 use crate::error::ErrorType::InternalServerError;
@@ -45,6 +48,23 @@ impl LocalStorage {
         })
     }
 
+    // the probe is named like a parts directory, so that listings skip it
+    pub async fn check(&self) -> Result<()> {
+        let probe = self.get_path(&format!("{}probe_{}", PARTS_DIR_PREFIX, Uuid::new_v4()));
+
+        fs::write(&probe, b"").await.map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "local storage directory is not writable",
+            )
+        })?;
+
+        fs::remove_file(&probe)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to remove probe file"))
+    }
+
     async fn task_watcher(storage: LocalStorage) -> Result<()> {
         let interval = 5 * 60; // run every 5 minutes
 
@@ -73,7 +93,7 @@ impl LocalStorage {
         }
     }
 
-    async fn cleanup_part_files(&self, file_name: &str, upload_id: &str) -> Result<()> {
+    pub(super) async fn cleanup_part_files(&self, file_name: &str, upload_id: &str) -> Result<()> {
         fs::remove_dir_all(self.get_parts_dir(file_name, upload_id))
             .await
             .map_err(|e| {
@@ -0,0 +1,81 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use tokio::fs;
+
+use super::upload::PARTS_DIR_PREFIX;
+use super::LocalStorage;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl LocalStorage {
+    // total size of all files, including parts of unfinished uploads
+    pub async fn get_usage(&self) -> Result<u64> {
+        let files = self.list_files().await?;
+
+        Ok(files.iter().map(|(_, size)| size).sum())
+    }
+
+    pub async fn list_object_names(&self) -> Result<Vec<String>> {
+        let objects = self.list_object_sizes().await?;
+
+        Ok(objects.into_iter().map(|(name, _)| name).collect())
+    }
+
+    // parts of unfinished uploads are left out, they are not objects yet
+    pub async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        let files = self.list_files().await?;
+
+        let objects = files
+            .into_iter()
+            .filter(|(name, _)| !name.starts_with(PARTS_DIR_PREFIX))
+            .collect();
+
+        Ok(objects)
+    }
+
+    // names are relative to the storage directory and separated by '/' like object names
+    async fn list_files(&self) -> Result<Vec<(String, u64)>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.path.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            let mut entries = fs::read_dir(&dir)
+                .await
+                .map_err(|e| Error::context(InternalServerError, e, "failed to read directory"))?;
+
+            while let Some(entry) = entries.next_entry().await.map_err(|e| {
+                Error::context(InternalServerError, e, "failed to read directory entry")
+            })? {
+                let metadata = entry.metadata().await.map_err(|e| {
+                    Error::context(InternalServerError, e, "failed to get entry metadata")
+                })?;
+
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                    continue;
+                }
+
+                let path = entry.path();
+
+                let name = path
+                    .strip_prefix(&self.path)
+                    .map_err(|e| {
+                        Error::context(InternalServerError, e, "failed to get relative path")
+                    })?
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                files.push((name, metadata.len()));
+            }
+        }
+
+        Ok(files)
+    }
+}
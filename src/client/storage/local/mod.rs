@@ -5,8 +5,10 @@
 :license: MIT, see LICENSE for more details.
 */
 
+pub mod backend;
 pub mod download;
 pub mod init;
+pub mod list;
 pub mod models;
 pub mod remove;
 #[cfg(test)]
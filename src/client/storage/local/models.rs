@@ -24,3 +24,84 @@ pub struct TaskInfo {
     pub parts: Vec<Part>,
     pub expiration_timestamp: i64,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64, // inclusive
+}
+
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeRequest {
+    Full,
+    Partial(ByteRange),
+    Unsatisfiable,
+}
+
+impl RangeRequest {
+    // only a single range in bytes is supported,
+    // anything else falls back to the full content as allowed by RFC 9110
+    pub fn from_header(value: &str, file_size: u64) -> Self {
+        let ranges = match value.trim().strip_prefix("bytes=") {
+            Some(ranges) => ranges,
+            None => return Self::Full,
+        };
+
+        if ranges.contains(',') {
+            return Self::Full;
+        }
+
+        let (start, end) = match ranges.trim().split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => return Self::Full,
+        };
+
+        if start.is_empty() {
+            // suffix range, the last n bytes
+            let suffix_length = match end.parse::<u64>() {
+                Ok(suffix_length) => suffix_length,
+                Err(_) => return Self::Full,
+            };
+
+            if suffix_length == 0 || file_size == 0 {
+                return Self::Unsatisfiable;
+            }
+
+            return Self::Partial(ByteRange {
+                start: file_size.saturating_sub(suffix_length),
+                end: file_size - 1,
+            });
+        }
+
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return Self::Full,
+        };
+
+        let end = if end.is_empty() {
+            None
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => Some(end),
+                _ => return Self::Full,
+            }
+        };
+
+        if start >= file_size {
+            return Self::Unsatisfiable;
+        }
+
+        let end = match end {
+            Some(end) => end.min(file_size - 1),
+            None => file_size - 1,
+        };
+
+        Self::Partial(ByteRange { start, end })
+    }
+}
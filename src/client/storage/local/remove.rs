@@ -5,10 +5,12 @@
 :license: MIT, see LICENSE for more details.
 */
 
+use std::path::Path;
 use tokio::fs;
 
 use super::utils::LocalStorageUtils;
 use super::LocalStorage;
+use crate::client::Storage;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
 
@@ -24,9 +26,63 @@ impl LocalStorage {
                 )
             })?;
 
+        self.remove_empty_parents(file_name).await;
+
+        Ok(())
+    }
+
+    pub async fn remove_object_if_exists(&self, file_name: &str) -> Result<()> {
+        if self.get_path(file_name).exists() {
+            self.remove_object(file_name).await?;
+        }
+
         Ok(())
     }
 
+    pub async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        let prefix = prefix.trim_end_matches('/');
+
+        if !Storage::is_valid_object_name(prefix) {
+            return Err(Error::new(
+                InternalServerError,
+                format!("invalid prefix {} in local storage", prefix),
+            ));
+        }
+
+        let dir_path = self.get_path(prefix);
+
+        if dir_path.exists() {
+            fs::remove_dir_all(dir_path).await.map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to remove prefix in local storage",
+                )
+            })?;
+
+            self.remove_empty_parents(prefix).await;
+        }
+
+        Ok(())
+    }
+
+    // objects live in a directory named by their id, which is left empty after removal
+    async fn remove_empty_parents(&self, name: &str) {
+        for parent in Path::new(name).ancestors().skip(1) {
+            if parent.as_os_str().is_empty() {
+                break;
+            }
+
+            // stops at the first directory that still has files
+            if fs::remove_dir(self.get_path(&parent.to_string_lossy()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
     pub async fn remove_objects_all(&self) -> Result<()> {
         self.remove_dir().await?;
         self.create_dir().await?;
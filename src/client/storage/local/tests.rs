@@ -5,27 +5,27 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::response::Response;
 use tokio::fs;
 
 use super::super::utils::tests::fake_data;
+use super::models::{ByteRange, RangeRequest};
 use super::utils::LocalStorageUtils;
 use super::LocalStorage;
-use crate::client::{storage::models::StorageClient, Storage};
 use crate::env::tests::{get_env, DBType, STType};
+use crate::env::StorageEnv;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
+use crate::utils::tests::ResponseExt;
 
 pub async fn get_storage() -> LocalStorage {
     let env = get_env(DBType::Sqlite, STType::LocalStorage);
 
-    let storage = match Storage::new(&env.storage).await.unwrap().client {
-        StorageClient::Local(storage) => storage,
+    match &env.storage {
+        StorageEnv::LocalStorage(config) => LocalStorage::new(config),
         _ => unreachable!(),
-    };
-
-    storage
+    }
 }
 
 pub async fn init(storage: &LocalStorage) -> Result<()> {
@@ -55,7 +55,9 @@ async fn test_local_storage_get_download_response() {
 
         upload_data(storage, file_name).await?;
 
-        storage.get_download_response(file_name).await
+        storage
+            .get_download_response(file_name, &HeaderMap::new())
+            .await
     }
 
     let storage = get_storage().await;
@@ -64,3 +66,198 @@ async fn test_local_storage_get_download_response() {
 
     assert_eq!(result.unwrap().status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn test_local_storage_get_download_response_range() {
+    async fn inner(storage: &LocalStorage) -> Result<Response> {
+        init(storage).await?;
+
+        let file_name = "test_range.txt";
+
+        upload_data(storage, file_name).await?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=6-10"));
+
+        storage.get_download_response(file_name, &headers).await
+    }
+
+    let storage = get_storage().await;
+    let result = inner(&storage).await;
+    reset(&storage).await;
+
+    let response = result.unwrap();
+    let content_range = format!("bytes 6-10/{}", fake_data().len());
+
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers().get(header::CONTENT_RANGE).unwrap(),
+        content_range.as_str()
+    );
+    assert_eq!(response.to_string().await.unwrap(), "world");
+}
+
+#[tokio::test]
+async fn test_local_storage_get_download_response_range_not_satisfiable() {
+    async fn inner(storage: &LocalStorage) -> Result<Response> {
+        init(storage).await?;
+
+        let file_name = "test_range_not_satisfiable.txt";
+
+        upload_data(storage, file_name).await?;
+
+        let range = format!("bytes={}-", fake_data().len());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_str(&range).unwrap());
+
+        storage.get_download_response(file_name, &headers).await
+    }
+
+    let storage = get_storage().await;
+    let result = inner(&storage).await;
+    reset(&storage).await;
+
+    assert_eq!(result.unwrap().status(), StatusCode::RANGE_NOT_SATISFIABLE);
+}
+
+#[tokio::test]
+async fn test_local_storage_get_download_response_not_modified() {
+    async fn inner(storage: &LocalStorage) -> Result<(Response, Response)> {
+        init(storage).await?;
+
+        let file_name = "test_not_modified.txt";
+
+        upload_data(storage, file_name).await?;
+
+        let response = storage
+            .get_download_response(file_name, &HeaderMap::new())
+            .await?;
+
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+
+        let response_not_modified = storage.get_download_response(file_name, &headers).await?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=0-4"));
+        headers.insert(header::IF_RANGE, HeaderValue::from_static("\"outdated\""));
+
+        let response_if_range = storage.get_download_response(file_name, &headers).await?;
+
+        Ok((response_not_modified, response_if_range))
+    }
+
+    let storage = get_storage().await;
+    let result = inner(&storage).await;
+    reset(&storage).await;
+
+    let (response_not_modified, response_if_range) = result.unwrap();
+
+    assert_eq!(response_not_modified.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response_if_range.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_local_storage_get_preview_response() {
+    async fn inner(storage: &LocalStorage) -> Result<Response> {
+        init(storage).await?;
+
+        let file_name = "test_preview.txt";
+
+        upload_data(storage, file_name).await?;
+
+        storage
+            .get_preview_response(file_name, &HeaderMap::new())
+            .await
+    }
+
+    let storage = get_storage().await;
+    let result = inner(&storage).await;
+    reset(&storage).await;
+
+    let response = result.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "inline; filename=\"test_preview.txt\""
+    );
+    assert!(response
+        .headers()
+        .contains_key(header::CONTENT_SECURITY_POLICY));
+}
+
+#[tokio::test]
+async fn test_local_storage_remove_prefix_invalid() {
+    async fn inner(storage: &LocalStorage) -> Result<Vec<bool>> {
+        init(storage).await?;
+
+        let file_name = "test_remove_prefix_invalid.txt";
+
+        upload_data(storage, file_name).await?;
+
+        let mut results = Vec::new();
+
+        for prefix in ["", "/", ".", "..", "a/../..", "/tmp"] {
+            results.push(storage.remove_prefix(prefix).await.is_err());
+        }
+
+        results.push(storage.get_path(file_name).exists());
+
+        Ok(results)
+    }
+
+    let storage = get_storage().await;
+    let result = inner(&storage).await;
+    reset(&storage).await;
+
+    assert!(result.unwrap().into_iter().all(|result| result));
+}
+
+#[test]
+fn test_local_storage_range_request_from_header() {
+    assert_eq!(
+        RangeRequest::from_header("bytes=0-99", 1000),
+        RangeRequest::Partial(ByteRange { start: 0, end: 99 })
+    );
+    assert_eq!(
+        RangeRequest::from_header("bytes=900-", 1000),
+        RangeRequest::Partial(ByteRange {
+            start: 900,
+            end: 999
+        })
+    );
+    assert_eq!(
+        RangeRequest::from_header("bytes=-100", 1000),
+        RangeRequest::Partial(ByteRange {
+            start: 900,
+            end: 999
+        })
+    );
+    assert_eq!(
+        RangeRequest::from_header("bytes=500-2000", 1000),
+        RangeRequest::Partial(ByteRange {
+            start: 500,
+            end: 999
+        })
+    );
+    assert_eq!(
+        RangeRequest::from_header("bytes=1000-", 1000),
+        RangeRequest::Unsatisfiable
+    );
+    assert_eq!(
+        RangeRequest::from_header("bytes=0-1,5-9", 1000),
+        RangeRequest::Full
+    );
+    assert_eq!(
+        RangeRequest::from_header("items=0-1", 1000),
+        RangeRequest::Full
+    );
+}
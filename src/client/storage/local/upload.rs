@@ -5,7 +5,7 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use tokio::fs::{self, File};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -17,12 +17,14 @@ use super::utils::LocalStorageUtils;
 use super::LocalStorage;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
-use crate::utils::get_current_timestamp;
+use crate::utils::{get_current_timestamp, UPLOAD_EXPIRATION};
+
+pub const PARTS_DIR_PREFIX: &str = "__PARTS__";
 
 impl LocalStorage {
     pub async fn create_multipart_upload_id(&self, file_name: &str) -> Result<String> {
         let upload_id = Uuid::new_v4().to_string();
-        let expiration_timestamp = get_current_timestamp() + 1000 * 24 * 3600; // 1 day
+        let expiration_timestamp = get_current_timestamp() + UPLOAD_EXPIRATION;
 
         self.tasks.lock().await.insert(
             upload_id.clone(),
@@ -80,10 +82,20 @@ impl LocalStorage {
         &self,
         file_name: &str,
         upload_id: &str,
-        parts: &Vec<Part>,
+        parts: &[Part],
     ) -> Result<()> {
+        // the final file must not be created for an unknown upload
+        if !self.tasks.lock().await.contains_key(upload_id) {
+            return Err(Error::new(
+                InternalServerError,
+                format!("upload {} not found", upload_id),
+            ));
+        }
+
         let file_path = self.get_path(file_name);
 
+        create_parent_dir(&file_path).await?;
+
         let mut final_file = File::create(file_path)
             .await
             .map_err(|e| Error::context(InternalServerError, e, "failed to create final file"))?;
@@ -126,12 +138,54 @@ impl LocalStorage {
         Ok(())
     }
 
+    pub async fn abort_multipart_upload(&self, file_name: &str, upload_id: &str) -> Result<()> {
+        if self.tasks.lock().await.remove(upload_id).is_none() {
+            return Err(Error::new(
+                InternalServerError,
+                format!("upload {} not found", upload_id),
+            ));
+        }
+
+        self.cleanup_part_files(file_name, upload_id).await
+    }
+
+    pub async fn put_object(&self, file_name: &str, data: &[u8]) -> Result<()> {
+        let file_path = self.get_path(file_name);
+
+        create_parent_dir(&file_path).await?;
+
+        fs::write(file_path, data)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to write file"))?;
+
+        Ok(())
+    }
+
     pub fn get_parts_dir(&self, file_name: &str, upload_id: &str) -> PathBuf {
-        self.get_path(&format!("__PARTS__{}_{}", file_name, upload_id))
+        // files in a bundle are nested, keep their parts in a single directory
+        self.get_path(&format!(
+            "{}{}_{}",
+            PARTS_DIR_PREFIX,
+            file_name.replace('/', "_"),
+            upload_id
+        ))
     }
 
     fn get_part_path(&self, file_name: &str, upload_id: &str, part_number: u16) -> PathBuf {
-        self.get_parts_dir(file_name, upload_id)
-            .join(&format!("__PART__{}_{}", file_name, part_number))
+        self.get_parts_dir(file_name, upload_id).join(format!(
+            "__PART__{}_{}",
+            file_name.replace('/', "_"),
+            part_number
+        ))
     }
 }
+
+async fn create_parent_dir(file_path: &Path) -> Result<()> {
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).await.map_err(|e| {
+            Error::context(InternalServerError, e, "failed to create parent directory")
+        })?;
+    }
+
+    Ok(())
+}
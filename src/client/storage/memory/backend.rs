@@ -0,0 +1,271 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::async_trait;
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::Response;
+use std::collections::BTreeMap;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use uuid::Uuid;
+
+use super::super::backend::StorageBackend;
+use super::super::models::{Disposition, Part};
+use super::super::thumbnail::THUMBNAIL_CACHE_CONTROL;
+use super::{MemoryStorage, MemoryUpload};
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::utils::{get_current_timestamp, UPLOAD_EXPIRATION};
+
+#[async_trait]
+impl StorageBackend for MemoryStorage {
+    async fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
+        let upload_id = Uuid::new_v4().to_string();
+        let now = get_current_timestamp();
+
+        let mut uploads = self.uploads.lock().unwrap();
+
+        // there's no watcher, abandoned uploads are dropped whenever a new one starts
+        uploads.retain(|_, upload| upload.expiration_timestamp > now);
+
+        uploads.insert(
+            upload_id.clone(),
+            MemoryUpload {
+                object: object.to_string(),
+                parts: BTreeMap::new(),
+                expiration_timestamp: now + UPLOAD_EXPIRATION,
+            },
+        );
+
+        Ok(upload_id)
+    }
+
+    async fn multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        part_data: &[u8],
+        part_number: u16,
+    ) -> Result<Part> {
+        let mut uploads = self.uploads.lock().unwrap();
+
+        let upload = match uploads.get_mut(upload_id) {
+            Some(upload) if upload.object == object => upload,
+            _ => {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("upload {} not found", upload_id),
+                ))
+            }
+        };
+
+        upload
+            .parts
+            .insert(part_number, Bytes::copy_from_slice(part_data));
+
+        Ok(Part {
+            number: part_number,
+            etag: format!("{:x}", md5::compute(part_data)),
+        })
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<()> {
+        let upload = match self.uploads.lock().unwrap().remove(upload_id) {
+            Some(upload) if upload.object == object => upload,
+            _ => {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("upload {} not found", upload_id),
+                ))
+            }
+        };
+
+        let mut data = Vec::new();
+
+        for part in parts {
+            match upload.parts.get(&part.number) {
+                Some(part_data) if format!("{:x}", md5::compute(part_data)) == part.etag => {
+                    data.extend_from_slice(part_data)
+                }
+                _ => {
+                    return Err(Error::new(
+                        InternalServerError,
+                        format!("part {} of upload {} not found", part.number, upload_id),
+                    ))
+                }
+            }
+        }
+
+        self.objects
+            .write()
+            .unwrap()
+            .insert(object.to_string(), Bytes::from(data));
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()> {
+        let mut uploads = self.uploads.lock().unwrap();
+
+        match uploads.get(upload_id) {
+            Some(upload) if upload.object == object => {
+                uploads.remove(upload_id);
+
+                Ok(())
+            }
+            _ => Err(Error::new(
+                InternalServerError,
+                format!("upload {} not found", upload_id),
+            )),
+        }
+    }
+
+    async fn put_object(&self, object: &str, data: &[u8]) -> Result<()> {
+        self.objects
+            .write()
+            .unwrap()
+            .insert(object.to_string(), Bytes::copy_from_slice(data));
+
+        Ok(())
+    }
+
+    async fn get_download_response(&self, object: &str, _headers: &HeaderMap) -> Result<Response> {
+        self.get_object_response(object, Disposition::Attachment)
+    }
+
+    async fn get_preview_response(&self, object: &str, _headers: &HeaderMap) -> Result<Response> {
+        self.get_object_response(object, Disposition::Inline)
+    }
+
+    async fn get_thumbnail_response(
+        &self,
+        thumbnail_name: &str,
+        _headers: &HeaderMap,
+    ) -> Result<Response> {
+        let mut response = self.get_object_response(thumbnail_name, Disposition::Inline)?;
+
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(THUMBNAIL_CACHE_CONTROL),
+        );
+
+        Ok(response)
+    }
+
+    async fn get_object(&self, object: &str) -> Result<Vec<u8>> {
+        Ok(self.get_data(object)?.to_vec())
+    }
+
+    async fn copy_object(
+        &self,
+        object: &str,
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<u64> {
+        let data = self.get_data(object)?;
+
+        writer
+            .write_all(&data)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to write object"))?;
+
+        Ok(data.len() as u64)
+    }
+
+    async fn is_object_exists(&self, object: &str) -> Result<bool> {
+        Ok(self.objects.read().unwrap().contains_key(object))
+    }
+
+    async fn get_object_size(&self, object: &str) -> Result<Option<u64>> {
+        Ok(self
+            .objects
+            .read()
+            .unwrap()
+            .get(object)
+            .map(|data| data.len() as u64))
+    }
+
+    async fn list_object_names(&self) -> Result<Vec<String>> {
+        Ok(self.objects.read().unwrap().keys().cloned().collect())
+    }
+
+    async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        let sizes = self
+            .objects
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(object, data)| (object.clone(), data.len() as u64))
+            .collect();
+
+        Ok(sizes)
+    }
+
+    async fn get_usage(&self) -> Result<u64> {
+        let size = self
+            .objects
+            .read()
+            .unwrap()
+            .values()
+            .map(|data| data.len() as u64)
+            .sum();
+
+        Ok(size)
+    }
+
+    async fn remove_object(&self, object: &str) -> Result<()> {
+        match self.objects.write().unwrap().remove(object) {
+            Some(_) => Ok(()),
+            None => Err(Error::new(
+                InternalServerError,
+                format!("file {} not found", object),
+            )),
+        }
+    }
+
+    async fn remove_object_if_exists(&self, object: &str) -> Result<()> {
+        self.objects.write().unwrap().remove(object);
+
+        Ok(())
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        let prefix = format!("{}/", prefix.trim_end_matches('/'));
+
+        self.objects
+            .write()
+            .unwrap()
+            .retain(|object, _| !object.starts_with(&prefix));
+
+        Ok(())
+    }
+
+    async fn remove_objects_all(&self) -> Result<()> {
+        self.objects.write().unwrap().clear();
+
+        Ok(())
+    }
+
+    async fn _destroy(&self) -> Result<()> {
+        self.objects.write().unwrap().clear();
+        self.uploads.lock().unwrap().clear();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, StatusCode};
+use axum::response::Response;
+
+use super::super::models::Disposition;
+use super::super::utils::{get_preview_content_security_policy, guess_mime_type, MIME_SNIFF_SIZE};
+use super::MemoryStorage;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl MemoryStorage {
+    pub(super) fn get_data(&self, object: &str) -> Result<Bytes> {
+        self.objects
+            .read()
+            .unwrap()
+            .get(object)
+            .cloned()
+            .ok_or_else(|| Error::new(InternalServerError, format!("file {} not found", object)))
+    }
+
+    // objects are already in memory, so they're always sent whole
+    pub(super) fn get_object_response(
+        &self,
+        object: &str,
+        disposition: Disposition,
+    ) -> Result<Response> {
+        let data = self.get_data(object)?;
+
+        let response = match disposition {
+            Disposition::Attachment => {
+                Response::builder().header(header::CONTENT_TYPE, "application/octet-stream")
+            }
+            Disposition::Inline => {
+                let head = &data[..data.len().min(MIME_SNIFF_SIZE)];
+                let content_type = guess_mime_type(object, Some(head));
+                let content_security_policy = get_preview_content_security_policy(&content_type);
+
+                Response::builder()
+                    .header(header::CONTENT_TYPE, content_type)
+                    .header(header::CONTENT_SECURITY_POLICY, content_security_policy)
+                    .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            }
+        };
+
+        response
+            .status(StatusCode::OK)
+            .header(
+                header::CONTENT_DISPOSITION,
+                disposition.header_value(object),
+            )
+            .header(header::CONTENT_LENGTH, data.len())
+            .body(Body::from(data))
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to build response for memory object",
+                )
+            })
+    }
+}
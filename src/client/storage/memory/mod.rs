@@ -0,0 +1,35 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+mod backend;
+mod download;
+#[cfg(test)]
+pub mod tests;
+
+use axum::body::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Debug)]
+struct MemoryUpload {
+    object: String,
+    parts: BTreeMap<u16, Bytes>,
+    expiration_timestamp: i64,
+}
+
+// nothing is persisted, for tests and ephemeral deployments
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    objects: Arc<RwLock<BTreeMap<String, Bytes>>>,
+    uploads: Arc<Mutex<HashMap<String, MemoryUpload>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,121 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::{header, HeaderMap, StatusCode};
+
+use super::super::backend::StorageBackend;
+use super::super::models::Part;
+use super::MemoryStorage;
+use crate::utils::tests::{sleep_async, ResponseExt};
+
+#[tokio::test]
+async fn test_memory_complete_multipart_upload() {
+    let storage = MemoryStorage::new();
+    let object = "id/test-complete-multipart-upload.txt";
+
+    let upload_id = storage.create_multipart_upload_id(object).await.unwrap();
+
+    let first = storage
+        .multipart_upload(object, &upload_id, b"hello ", 1)
+        .await
+        .unwrap();
+    let second = storage
+        .multipart_upload(object, &upload_id, b"world!", 2)
+        .await
+        .unwrap();
+
+    // parts are joined in the given order, not in the order they arrived
+    storage
+        .complete_multipart_upload(object, &upload_id, &[first, second])
+        .await
+        .unwrap();
+
+    assert_eq!(storage.get_object(object).await.unwrap(), b"hello world!");
+    assert!(storage.uploads.lock().unwrap().is_empty());
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_memory_complete_multipart_upload_invalid_part() {
+    let storage = MemoryStorage::new();
+    let object = "test-complete-multipart-upload-invalid-part.txt";
+
+    let upload_id = storage.create_multipart_upload_id(object).await.unwrap();
+
+    storage
+        .multipart_upload(object, &upload_id, b"hello world!", 1)
+        .await
+        .unwrap();
+
+    let result = storage
+        .complete_multipart_upload(
+            object,
+            &upload_id,
+            &[Part {
+                number: 1,
+                etag: "wrong".to_string(),
+            }],
+        )
+        .await;
+
+    assert!(result.is_err());
+    assert!(!storage.is_object_exists(object).await.unwrap());
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_memory_get_preview_response() {
+    let storage = MemoryStorage::new();
+    let object = "id/test preview.txt";
+
+    storage.put_object(object, b"hello world!").await.unwrap();
+
+    let response = storage
+        .get_preview_response(object, &HeaderMap::new())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "inline; filename=\"test preview.txt\""
+    );
+    assert_eq!(response.to_string().await.unwrap(), "hello world!");
+
+    let result = storage
+        .get_download_response("missing.txt", &HeaderMap::new())
+        .await;
+
+    assert!(result.is_err());
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_memory_remove_prefix() {
+    let storage = MemoryStorage::new();
+
+    for object in ["id/a.txt", "id/b/c.txt", "id2/a.txt"] {
+        storage.put_object(object, b"hello world!").await.unwrap();
+    }
+
+    storage.remove_prefix("id").await.unwrap();
+
+    assert_eq!(
+        storage.list_object_names().await.unwrap(),
+        vec!["id2/a.txt".to_string()]
+    );
+    assert_eq!(storage.get_usage().await.unwrap(), 12);
+
+    sleep_async(1).await;
+}
@@ -0,0 +1,127 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::async_trait;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use tokio::io::AsyncWrite;
+
+use super::super::backend::StorageBackend;
+use super::super::models::Part;
+use super::Minio;
+use crate::error::Result;
+
+#[async_trait]
+impl StorageBackend for Minio {
+    async fn init(&self) -> Result<()> {
+        Minio::init(self).await
+    }
+
+    async fn check(&self) -> Result<()> {
+        Minio::check(self).await
+    }
+
+    async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
+        Minio::create_multipart_upload_id(self, object).await
+    }
+
+    async fn multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        part_data: &[u8],
+        part_number: u16,
+    ) -> Result<Part> {
+        Minio::multipart_upload(self, object, upload_id, part_data, part_number).await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<()> {
+        Minio::complete_multipart_upload(self, object, upload_id, parts).await
+    }
+
+    async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()> {
+        Minio::abort_multipart_upload(self, object, upload_id).await
+    }
+
+    async fn put_object(&self, object: &str, data: &[u8]) -> Result<()> {
+        Minio::put_object(self, object, data).await
+    }
+
+    async fn get_download_response(&self, object: &str, _headers: &HeaderMap) -> Result<Response> {
+        Minio::get_download_response(self, object).await
+    }
+
+    async fn get_preview_response(&self, object: &str, _headers: &HeaderMap) -> Result<Response> {
+        Minio::get_preview_response(self, object).await
+    }
+
+    async fn get_thumbnail_response(
+        &self,
+        thumbnail_name: &str,
+        _headers: &HeaderMap,
+    ) -> Result<Response> {
+        Minio::get_thumbnail_response(self, thumbnail_name).await
+    }
+
+    async fn get_object(&self, object: &str) -> Result<Vec<u8>> {
+        Minio::get_object(self, object).await
+    }
+
+    async fn copy_object(
+        &self,
+        object: &str,
+        mut writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<u64> {
+        Minio::copy_object(self, object, &mut writer).await
+    }
+
+    async fn is_object_exists(&self, object: &str) -> Result<bool> {
+        Minio::is_object_exists(self, object).await
+    }
+
+    async fn get_object_size(&self, object: &str) -> Result<Option<u64>> {
+        Minio::get_object_size(self, object).await
+    }
+
+    async fn list_object_names(&self) -> Result<Vec<String>> {
+        Minio::list_object_names(self).await
+    }
+
+    async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        Minio::list_object_sizes(self).await
+    }
+
+    async fn get_usage(&self) -> Result<u64> {
+        Minio::get_usage(self).await
+    }
+
+    async fn remove_object(&self, object: &str) -> Result<()> {
+        Minio::remove_object(self, object).await
+    }
+
+    // removing a missing object is not an error in s3
+    async fn remove_object_if_exists(&self, object: &str) -> Result<()> {
+        Minio::remove_object(self, object).await
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        Minio::remove_prefix(self, prefix).await
+    }
+
+    async fn remove_objects_all(&self) -> Result<()> {
+        Minio::remove_objects_all(self).await
+    }
+
+    async fn _destroy(&self) -> Result<()> {
+        self._remove_bucket().await
+    }
+}
@@ -8,18 +8,162 @@
 use axum::body::Body;
 use axum::http::{header, StatusCode};
 use axum::response::Response;
-use minio::s3::args::GetPresignedObjectUrlArgs;
-use minio::s3::utils::urlencode;
+use minio::s3::args::{GetObjectArgs, GetPresignedObjectUrlArgs, StatObjectArgs};
+use minio::s3::error::Error as MinioError;
+use minio::s3::utils::{urlencode, Multimap};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
+use super::super::models::Disposition;
+use super::super::thumbnail::THUMBNAIL_CACHE_CONTROL;
+use super::super::utils::{guess_mime_type, guess_presigned_preview_mime_type};
 use super::Minio;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::{Error, Result};
 
 impl Minio {
     pub async fn get_download_response(&self, remote_path: &str) -> Result<Response> {
+        // the object name is prefixed by its id, so the browser needs the display name
+        let mut query_params = Multimap::new();
+
+        query_params.insert(
+            "response-content-disposition".to_string(),
+            Disposition::Attachment.header_value(remote_path),
+        );
+
+        self.get_presigned_response(remote_path, Some(&query_params))
+            .await
+    }
+
+    pub async fn get_preview_response(&self, remote_path: &str) -> Result<Response> {
+        // objects are not fetched here, so only the extension is used for detection
+        let mut query_params = Multimap::new();
+
+        query_params.insert(
+            "response-content-type".to_string(),
+            guess_presigned_preview_mime_type(remote_path),
+        );
+        query_params.insert(
+            "response-content-disposition".to_string(),
+            Disposition::Inline.header_value(remote_path),
+        );
+
+        self.get_presigned_response(remote_path, Some(&query_params))
+            .await
+    }
+
+    pub async fn get_thumbnail_response(&self, remote_path: &str) -> Result<Response> {
+        let mut query_params = Multimap::new();
+
+        query_params.insert(
+            "response-content-type".to_string(),
+            guess_mime_type(remote_path, None),
+        );
+        query_params.insert(
+            "response-cache-control".to_string(),
+            THUMBNAIL_CACHE_CONTROL.to_string(),
+        );
+
+        self.get_presigned_response(remote_path, Some(&query_params))
+            .await
+    }
+
+    pub async fn get_object(&self, remote_path: &str) -> Result<Vec<u8>> {
+        let encoded_remote_path = urlencode(remote_path);
+
+        let args = GetObjectArgs::new(&self.bucket, &encoded_remote_path).map_err(|e| {
+            Error::context(InternalServerError, e, "failed to create get object args")
+        })?;
+
+        let data = self
+            .client
+            .get_object(&args)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to get object"))?
+            .bytes()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read object"))?
+            .to_vec();
+
+        Ok(data)
+    }
+
+    pub async fn is_object_exists(&self, remote_path: &str) -> Result<bool> {
         let encoded_remote_path = urlencode(remote_path);
 
-        let args = GetPresignedObjectUrlArgs::new(
+        let args = StatObjectArgs::new(&self.bucket, &encoded_remote_path).map_err(|e| {
+            Error::context(InternalServerError, e, "failed to create stat object args")
+        })?;
+
+        match self.client.stat_object(&args).await {
+            Ok(_) => Ok(true),
+            Err(MinioError::S3Error(response)) if response.code == "NoSuchKey" => Ok(false),
+            Err(e) => Err(Error::context(
+                InternalServerError,
+                e,
+                "failed to stat object",
+            )),
+        }
+    }
+
+    pub async fn get_object_size(&self, remote_path: &str) -> Result<Option<u64>> {
+        let encoded_remote_path = urlencode(remote_path);
+
+        let args = StatObjectArgs::new(&self.bucket, &encoded_remote_path).map_err(|e| {
+            Error::context(InternalServerError, e, "failed to create stat object args")
+        })?;
+
+        match self.client.stat_object(&args).await {
+            Ok(response) => Ok(Some(response.size as u64)),
+            Err(MinioError::S3Error(response)) if response.code == "NoSuchKey" => Ok(None),
+            Err(e) => Err(Error::context(
+                InternalServerError,
+                e,
+                "failed to stat object",
+            )),
+        }
+    }
+
+    pub async fn copy_object<W>(&self, remote_path: &str, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let encoded_remote_path = urlencode(remote_path);
+
+        let args = GetObjectArgs::new(&self.bucket, &encoded_remote_path).map_err(|e| {
+            Error::context(InternalServerError, e, "failed to create get object args")
+        })?;
+
+        let mut response = self
+            .client
+            .get_object(&args)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to get object"))?;
+
+        let mut size = 0;
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read object chunk"))?
+        {
+            writer.write_all(&chunk).await.map_err(|e| {
+                Error::context(InternalServerError, e, "failed to write object chunk")
+            })?;
+
+            size += chunk.len() as u64;
+        }
+
+        Ok(size)
+    }
+
+    async fn get_presigned_response(
+        &self,
+        remote_path: &str,
+        extra_query_params: Option<&Multimap>,
+    ) -> Result<Response> {
+        let encoded_remote_path = urlencode(remote_path);
+
+        let mut args = GetPresignedObjectUrlArgs::new(
             &self.bucket,
             &encoded_remote_path,
             http::method::Method::GET,
@@ -32,6 +176,8 @@ impl Minio {
             )
         })?;
 
+        args.extra_query_params = extra_query_params;
+
         let url = self
             .client
             .get_presigned_object_url(&args)
@@ -59,6 +59,17 @@ impl Minio {
         Ok(())
     }
 
+    pub async fn check(&self) -> Result<()> {
+        if !self.is_bucket_exists().await? {
+            return Err(Error::new(
+                InternalServerError,
+                format!("Minio bucket {} does not exist", self.bucket),
+            ));
+        }
+
+        Ok(())
+    }
+
     pub async fn is_bucket_exists(&self) -> Result<bool> {
         let args = BucketExistsArgs::new(&self.bucket)
             .map_err(|e| Error::context(InternalServerError, e, "invalid Minio bucket name"))?;
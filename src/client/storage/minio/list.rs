@@ -15,7 +15,11 @@ use crate::error::{Error, Result};
 
 impl Minio {
     pub async fn list_objects(&self) -> Result<Vec<Item>> {
-        let args = ListObjectsV2Args::new(&self.bucket).map_err(|e| {
+        self.list_objects_with_prefix(None).await
+    }
+
+    pub async fn list_objects_with_prefix(&self, prefix: Option<&str>) -> Result<Vec<Item>> {
+        let mut args = ListObjectsV2Args::new(&self.bucket).map_err(|e| {
             Error::context(
                 InternalServerError,
                 e,
@@ -23,6 +27,8 @@ impl Minio {
             )
         })?;
 
+        args.prefix = prefix;
+
         let response = self
             .client
             .list_objects_v2(&args)
@@ -33,4 +39,30 @@ impl Minio {
 
         Ok(objects)
     }
+
+    pub async fn list_object_names(&self) -> Result<Vec<String>> {
+        let objects = self.list_objects().await?;
+
+        Ok(objects.into_iter().map(|object| object.name).collect())
+    }
+
+    pub async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        let objects = self.list_objects().await?;
+
+        Ok(objects
+            .into_iter()
+            .map(|object| (object.name, object.size.unwrap_or_default() as u64))
+            .collect())
+    }
+
+    pub async fn get_usage(&self) -> Result<u64> {
+        let objects = self.list_objects().await?;
+
+        let size = objects
+            .iter()
+            .map(|object| object.size.unwrap_or_default() as u64)
+            .sum();
+
+        Ok(size)
+    }
 }
@@ -5,6 +5,7 @@
 :license: MIT, see LICENSE for more details.
 */
 
+mod backend;
 mod download;
 mod init;
 mod list;
@@ -33,8 +33,26 @@ impl Minio {
         Ok(())
     }
 
+    pub async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        // prefixes are virtual in s3, only the objects under them need removing
+        let prefix = format!("{}/", prefix.trim_end_matches('/'));
+
+        let objects: Vec<Item> = self.list_objects_with_prefix(Some(&prefix)).await?;
+
+        if objects.is_empty() {
+            return Ok(());
+        }
+
+        self.remove_objects(objects).await
+    }
+
     pub async fn remove_objects_all(&self) -> Result<()> {
         let objects: Vec<Item> = self.list_objects().await?;
+
+        self.remove_objects(objects).await
+    }
+
+    async fn remove_objects(&self, objects: Vec<Item>) -> Result<()> {
         let mut objects_delete: Vec<DeleteObject> = Vec::new();
 
         for object in objects.iter() {
@@ -69,7 +87,7 @@ impl Minio {
             return Err(Error::new(
                 InternalServerError,
                 format!(
-                    "successfully removed objects but got errors:\nobjects: {:#?}\nerrors: {:#?}",
+                    "successfully removed objects but got errors: objects: {:?}, errors: {:?}",
                     response.objects, response.errors
                 ),
             ));
@@ -5,27 +5,19 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use minio::s3::args::PutObjectArgs;
-use std::io::Cursor;
-
-use super::super::utils::tests::fake_data;
 use super::Minio;
-use crate::client::storage::models::StorageClient;
-use crate::client::Storage;
 use crate::env::tests::{get_env, DBType, STType};
-use crate::error::ErrorType::InternalServerError;
-use crate::error::{Error, Result};
+use crate::env::StorageEnv;
+use crate::error::Result;
 use crate::utils::tests::sleep_async;
 
 pub async fn get_storage() -> Minio {
     let env = get_env(DBType::Sqlite, STType::Minio);
 
-    let storage = match Storage::new(&env.storage).await.unwrap().client {
-        StorageClient::Minio(storage) => storage,
+    match &env.storage {
+        StorageEnv::Minio(config) => Minio::new(config).unwrap(),
         _ => unreachable!(),
-    };
-
-    storage
+    }
 }
 
 pub async fn init(storage: &Minio) -> Result<()> {
@@ -36,24 +28,6 @@ pub async fn reset(storage: &Minio) {
     storage._remove_bucket().await.unwrap();
 }
 
-pub async fn upload_data(storage: &Minio, object: &str) -> Result<()> {
-    let data = fake_data();
-
-    let size = data.len();
-    let mut data = Cursor::new(data);
-
-    let mut args = PutObjectArgs::new(&storage.bucket, object, &mut data, Some(size), None)
-        .map_err(|e| Error::context(InternalServerError, e, "failed to create put object args"))?;
-
-    storage
-        .client
-        .put_object(&mut args)
-        .await
-        .map_err(|e| Error::context(InternalServerError, e, "failed to put object"))?;
-
-    Ok(())
-}
-
 #[tokio::test]
 async fn test_minio_create_buffer_if_not_exists() {
     let storage = get_storage().await;
@@ -4,8 +4,12 @@
 :copyright: (C) 2024 L-ING <hlf01@icloud.com>
 :license: MIT, see LICENSE for more details.
 */
-use minio::s3::args::{CompleteMultipartUploadArgs, CreateMultipartUploadArgs, UploadPartArgs};
+use minio::s3::args::{
+    AbortMultipartUploadArgs, CompleteMultipartUploadArgs, CreateMultipartUploadArgs,
+    PutObjectArgs, UploadPartArgs,
+};
 use minio::s3::utils::urlencode;
+use std::io::Cursor;
 
 use super::super::models::Part;
 use super::Minio;
@@ -78,7 +82,7 @@ impl Minio {
         &self,
         remote_path: &str,
         upload_id: &str,
-        parts: &Vec<Part>,
+        parts: &[Part],
     ) -> Result<()> {
         let encoded_remote_path = urlencode(remote_path);
 
@@ -113,4 +117,49 @@ impl Minio {
 
         Ok(())
     }
+
+    pub async fn abort_multipart_upload(&self, remote_path: &str, upload_id: &str) -> Result<()> {
+        let encoded_remote_path = urlencode(remote_path);
+
+        let args = AbortMultipartUploadArgs::new(&self.bucket, &encoded_remote_path, upload_id)
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to create abort multipart upload args",
+                )
+            })?;
+
+        self.client
+            .abort_multipart_upload(&args)
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to abort multipart upload")
+            })?;
+
+        Ok(())
+    }
+
+    pub async fn put_object(&self, remote_path: &str, data: &[u8]) -> Result<()> {
+        let encoded_remote_path = urlencode(remote_path);
+
+        let size = data.len();
+        let mut data = Cursor::new(data);
+
+        let mut args = PutObjectArgs::new(
+            &self.bucket,
+            &encoded_remote_path,
+            &mut data,
+            Some(size),
+            None,
+        )
+        .map_err(|e| Error::context(InternalServerError, e, "failed to create put object args"))?;
+
+        self.client
+            .put_object(&mut args)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to put object"))?;
+
+        Ok(())
+    }
 }
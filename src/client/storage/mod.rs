@@ -5,109 +5,237 @@
 :license: MIT, see LICENSE for more details.
 */
 
+mod archive;
+pub mod backend;
+mod encryption;
+mod hash;
 mod local;
+mod memory;
 mod minio;
 pub mod models;
+mod s3;
 #[cfg(test)]
 pub mod tests;
+mod thumbnail;
 mod utils;
 
+use axum::http::HeaderMap;
 use axum::response::Response;
+use backend::StorageBackend;
+use encryption::DecryptWriter;
 use local::LocalStorage;
+use memory::MemoryStorage;
 use minio::Minio;
-use models::{Part, StorageClient};
+use models::{Disposition, Part};
+use s3::S3;
+use std::sync::Arc;
+use tokio::io::AsyncWrite;
 
+use crate::crypto::Crypto;
 use crate::env::StorageEnv;
-use crate::error::Result;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::utils::gen_object_name;
+
+const OBJECT_NAME_RETRY: usize = 3;
 
 #[derive(Clone)]
 pub struct Storage {
-    pub client: StorageClient,
+    pub backend: Arc<dyn StorageBackend>,
+    // objects are encrypted at rest when set
+    crypto: Option<Crypto>,
 }
 
 impl Storage {
-    pub async fn new(config: &StorageEnv) -> Result<Self> {
-        let client = match config {
-            StorageEnv::Minio(config) => StorageClient::Minio(Minio::new(config)?),
-            StorageEnv::LocalStorage(config) => StorageClient::Local(LocalStorage::new(config)),
+    pub async fn new(config: &StorageEnv, crypto: Option<Crypto>) -> Result<Self> {
+        let backend: Arc<dyn StorageBackend> = match config {
+            StorageEnv::Minio(config) => Arc::new(Minio::new(config)?),
+            StorageEnv::S3(config) => Arc::new(S3::new(config)?),
+            StorageEnv::LocalStorage(config) => Arc::new(LocalStorage::new(config)),
+            StorageEnv::Memory => Arc::new(MemoryStorage::new()),
         };
 
-        Ok(Self { client })
+        Ok(Self { backend, crypto })
     }
 
     pub async fn init(&self) -> Result<()> {
-        match &self.client {
-            StorageClient::Local(storage) => storage.init().await,
-            StorageClient::Minio(storage) => storage.init().await,
+        self.backend.init().await
+    }
+
+    // names from requests must stay inside the storage, an empty or relative component
+    // would point at the storage itself or escape it
+    pub fn is_valid_object_name(name: &str) -> bool {
+        !name
+            .split(['/', '\\'])
+            .any(|component| matches!(component, "" | "." | ".."))
+    }
+
+    pub async fn check(&self) -> Result<()> {
+        self.backend.check().await
+    }
+
+    pub async fn get_download_response(
+        &self,
+        object: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        if self.crypto.is_some() {
+            return self
+                .get_decrypted_response(object, Disposition::Attachment)
+                .await;
         }
+
+        self.backend.get_download_response(object, headers).await
     }
 
-    pub async fn get_download_response(&self, object: &str) -> Result<Response> {
-        match &self.client {
-            StorageClient::Local(storage) => storage.get_download_response(object).await,
-            StorageClient::Minio(storage) => storage.get_download_response(object).await,
+    pub async fn get_preview_response(
+        &self,
+        object: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        if self.crypto.is_some() {
+            return self
+                .get_decrypted_response(object, Disposition::Inline)
+                .await;
         }
+
+        self.backend.get_preview_response(object, headers).await
     }
 
-    pub async fn remove_object(&self, object: &str) -> Result<()> {
-        match &self.client {
-            StorageClient::Local(storage) => storage.remove_object(object).await,
-            StorageClient::Minio(storage) => storage.remove_object(object).await,
+    pub async fn get_object(&self, object: &str) -> Result<Vec<u8>> {
+        if self.crypto.is_some() {
+            let mut data = Vec::new();
+            self.copy_object(object, &mut data).await?;
+
+            return Ok(data);
         }
+
+        self.backend.get_object(object).await
     }
 
-    pub async fn remove_objects_all(&self) -> Result<()> {
-        match &self.client {
-            StorageClient::Local(storage) => storage.remove_objects_all().await,
-            StorageClient::Minio(storage) => storage.remove_objects_all().await,
+    // sent through the server, for clients that can't follow a redirect to the storage
+    pub async fn get_proxied_response(&self, object: &str) -> Result<Response> {
+        self.get_decrypted_response(object, Disposition::Attachment)
+            .await
+    }
+
+    pub async fn is_object_exists(&self, object: &str) -> Result<bool> {
+        self.backend.is_object_exists(object).await
+    }
+
+    // the stored size, which includes the frames of encrypted objects
+    pub async fn get_object_size(&self, object: &str) -> Result<Option<u64>> {
+        self.backend.get_object_size(object).await
+    }
+
+    // object names carry a random id, retry in the unlikely case that it's taken
+    pub async fn create_object_name(&self, file_name: &str) -> Result<String> {
+        for _ in 0..OBJECT_NAME_RETRY {
+            let object = gen_object_name(file_name);
+
+            if !self.is_object_exists(&object).await? {
+                return Ok(object);
+            }
+
+            tracing::warn!("object name {} already exists, retrying", object);
         }
+
+        Err(Error::new(
+            InternalServerError,
+            format!("failed to create a unique object name for {}", file_name),
+        ))
     }
 
-    pub async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
-        match &self.client {
-            StorageClient::Local(storage) => storage.create_multipart_upload_id(object).await,
-            StorageClient::Minio(storage) => storage.create_multipart_upload_id(object).await,
+    pub async fn copy_object<W>(&self, object: &str, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        match &self.crypto {
+            Some(crypto) => {
+                let mut writer = DecryptWriter::new(crypto, object, writer);
+
+                self.copy_raw_object(object, &mut writer).await?;
+
+                writer.finish().await
+            }
+            None => self.copy_raw_object(object, writer).await,
         }
     }
 
+    async fn copy_raw_object<W>(&self, object: &str, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.backend.copy_object(object, writer).await
+    }
+
+    pub async fn put_object(&self, object: &str, data: &[u8]) -> Result<()> {
+        let data = self.encrypt_part(object, Some(data.len() as u64), 1, data)?;
+
+        self.backend.put_object(object, &data).await
+    }
+
+    pub async fn remove_object(&self, object: &str) -> Result<()> {
+        self.backend.remove_object(object).await
+    }
+
+    pub async fn get_usage(&self) -> Result<u64> {
+        self.backend.get_usage().await
+    }
+
+    pub async fn list_object_names(&self) -> Result<Vec<String>> {
+        self.backend.list_object_names().await
+    }
+
+    // encrypted objects are stored larger than their content, check is_encrypted first
+    pub async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        self.backend.list_object_sizes().await
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.crypto.is_some()
+    }
+
+    pub async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        self.backend.remove_prefix(prefix).await
+    }
+
+    pub async fn remove_objects_all(&self) -> Result<()> {
+        self.backend.remove_objects_all().await
+    }
+
+    pub async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
+        self.backend.create_multipart_upload_id(object).await
+    }
+
+    // the size of the whole object is only needed when it's encrypted
     pub async fn multipart_upload(
         &self,
         object: &str,
         upload_id: &str,
         part_data: &[u8],
         part_number: u16,
+        object_size: Option<u64>,
     ) -> Result<Part> {
-        match &self.client {
-            StorageClient::Local(storage) => {
-                storage
-                    .multipart_upload(object, upload_id, part_data, part_number)
-                    .await
-            }
-            StorageClient::Minio(storage) => {
-                storage
-                    .multipart_upload(object, upload_id, part_data, part_number)
-                    .await
-            }
-        }
+        let part_data = self.encrypt_part(object, object_size, part_number, part_data)?;
+
+        self.backend
+            .multipart_upload(object, upload_id, &part_data, part_number)
+            .await
     }
 
     pub async fn complete_multipart_upload(
         &self,
         object: &str,
         upload_id: &str,
-        parts: &Vec<Part>,
+        parts: &[Part],
     ) -> Result<()> {
-        match &self.client {
-            StorageClient::Local(storage) => {
-                storage
-                    .complete_multipart_upload(object, upload_id, parts)
-                    .await
-            }
-            StorageClient::Minio(storage) => {
-                storage
-                    .complete_multipart_upload(object, upload_id, parts)
-                    .await
-            }
-        }
+        self.backend
+            .complete_multipart_upload(object, upload_id, parts)
+            .await
+    }
+
+    pub async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()> {
+        self.backend.abort_multipart_upload(object, upload_id).await
     }
 }
@@ -7,17 +7,33 @@
 
 use serde::{Deserialize, Serialize};
 
-use super::local::LocalStorage;
-use super::minio::Minio;
-
-#[derive(Clone)]
-pub enum StorageClient {
-    Local(LocalStorage),
-    Minio(Minio),
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Part {
     pub number: u16,
     pub etag: String,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Disposition {
+    Attachment,
+    Inline,
+}
+
+impl Disposition {
+    pub fn header_value(&self, file_name: &str) -> String {
+        // object names are prefixed by their id, only the last component is shown
+        let file_name = file_name.rsplit('/').next().unwrap_or(file_name);
+
+        match self {
+            Self::Attachment => format!("attachment; filename=\"{}\"", file_name),
+            Self::Inline => format!("inline; filename=\"{}\"", file_name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub object: String,
+    pub timestamp: i64,
+}
@@ -0,0 +1,127 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::async_trait;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use tokio::io::AsyncWrite;
+
+use super::super::backend::StorageBackend;
+use super::super::models::Part;
+use super::S3;
+use crate::error::Result;
+
+#[async_trait]
+impl StorageBackend for S3 {
+    async fn init(&self) -> Result<()> {
+        S3::init(self).await
+    }
+
+    async fn check(&self) -> Result<()> {
+        S3::check(self).await
+    }
+
+    async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
+        S3::create_multipart_upload_id(self, object).await
+    }
+
+    async fn multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        part_data: &[u8],
+        part_number: u16,
+    ) -> Result<Part> {
+        S3::multipart_upload(self, object, upload_id, part_data, part_number).await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<()> {
+        S3::complete_multipart_upload(self, object, upload_id, parts).await
+    }
+
+    async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()> {
+        S3::abort_multipart_upload(self, object, upload_id).await
+    }
+
+    async fn put_object(&self, object: &str, data: &[u8]) -> Result<()> {
+        S3::put_object(self, object, data).await
+    }
+
+    async fn get_download_response(&self, object: &str, _headers: &HeaderMap) -> Result<Response> {
+        S3::get_download_response(self, object).await
+    }
+
+    async fn get_preview_response(&self, object: &str, _headers: &HeaderMap) -> Result<Response> {
+        S3::get_preview_response(self, object).await
+    }
+
+    async fn get_thumbnail_response(
+        &self,
+        thumbnail_name: &str,
+        _headers: &HeaderMap,
+    ) -> Result<Response> {
+        S3::get_thumbnail_response(self, thumbnail_name).await
+    }
+
+    async fn get_object(&self, object: &str) -> Result<Vec<u8>> {
+        S3::get_object(self, object).await
+    }
+
+    async fn copy_object(
+        &self,
+        object: &str,
+        mut writer: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<u64> {
+        S3::copy_object(self, object, &mut writer).await
+    }
+
+    async fn is_object_exists(&self, object: &str) -> Result<bool> {
+        S3::is_object_exists(self, object).await
+    }
+
+    async fn get_object_size(&self, object: &str) -> Result<Option<u64>> {
+        S3::get_object_size(self, object).await
+    }
+
+    async fn list_object_names(&self) -> Result<Vec<String>> {
+        S3::list_object_names(self).await
+    }
+
+    async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        S3::list_object_sizes(self).await
+    }
+
+    async fn get_usage(&self) -> Result<u64> {
+        S3::get_usage(self).await
+    }
+
+    async fn remove_object(&self, object: &str) -> Result<()> {
+        S3::remove_object(self, object).await
+    }
+
+    // removing a missing object is not an error in s3
+    async fn remove_object_if_exists(&self, object: &str) -> Result<()> {
+        S3::remove_object(self, object).await
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        S3::remove_prefix(self, prefix).await
+    }
+
+    async fn remove_objects_all(&self) -> Result<()> {
+        S3::remove_objects_all(self).await
+    }
+
+    async fn _destroy(&self) -> Result<()> {
+        self._remove_bucket().await
+    }
+}
@@ -0,0 +1,176 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::http::{header, Method, StatusCode};
+use axum::response::Response;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::super::models::Disposition;
+use super::super::thumbnail::THUMBNAIL_CACHE_CONTROL;
+use super::super::utils::{guess_mime_type, guess_presigned_preview_mime_type};
+use super::S3;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl S3 {
+    pub async fn get_download_response(&self, object: &str) -> Result<Response> {
+        // the object name is prefixed by its id, so the browser needs the display name
+        let content_disposition = Disposition::Attachment.header_value(object);
+
+        self.get_presigned_response(
+            object,
+            &[("response-content-disposition", &content_disposition)],
+        )
+    }
+
+    pub async fn get_preview_response(&self, object: &str) -> Result<Response> {
+        // objects are not fetched here, so only the extension is used for detection
+        let content_type = guess_presigned_preview_mime_type(object);
+        let content_disposition = Disposition::Inline.header_value(object);
+
+        self.get_presigned_response(
+            object,
+            &[
+                ("response-content-type", &content_type),
+                ("response-content-disposition", &content_disposition),
+            ],
+        )
+    }
+
+    pub async fn get_thumbnail_response(&self, object: &str) -> Result<Response> {
+        let content_type = guess_mime_type(object, None);
+
+        self.get_presigned_response(
+            object,
+            &[
+                ("response-content-type", &content_type),
+                ("response-cache-control", THUMBNAIL_CACHE_CONTROL),
+            ],
+        )
+    }
+
+    pub async fn get_object(&self, object: &str) -> Result<Vec<u8>> {
+        let data = self
+            .send(
+                Method::GET,
+                Some(&self.get_key(object)),
+                &[],
+                Vec::new(),
+                Vec::new(),
+            )
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to get object"))?
+            .bytes()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read object"))?
+            .to_vec();
+
+        Ok(data)
+    }
+
+    pub async fn is_object_exists(&self, object: &str) -> Result<bool> {
+        let response = self
+            .send_raw(
+                Method::HEAD,
+                Some(&self.get_key(object)),
+                &[],
+                Vec::new(),
+                Vec::new(),
+            )
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(Error::new(
+                InternalServerError,
+                format!("failed to stat object: {}", status),
+            )),
+        }
+    }
+
+    pub async fn get_object_size(&self, object: &str) -> Result<Option<u64>> {
+        let response = self
+            .send_raw(
+                Method::HEAD,
+                Some(&self.get_key(object)),
+                &[],
+                Vec::new(),
+                Vec::new(),
+            )
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => {
+                let size = response
+                    .headers()
+                    .get(header::CONTENT_LENGTH)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or_else(|| {
+                        Error::new(InternalServerError, "missing content length of object")
+                    })?;
+
+                Ok(Some(size))
+            }
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(Error::new(
+                InternalServerError,
+                format!("failed to stat object: {}", status),
+            )),
+        }
+    }
+
+    pub async fn copy_object<W>(&self, object: &str, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut response = self
+            .send(
+                Method::GET,
+                Some(&self.get_key(object)),
+                &[],
+                Vec::new(),
+                Vec::new(),
+            )
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to get object"))?;
+
+        let mut size = 0;
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read object chunk"))?
+        {
+            writer.write_all(&chunk).await.map_err(|e| {
+                Error::context(InternalServerError, e, "failed to write object chunk")
+            })?;
+
+            size += chunk.len() as u64;
+        }
+
+        Ok(size)
+    }
+
+    fn get_presigned_response(&self, object: &str, query: &[(&str, &str)]) -> Result<Response> {
+        let url = self.get_presigned_url(&self.get_key(object), query)?;
+
+        Response::builder()
+            .status(StatusCode::FOUND)
+            .header(header::LOCATION, url.as_str())
+            .body(Body::empty())
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to build response for redirect to presigned url",
+                )
+            })
+    }
+}
@@ -0,0 +1,121 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::{Method, StatusCode};
+use reqwest::{Client, Url};
+
+use super::models::ServerSideEncryption;
+use super::sign::Signer;
+use super::S3;
+use crate::env::S3Env;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl S3 {
+    pub fn new(
+        S3Env {
+            endpoint,
+            region,
+            access_key,
+            secret_key,
+            session_token,
+            bucket,
+            prefix,
+            path_style,
+            server_side_encryption,
+            sse_kms_key_id,
+        }: &S3Env,
+    ) -> Result<Self> {
+        let endpoint = endpoint
+            .parse::<Url>()
+            .map_err(|e| Error::context(InternalServerError, e, "failed to parse S3 endpoint"))?;
+
+        if endpoint.host_str().is_none() {
+            return Err(Error::new(InternalServerError, "S3 endpoint has no host"));
+        }
+
+        let server_side_encryption = match server_side_encryption {
+            Some(algorithm) => Some(ServerSideEncryption::new(
+                algorithm,
+                sse_kms_key_id.as_ref(),
+            )?),
+            None if sse_kms_key_id.is_some() => {
+                return Err(Error::new(
+                    InternalServerError,
+                    "S3 KMS key id requires aws:kms server side encryption",
+                ))
+            }
+            None => None,
+        };
+
+        let prefix = match prefix.as_deref().map(|prefix| prefix.trim_matches('/')) {
+            Some(prefix) if !prefix.is_empty() => format!("{}/", prefix),
+            _ => String::new(),
+        };
+
+        let client = Client::builder()
+            .build()
+            .map_err(|e| Error::context(InternalServerError, e, "failed to create S3 client"))?;
+
+        Ok(Self {
+            client,
+            signer: Signer {
+                access_key: access_key.to_string(),
+                secret_key: secret_key.to_string(),
+                session_token: session_token.clone(),
+                region: region.to_string(),
+            },
+            endpoint,
+            bucket: bucket.to_string(),
+            prefix,
+            path_style: *path_style,
+            server_side_encryption,
+        })
+    }
+
+    pub async fn init(&self) -> Result<()> {
+        self.create_bucket_if_not_exists().await?;
+
+        Ok(())
+    }
+
+    pub async fn create_bucket_if_not_exists(&self) -> Result<()> {
+        if !self.is_bucket_exists().await? {
+            self.send(Method::PUT, None, &[], Vec::new(), Vec::new())
+                .await
+                .map_err(|e| Error::context(InternalServerError, e, "failed to make S3 bucket"))?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn check(&self) -> Result<()> {
+        if !self.is_bucket_exists().await? {
+            return Err(Error::new(
+                InternalServerError,
+                format!("S3 bucket {} does not exist", self.bucket),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn is_bucket_exists(&self) -> Result<bool> {
+        let response = self
+            .send_raw(Method::HEAD, None, &[], Vec::new(), Vec::new())
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(Error::new(
+                InternalServerError,
+                format!("failed to check S3 bucket existence: {}", status),
+            )),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::Method;
+
+use super::models::{ListBucketResult, Object};
+use super::S3;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl S3 {
+    pub async fn list_objects(&self) -> Result<Vec<Object>> {
+        self.list_objects_with_prefix("").await
+    }
+
+    // keys are listed with the storage prefix, the given prefix is relative to it
+    pub async fn list_objects_with_prefix(&self, prefix: &str) -> Result<Vec<Object>> {
+        let prefix = self.get_key(prefix);
+
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix.as_str())];
+
+            if let Some(continuation_token) = &continuation_token {
+                query.push(("continuation-token", continuation_token));
+            }
+
+            let body = self
+                .send(Method::GET, None, &query, Vec::new(), Vec::new())
+                .await
+                .map_err(|e| Error::context(InternalServerError, e, "failed to list objects"))?
+                .text()
+                .await
+                .map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        "failed to read list objects response",
+                    )
+                })?;
+
+            let ListBucketResult {
+                is_truncated,
+                contents,
+                next_continuation_token,
+            } = quick_xml::de::from_str(&body).map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to parse list objects response",
+                )
+            })?;
+
+            objects.extend(contents);
+
+            match next_continuation_token {
+                Some(next_continuation_token) if is_truncated => {
+                    continuation_token = Some(next_continuation_token)
+                }
+                _ => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    pub async fn list_object_names(&self) -> Result<Vec<String>> {
+        let objects = self.list_objects().await?;
+
+        let names = objects
+            .into_iter()
+            .filter_map(|object| {
+                object
+                    .key
+                    .strip_prefix(&self.prefix)
+                    .map(|name| name.to_string())
+            })
+            .collect();
+
+        Ok(names)
+    }
+
+    pub async fn list_object_sizes(&self) -> Result<Vec<(String, u64)>> {
+        let objects = self.list_objects().await?;
+
+        let sizes = objects
+            .into_iter()
+            .filter_map(|object| {
+                object
+                    .key
+                    .strip_prefix(&self.prefix)
+                    .map(|name| (name.to_string(), object.size))
+            })
+            .collect();
+
+        Ok(sizes)
+    }
+
+    pub async fn get_usage(&self) -> Result<u64> {
+        let objects = self.list_objects().await?;
+
+        let size = objects.iter().map(|object| object.size).sum();
+
+        Ok(size)
+    }
+}
@@ -0,0 +1,38 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+mod backend;
+mod download;
+mod init;
+mod list;
+mod models;
+mod remove;
+mod request;
+mod sign;
+#[cfg(test)]
+pub mod stand_in;
+#[cfg(test)]
+pub mod tests;
+mod upload;
+
+use reqwest::{Client, Url};
+
+use models::ServerSideEncryption;
+use sign::Signer;
+
+// talks to any S3 compatible service with signature v4
+#[derive(Debug, Clone)]
+pub struct S3 {
+    client: Client,
+    signer: Signer,
+    endpoint: Url,
+    pub bucket: String,
+    // empty or ending with a slash
+    prefix: String,
+    path_style: bool,
+    server_side_encryption: Option<ServerSideEncryption>,
+}
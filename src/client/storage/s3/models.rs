@@ -0,0 +1,133 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+pub const X_AMZ_SERVER_SIDE_ENCRYPTION: &str = "x-amz-server-side-encryption";
+pub const X_AMZ_SERVER_SIDE_ENCRYPTION_KMS_KEY_ID: &str =
+    "x-amz-server-side-encryption-aws-kms-key-id";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerSideEncryption {
+    Aes256,
+    // the bucket default key is used if no key id is given
+    Kms(Option<String>),
+}
+
+impl ServerSideEncryption {
+    pub fn new(algorithm: &str, kms_key_id: Option<&String>) -> Result<Self> {
+        match (algorithm, kms_key_id) {
+            ("AES256", None) => Ok(Self::Aes256),
+            ("aws:kms", kms_key_id) => Ok(Self::Kms(kms_key_id.cloned())),
+            ("AES256", Some(_)) => Err(Error::new(
+                InternalServerError,
+                "S3 KMS key id requires aws:kms server side encryption",
+            )),
+            _ => Err(Error::new(
+                InternalServerError,
+                "S3 server side encryption must be one of AES256 or aws:kms",
+            )),
+        }
+    }
+
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Aes256 => vec![(X_AMZ_SERVER_SIDE_ENCRYPTION, "AES256".to_string())],
+            Self::Kms(kms_key_id) => {
+                let mut headers = vec![(X_AMZ_SERVER_SIDE_ENCRYPTION, "aws:kms".to_string())];
+
+                if let Some(kms_key_id) = kms_key_id {
+                    headers.push((X_AMZ_SERVER_SIDE_ENCRYPTION_KMS_KEY_ID, kms_key_id.clone()));
+                }
+
+                headers
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ErrorResponse {
+    pub code: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListBucketResult {
+    #[serde(default)]
+    pub is_truncated: bool,
+    #[serde(default)]
+    pub contents: Vec<Object>,
+    pub next_continuation_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Object {
+    pub key: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InitiateMultipartUploadResult {
+    pub upload_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "CompleteMultipartUpload")]
+pub struct CompleteMultipartUpload {
+    #[serde(rename = "Part")]
+    pub parts: Vec<CompletePart>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CompletePart {
+    pub part_number: u16,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "Delete")]
+pub struct Delete {
+    #[serde(rename = "Quiet")]
+    pub quiet: bool,
+    #[serde(rename = "Object")]
+    pub objects: Vec<DeleteObject>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteObject {
+    pub key: String,
+}
+
+// only failures are listed in quiet mode
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteResult {
+    #[serde(default, rename = "Error")]
+    pub errors: Vec<DeleteError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeleteError {
+    pub key: String,
+    pub code: String,
+    #[serde(default)]
+    pub message: String,
+}
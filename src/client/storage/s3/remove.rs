@@ -0,0 +1,124 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::Method;
+use base64::engine::general_purpose::STANDARD as base64;
+use base64::Engine;
+
+use super::models::{Delete, DeleteObject, DeleteResult, Object};
+use super::S3;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+// the most keys a single delete request takes
+const DELETE_BATCH_SIZE: usize = 1000;
+
+impl S3 {
+    pub async fn remove_object(&self, object: &str) -> Result<()> {
+        self.send(
+            Method::DELETE,
+            Some(&self.get_key(object)),
+            &[],
+            Vec::new(),
+            Vec::new(),
+        )
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to remove object"))?;
+
+        Ok(())
+    }
+
+    pub async fn remove_prefix(&self, prefix: &str) -> Result<()> {
+        // prefixes are virtual in s3, only the objects under them need removing
+        let prefix = format!("{}/", prefix.trim_end_matches('/'));
+
+        let objects = self.list_objects_with_prefix(&prefix).await?;
+
+        self.remove_objects(objects).await
+    }
+
+    // only objects under the storage prefix are removed
+    pub async fn remove_objects_all(&self) -> Result<()> {
+        let objects = self.list_objects().await?;
+
+        self.remove_objects(objects).await
+    }
+
+    async fn remove_objects(&self, objects: Vec<Object>) -> Result<()> {
+        for objects in objects.chunks(DELETE_BATCH_SIZE) {
+            let delete = Delete {
+                quiet: true,
+                objects: objects
+                    .iter()
+                    .map(|object| DeleteObject {
+                        key: object.key.clone(),
+                    })
+                    .collect(),
+            };
+
+            let body = quick_xml::se::to_string(&delete).map_err(|e| {
+                Error::context(InternalServerError, e, "failed to serialize delete objects")
+            })?;
+
+            // required by s3 for multi-object delete
+            let content_md5 = base64.encode(md5::compute(&body).0);
+
+            let body = self
+                .send(
+                    Method::POST,
+                    None,
+                    &[("delete", "")],
+                    vec![("content-md5", content_md5)],
+                    body.into_bytes(),
+                )
+                .await
+                .map_err(|e| Error::context(InternalServerError, e, "failed to remove objects"))?
+                .text()
+                .await
+                .map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        "failed to read remove objects response",
+                    )
+                })?;
+
+            let DeleteResult { errors } = quick_xml::de::from_str(&body).map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to parse remove objects response",
+                )
+            })?;
+
+            if !errors.is_empty() {
+                let errors = errors
+                    .iter()
+                    .map(|error| format!("{}: {} {}", error.key, error.code, error.message))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("failed to remove objects:\n{}", errors),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn _remove_bucket(&self) -> Result<()> {
+        self.remove_objects_all().await?;
+
+        self.send(Method::DELETE, None, &[], Vec::new(), Vec::new())
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to remove bucket"))?;
+
+        Ok(())
+    }
+}
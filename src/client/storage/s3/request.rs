@@ -0,0 +1,146 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method};
+use chrono::Utc;
+use reqwest::{Response, Url};
+
+use super::models::ErrorResponse;
+use super::sign::{get_canonical_query, hash_payload};
+use super::S3;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::utils::uri_encode;
+
+// the longest expiration allowed by signature v4
+const PRESIGN_EXPIRES: u64 = 7 * 24 * 3600; // 7 days
+
+impl S3 {
+    // objects are stored under the prefix
+    pub(super) fn get_key(&self, object: &str) -> String {
+        format!("{}{}", self.prefix, object)
+    }
+
+    pub(super) fn get_url(&self, key: Option<&str>, query: &[(&str, &str)]) -> Result<Url> {
+        let mut url = self.endpoint.clone();
+
+        let key_path = key.map(|key| uri_encode(key, false)).unwrap_or_default();
+
+        let path = if self.path_style {
+            match key {
+                Some(_) => format!("/{}/{}", self.bucket, key_path),
+                None => format!("/{}", self.bucket),
+            }
+        } else {
+            let host = format!(
+                "{}.{}",
+                self.bucket,
+                self.endpoint.host_str().unwrap_or_default()
+            );
+
+            url.set_host(Some(&host)).map_err(|e| {
+                Error::context(InternalServerError, e, "failed to set S3 bucket host")
+            })?;
+
+            format!("/{}", key_path)
+        };
+
+        url.set_path(&path);
+
+        let query = query
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<Vec<(String, String)>>();
+
+        if query.is_empty() {
+            url.set_query(None);
+        } else {
+            url.set_query(Some(&get_canonical_query(&query)));
+        }
+
+        Ok(url)
+    }
+
+    pub(super) fn get_presigned_url(&self, key: &str, query: &[(&str, &str)]) -> Result<Url> {
+        let mut url = self.get_url(Some(key), query)?;
+
+        self.signer
+            .presign(&Method::GET, &mut url, PRESIGN_EXPIRES, Utc::now());
+
+        Ok(url)
+    }
+
+    // fails on error statuses with the code returned by S3
+    pub(super) async fn send(
+        &self,
+        method: Method,
+        key: Option<&str>,
+        query: &[(&str, &str)],
+        headers: Vec<(&str, String)>,
+        body: Vec<u8>,
+    ) -> Result<Response> {
+        let response = self.send_raw(method, key, query, headers, body).await?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+
+        let message = match quick_xml::de::from_str::<ErrorResponse>(&body) {
+            Ok(ErrorResponse { code, message }) => format!("{} {}", code, message),
+            Err(_) => body,
+        };
+
+        Err(Error::new(
+            InternalServerError,
+            format!("S3 request failed with {}: {}", status, message.trim()),
+        ))
+    }
+
+    pub(super) async fn send_raw(
+        &self,
+        method: Method,
+        key: Option<&str>,
+        query: &[(&str, &str)],
+        headers: Vec<(&str, String)>,
+        body: Vec<u8>,
+    ) -> Result<Response> {
+        let url = self.get_url(key, query)?;
+
+        let mut header_map = HeaderMap::new();
+
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                Error::context(InternalServerError, e, "invalid header name for S3")
+            })?;
+            let value = HeaderValue::from_str(&value).map_err(|e| {
+                Error::context(InternalServerError, e, "invalid header value for S3")
+            })?;
+
+            header_map.insert(name, value);
+        }
+
+        self.signer.sign(
+            &method,
+            &url,
+            &mut header_map,
+            &hash_payload(&body),
+            Utc::now(),
+        )?;
+
+        self.client
+            .request(method, url)
+            .headers(header_map)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to send S3 request"))
+    }
+}
@@ -0,0 +1,247 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::{header, HeaderMap, HeaderValue, Method};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use reqwest::Url;
+use ring::hmac;
+use sha2::{Digest, Sha256};
+
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::utils::uri_encode;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const SERVICE: &str = "s3";
+pub const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+pub const X_AMZ_DATE: &str = "x-amz-date";
+pub const X_AMZ_CONTENT_SHA256: &str = "x-amz-content-sha256";
+pub const X_AMZ_SECURITY_TOKEN: &str = "x-amz-security-token";
+
+#[derive(Debug, Clone)]
+pub struct Signer {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
+impl Signer {
+    // adds the date, payload hash, session token and authorization headers
+    pub fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &mut HeaderMap,
+        payload_hash: &str,
+        time: DateTime<Utc>,
+    ) -> Result<()> {
+        let amz_date = format_amz_date(time);
+
+        headers.insert(header::HOST, to_header_value(&get_host(url))?);
+        headers.insert(X_AMZ_DATE, to_header_value(&amz_date)?);
+        headers.insert(X_AMZ_CONTENT_SHA256, to_header_value(payload_hash)?);
+
+        if let Some(session_token) = &self.session_token {
+            headers.insert(X_AMZ_SECURITY_TOKEN, to_header_value(session_token)?);
+        }
+
+        let mut signed_headers = headers
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().map_err(|e| {
+                    Error::context(InternalServerError, e, "invalid header value to sign")
+                })?;
+
+                Ok((name.as_str().to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<Vec<(String, String)>>>()?;
+
+        signed_headers.sort();
+
+        let signed_header_names = get_signed_header_names(&signed_headers);
+
+        let signature = self.calculate_signature(
+            method.as_str(),
+            url.path(),
+            &get_query_pairs(url),
+            &signed_headers,
+            payload_hash,
+            &amz_date,
+        );
+
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM,
+            self.access_key,
+            self.get_scope(&amz_date),
+            signed_header_names,
+            signature
+        );
+
+        headers.insert(header::AUTHORIZATION, to_header_value(&authorization)?);
+
+        Ok(())
+    }
+
+    // only the host is signed, so the url can be opened by anyone who has it
+    pub fn presign(&self, method: &Method, url: &mut Url, expires: u64, time: DateTime<Utc>) {
+        let amz_date = format_amz_date(time);
+
+        let mut query_pairs = get_query_pairs(url);
+
+        query_pairs.extend([
+            ("X-Amz-Algorithm".to_string(), ALGORITHM.to_string()),
+            (
+                "X-Amz-Credential".to_string(),
+                format!("{}/{}", self.access_key, self.get_scope(&amz_date)),
+            ),
+            ("X-Amz-Date".to_string(), amz_date.clone()),
+            ("X-Amz-Expires".to_string(), expires.to_string()),
+            ("X-Amz-SignedHeaders".to_string(), "host".to_string()),
+        ]);
+
+        if let Some(session_token) = &self.session_token {
+            query_pairs.push(("X-Amz-Security-Token".to_string(), session_token.clone()));
+        }
+
+        let signature = self.calculate_signature(
+            method.as_str(),
+            url.path(),
+            &query_pairs,
+            &[("host".to_string(), get_host(url))],
+            UNSIGNED_PAYLOAD,
+            &amz_date,
+        );
+
+        query_pairs.push(("X-Amz-Signature".to_string(), signature));
+
+        url.set_query(Some(&get_canonical_query(&query_pairs)));
+    }
+
+    // headers must be lowercase and sorted, the path must already be uri encoded
+    pub fn calculate_signature(
+        &self,
+        method: &str,
+        path: &str,
+        query_pairs: &[(String, String)],
+        headers: &[(String, String)],
+        payload_hash: &str,
+        amz_date: &str,
+    ) -> String {
+        let canonical_headers = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect::<String>();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method,
+            path,
+            get_canonical_query(query_pairs),
+            canonical_headers,
+            get_signed_header_names(headers),
+            payload_hash
+        );
+
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            amz_date,
+            self.get_scope(amz_date),
+            hash_payload(canonical_request.as_bytes())
+        );
+
+        let date = &amz_date[..8];
+
+        let key = hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), date);
+        let key = hmac_sha256(&key, &self.region);
+        let key = hmac_sha256(&key, SERVICE);
+        let key = hmac_sha256(&key, "aws4_request");
+
+        to_hex(&hmac_sha256(&key, &string_to_sign))
+    }
+
+    fn get_scope(&self, amz_date: &str) -> String {
+        format!(
+            "{}/{}/{}/aws4_request",
+            &amz_date[..8],
+            self.region,
+            SERVICE
+        )
+    }
+}
+
+pub fn hash_payload(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub fn get_canonical_query(query_pairs: &[(String, String)]) -> String {
+    let mut query_pairs = query_pairs
+        .iter()
+        .map(|(key, value)| (uri_encode(key, true), uri_encode(value, true)))
+        .collect::<Vec<(String, String)>>();
+
+    query_pairs.sort();
+
+    query_pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+pub fn get_query_pairs(url: &Url) -> Vec<(String, String)> {
+    url.query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
+
+pub fn format_amz_date(time: DateTime<Utc>) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        time.year(),
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+fn get_host(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+fn get_signed_header_names(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<&str>>()
+        .join(";")
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+
+    hmac::sign(&key, data.as_bytes()).as_ref().to_vec()
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn to_header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value)
+        .map_err(|e| Error::context(InternalServerError, e, "invalid header value for S3"))
+}
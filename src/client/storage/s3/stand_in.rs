@@ -0,0 +1,688 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+// a minimal S3 compatible server, so the S3 backend can be tested without a real service
+
+use axum::body::{Body, Bytes};
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::response::Response;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD as base64;
+use base64::Engine;
+use quick_xml::escape::escape;
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::TcpListener as StdTcpListener;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+use super::sign::{hash_payload, Signer, UNSIGNED_PAYLOAD, X_AMZ_CONTENT_SHA256, X_AMZ_DATE};
+use crate::env::S3Env;
+
+pub const STAND_IN_ACCESS_KEY: &str = "transfery-access-key";
+pub const STAND_IN_SECRET_KEY: &str = "transfery-secret-key";
+pub const STAND_IN_SESSION_TOKEN: &str = "transfery-session-token";
+pub const STAND_IN_REGION: &str = "eu-central-1";
+pub const STAND_IN_BUCKET: &str = "transfery-test";
+
+// small pages, so listing has to follow continuation tokens
+const LIST_PAGE_SIZE: usize = 2;
+
+static STAND_IN: OnceLock<(String, Arc<StandIn>)> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub data: Vec<u8>,
+    pub server_side_encryption: Option<String>,
+}
+
+#[derive(Debug)]
+struct Upload {
+    bucket: String,
+    key: String,
+    server_side_encryption: Option<String>,
+    parts: BTreeMap<u16, (String, Vec<u8>)>,
+}
+
+#[derive(Debug, Default)]
+pub struct StandIn {
+    buckets: Mutex<HashMap<String, BTreeMap<String, StoredObject>>>,
+    uploads: Mutex<HashMap<String, Upload>>,
+}
+
+impl StandIn {
+    pub fn is_upload_exists(&self, upload_id: &str) -> bool {
+        self.uploads.lock().unwrap().contains_key(upload_id)
+    }
+
+    pub fn get_object(&self, bucket: &str, key: &str) -> Option<StoredObject> {
+        self.buckets
+            .lock()
+            .unwrap()
+            .get(bucket)
+            .and_then(|objects| objects.get(key).cloned())
+    }
+}
+
+// the server runs on its own thread, so it outlives the runtime of every test
+pub fn get_stand_in() -> (String, Arc<StandIn>) {
+    let (endpoint, stand_in) = STAND_IN.get_or_init(|| {
+        let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let stand_in = Arc::new(StandIn::default());
+        let state = stand_in.clone();
+
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            runtime.block_on(async move {
+                let listener = TcpListener::from_std(listener).unwrap();
+
+                let router = Router::new()
+                    .fallback(handle)
+                    .layer(DefaultBodyLimit::disable())
+                    .with_state(state);
+
+                axum::serve(listener, router).await.unwrap();
+            });
+        });
+
+        (endpoint, stand_in)
+    });
+
+    (endpoint.clone(), stand_in.clone())
+}
+
+pub fn get_stand_in_env() -> S3Env {
+    let (endpoint, _) = get_stand_in();
+
+    S3Env {
+        endpoint,
+        region: STAND_IN_REGION.to_string(),
+        access_key: STAND_IN_ACCESS_KEY.to_string(),
+        secret_key: STAND_IN_SECRET_KEY.to_string(),
+        session_token: Some(STAND_IN_SESSION_TOKEN.to_string()),
+        bucket: STAND_IN_BUCKET.to_string(),
+        prefix: Some("transfery".to_string()),
+        path_style: true,
+        server_side_encryption: Some("AES256".to_string()),
+        sse_kms_key_id: None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CompleteMultipartUpload {
+    #[serde(default)]
+    part: Vec<CompletePart>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CompletePart {
+    part_number: u16,
+    #[serde(rename = "ETag")]
+    etag: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Delete {
+    #[serde(default)]
+    object: Vec<DeleteObject>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeleteObject {
+    key: String,
+}
+
+struct Request {
+    method: Method,
+    bucket: String,
+    key: Option<String>,
+    query: HashMap<String, String>,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Request {
+    fn has_query(&self, name: &str) -> bool {
+        self.query.contains_key(name)
+    }
+
+    fn get_header(&self, name: &str) -> Option<String> {
+        self.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    }
+}
+
+async fn handle(
+    State(stand_in): State<Arc<StandIn>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let url = Url::parse(&format!("http://stand-in{}", uri)).unwrap();
+
+    let query_pairs = url
+        .query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<(String, String)>>();
+
+    if let Err(message) = verify(&method, uri.path(), &query_pairs, &headers, &body) {
+        return error_response(StatusCode::FORBIDDEN, "SignatureDoesNotMatch", &message);
+    }
+
+    let path = uri.path().trim_start_matches('/');
+
+    let (bucket, key) = match path.split_once('/') {
+        Some((bucket, key)) => (bucket.to_string(), Some(uri_decode(key))),
+        None => (path.to_string(), None),
+    };
+
+    let request = Request {
+        method,
+        bucket,
+        key,
+        query: query_pairs.into_iter().collect(),
+        headers,
+        body,
+    };
+
+    match &request.key {
+        Some(key) => handle_object(&stand_in, &request, key),
+        None => handle_bucket(&stand_in, &request),
+    }
+}
+
+fn verify(
+    method: &Method,
+    path: &str,
+    query_pairs: &[(String, String)],
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), String> {
+    let signer = Signer {
+        access_key: STAND_IN_ACCESS_KEY.to_string(),
+        secret_key: STAND_IN_SECRET_KEY.to_string(),
+        session_token: Some(STAND_IN_SESSION_TOKEN.to_string()),
+        region: STAND_IN_REGION.to_string(),
+    };
+
+    let get_header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .ok_or_else(|| format!("missing header {}", name))
+    };
+
+    let get_query = |name: &str| {
+        query_pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    // presigned url
+    if let Some(signature) = get_query("X-Amz-Signature") {
+        if get_query("X-Amz-Security-Token").as_deref() != Some(STAND_IN_SESSION_TOKEN) {
+            return Err("invalid session token".to_string());
+        }
+
+        let amz_date = get_query("X-Amz-Date").ok_or("missing X-Amz-Date")?;
+
+        let query_pairs = query_pairs
+            .iter()
+            .filter(|(key, _)| key != "X-Amz-Signature")
+            .cloned()
+            .collect::<Vec<(String, String)>>();
+
+        let expected = signer.calculate_signature(
+            method.as_str(),
+            path,
+            &query_pairs,
+            &[("host".to_string(), get_header("host")?)],
+            UNSIGNED_PAYLOAD,
+            &amz_date,
+        );
+
+        return match signature == expected {
+            true => Ok(()),
+            false => Err("presigned signature mismatch".to_string()),
+        };
+    }
+
+    let authorization = get_header("authorization")?;
+
+    let fields = authorization
+        .trim_start_matches("AWS4-HMAC-SHA256")
+        .split(',')
+        .filter_map(|field| field.trim().split_once('='))
+        .collect::<HashMap<&str, &str>>();
+
+    let credential = fields.get("Credential").ok_or("missing credential")?;
+
+    if !credential.starts_with(&format!("{}/", STAND_IN_ACCESS_KEY)) {
+        return Err("invalid access key".to_string());
+    }
+
+    if get_header("x-amz-security-token")? != STAND_IN_SESSION_TOKEN {
+        return Err("invalid session token".to_string());
+    }
+
+    let payload_hash = get_header(X_AMZ_CONTENT_SHA256)?;
+
+    if payload_hash != hash_payload(body) {
+        return Err("payload hash mismatch".to_string());
+    }
+
+    let signed_headers = fields
+        .get("SignedHeaders")
+        .ok_or("missing signed headers")?
+        .split(';')
+        .map(|name| Ok((name.to_string(), get_header(name)?)))
+        .collect::<Result<Vec<(String, String)>, String>>()?;
+
+    let expected = signer.calculate_signature(
+        method.as_str(),
+        path,
+        query_pairs,
+        &signed_headers,
+        &payload_hash,
+        &get_header(X_AMZ_DATE)?,
+    );
+
+    match fields.get("Signature") == Some(&expected.as_str()) {
+        true => Ok(()),
+        false => Err("signature mismatch".to_string()),
+    }
+}
+
+fn handle_bucket(stand_in: &StandIn, request: &Request) -> Response {
+    let mut buckets = stand_in.buckets.lock().unwrap();
+
+    match request.method {
+        Method::HEAD => match buckets.contains_key(&request.bucket) {
+            true => empty_response(StatusCode::OK),
+            false => empty_response(StatusCode::NOT_FOUND),
+        },
+        Method::PUT => {
+            buckets.entry(request.bucket.clone()).or_default();
+
+            empty_response(StatusCode::OK)
+        }
+        Method::DELETE => match buckets.get(&request.bucket) {
+            None => no_such_bucket(),
+            Some(objects) if !objects.is_empty() => error_response(
+                StatusCode::CONFLICT,
+                "BucketNotEmpty",
+                "The bucket you tried to delete is not empty",
+            ),
+            Some(_) => {
+                buckets.remove(&request.bucket);
+
+                empty_response(StatusCode::NO_CONTENT)
+            }
+        },
+        Method::GET if request.query.get("list-type").map(|value| value.as_str()) == Some("2") => {
+            let objects = match buckets.get(&request.bucket) {
+                Some(objects) => objects,
+                None => return no_such_bucket(),
+            };
+
+            let prefix = request.query.get("prefix").cloned().unwrap_or_default();
+            let start_after = request.query.get("continuation-token");
+
+            let keys = objects
+                .iter()
+                .filter(|(key, _)| key.starts_with(&prefix))
+                .filter(|(key, _)| match start_after {
+                    Some(start_after) => *key > start_after,
+                    None => true,
+                })
+                .collect::<Vec<(&String, &StoredObject)>>();
+
+            let is_truncated = keys.len() > LIST_PAGE_SIZE;
+
+            let mut body = format!(
+                "<ListBucketResult><Name>{}</Name><Prefix>{}</Prefix><IsTruncated>{}</IsTruncated>",
+                escape(&request.bucket),
+                escape(&prefix),
+                is_truncated
+            );
+
+            for (key, object) in keys.iter().take(LIST_PAGE_SIZE) {
+                body.push_str(&format!(
+                    "<Contents><Key>{}</Key><Size>{}</Size></Contents>",
+                    escape(key.as_str()),
+                    object.data.len()
+                ));
+            }
+
+            if is_truncated {
+                body.push_str(&format!(
+                    "<NextContinuationToken>{}</NextContinuationToken>",
+                    escape(keys[LIST_PAGE_SIZE - 1].0.as_str())
+                ));
+            }
+
+            body.push_str("</ListBucketResult>");
+
+            xml_response(body)
+        }
+        Method::POST if request.has_query("delete") => {
+            let content_md5 = base64.encode(md5::compute(&request.body).0);
+
+            if request.get_header("content-md5") != Some(content_md5) {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "InvalidDigest",
+                    "The Content-MD5 you specified was invalid",
+                );
+            }
+
+            let delete: Delete =
+                match quick_xml::de::from_str(std::str::from_utf8(&request.body).unwrap()) {
+                    Ok(delete) => delete,
+                    Err(e) => {
+                        return error_response(
+                            StatusCode::BAD_REQUEST,
+                            "MalformedXML",
+                            &e.to_string(),
+                        )
+                    }
+                };
+
+            let objects = match buckets.get_mut(&request.bucket) {
+                Some(objects) => objects,
+                None => return no_such_bucket(),
+            };
+
+            for DeleteObject { key } in delete.object {
+                objects.remove(&key);
+            }
+
+            xml_response("<DeleteResult></DeleteResult>".to_string())
+        }
+        _ => error_response(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "Not implemented by the stand-in",
+        ),
+    }
+}
+
+fn handle_object(stand_in: &StandIn, request: &Request, key: &str) -> Response {
+    let mut buckets = stand_in.buckets.lock().unwrap();
+    let mut uploads = stand_in.uploads.lock().unwrap();
+
+    let objects = match buckets.get_mut(&request.bucket) {
+        Some(objects) => objects,
+        None => return no_such_bucket(),
+    };
+
+    let server_side_encryption = request.get_header("x-amz-server-side-encryption");
+
+    match request.method {
+        Method::PUT if request.has_query("uploadId") => {
+            let upload = match uploads.get_mut(&request.query["uploadId"]) {
+                Some(upload) if upload.bucket == request.bucket && upload.key == key => upload,
+                _ => return no_such_upload(),
+            };
+
+            let part_number = match request.query.get("partNumber").map(|value| value.parse()) {
+                Some(Ok(part_number)) => part_number,
+                _ => {
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        "InvalidArgument",
+                        "Part number must be an integer",
+                    )
+                }
+            };
+
+            let etag = gen_etag(&request.body);
+
+            upload
+                .parts
+                .insert(part_number, (etag.clone(), request.body.to_vec()));
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::ETAG, etag)
+                .body(Body::empty())
+                .unwrap()
+        }
+        Method::PUT => {
+            let etag = gen_etag(&request.body);
+
+            objects.insert(
+                key.to_string(),
+                StoredObject {
+                    data: request.body.to_vec(),
+                    server_side_encryption,
+                },
+            );
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::ETAG, etag)
+                .body(Body::empty())
+                .unwrap()
+        }
+        Method::POST if request.has_query("uploads") => {
+            let upload_id = Uuid::new_v4().to_string();
+
+            uploads.insert(
+                upload_id.clone(),
+                Upload {
+                    bucket: request.bucket.clone(),
+                    key: key.to_string(),
+                    server_side_encryption,
+                    parts: BTreeMap::new(),
+                },
+            );
+
+            xml_response(format!(
+                "<InitiateMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                escape(&request.bucket),
+                escape(key),
+                upload_id
+            ))
+        }
+        Method::POST if request.has_query("uploadId") => {
+            let upload = match uploads.remove(&request.query["uploadId"]) {
+                Some(upload) if upload.bucket == request.bucket && upload.key == key => upload,
+                _ => return no_such_upload(),
+            };
+
+            let complete: CompleteMultipartUpload =
+                match quick_xml::de::from_str(std::str::from_utf8(&request.body).unwrap()) {
+                    Ok(complete) => complete,
+                    Err(e) => {
+                        return error_response(
+                            StatusCode::BAD_REQUEST,
+                            "MalformedXML",
+                            &e.to_string(),
+                        )
+                    }
+                };
+
+            let mut data = Vec::new();
+
+            for CompletePart { part_number, etag } in complete.part {
+                match upload.parts.get(&part_number) {
+                    Some((part_etag, part_data)) if *part_etag == etag => {
+                        data.extend_from_slice(part_data)
+                    }
+                    _ => {
+                        return error_response(
+                            StatusCode::BAD_REQUEST,
+                            "InvalidPart",
+                            "One or more of the specified parts could not be found",
+                        )
+                    }
+                }
+            }
+
+            let etag = gen_etag(&data);
+
+            objects.insert(
+                key.to_string(),
+                StoredObject {
+                    data,
+                    server_side_encryption: upload.server_side_encryption,
+                },
+            );
+
+            xml_response(format!(
+                "<CompleteMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key><ETag>{}</ETag></CompleteMultipartUploadResult>",
+                escape(&request.bucket),
+                escape(key),
+                escape(&etag)
+            ))
+        }
+        Method::GET => match objects.get(key) {
+            Some(object) => {
+                let mut response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_LENGTH, object.data.len());
+
+                for (param, name) in [
+                    ("response-content-type", header::CONTENT_TYPE),
+                    ("response-content-disposition", header::CONTENT_DISPOSITION),
+                    ("response-cache-control", header::CACHE_CONTROL),
+                ] {
+                    if let Some(value) = request.query.get(param) {
+                        response = response.header(name, value);
+                    }
+                }
+
+                response.body(Body::from(object.data.clone())).unwrap()
+            }
+            None => no_such_key(),
+        },
+        Method::HEAD => match objects.get(key) {
+            Some(object) => Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_LENGTH, object.data.len())
+                .body(Body::empty())
+                .unwrap(),
+            None => empty_response(StatusCode::NOT_FOUND),
+        },
+        Method::DELETE if request.has_query("uploadId") => {
+            match uploads.remove(&request.query["uploadId"]) {
+                Some(upload) if upload.bucket == request.bucket && upload.key == key => {
+                    empty_response(StatusCode::NO_CONTENT)
+                }
+                _ => no_such_upload(),
+            }
+        }
+        Method::DELETE => {
+            objects.remove(key);
+
+            empty_response(StatusCode::NO_CONTENT)
+        }
+        _ => error_response(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "Not implemented by the stand-in",
+        ),
+    }
+}
+
+fn uri_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match (bytes[index], value.get(index + 1..index + 3)) {
+            (b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+                result.push(u8::from_str_radix(hex, 16).unwrap());
+                index += 3;
+            }
+            (byte, _) => {
+                result.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+fn gen_etag(data: &[u8]) -> String {
+    format!("\"{:x}\"", md5::compute(data))
+}
+
+fn empty_response(status: StatusCode) -> Response {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn xml_response(body: String) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/xml")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, code: &str, message: &str) -> Response {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/xml")
+        .body(Body::from(format!(
+            "<Error><Code>{}</Code><Message>{}</Message></Error>",
+            code,
+            escape(message)
+        )))
+        .unwrap()
+}
+
+fn no_such_bucket() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "NoSuchBucket",
+        "The specified bucket does not exist",
+    )
+}
+
+fn no_such_key() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "NoSuchKey",
+        "The specified key does not exist",
+    )
+}
+
+fn no_such_upload() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "NoSuchUpload",
+        "The specified multipart upload does not exist",
+    )
+}
@@ -0,0 +1,188 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::{header, Method};
+
+use super::super::models::Part;
+use super::models::{
+    CompleteMultipartUpload, CompletePart, ErrorResponse, InitiateMultipartUploadResult,
+};
+use super::S3;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+impl S3 {
+    pub async fn create_multipart_upload_id(&self, object: &str) -> Result<String> {
+        let body = self
+            .send(
+                Method::POST,
+                Some(&self.get_key(object)),
+                &[("uploads", "")],
+                self.get_encryption_headers(),
+                Vec::new(),
+            )
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to get multipart upload response",
+                )
+            })?
+            .text()
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to read multipart upload response",
+                )
+            })?;
+
+        let InitiateMultipartUploadResult { upload_id } =
+            quick_xml::de::from_str(&body).map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to parse multipart upload response",
+                )
+            })?;
+
+        Ok(upload_id)
+    }
+
+    pub async fn multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        part_data: &[u8],
+        part_number: u16,
+    ) -> Result<Part> {
+        let response = self
+            .send(
+                Method::PUT,
+                Some(&self.get_key(object)),
+                &[
+                    ("partNumber", &part_number.to_string()),
+                    ("uploadId", upload_id),
+                ],
+                Vec::new(),
+                part_data.to_vec(),
+            )
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to upload part"))?;
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .ok_or_else(|| Error::new(InternalServerError, "no etag in upload part response"))?
+            .to_string();
+
+        Ok(Part {
+            number: part_number,
+            etag,
+        })
+    }
+
+    pub async fn complete_multipart_upload(
+        &self,
+        object: &str,
+        upload_id: &str,
+        parts: &[Part],
+    ) -> Result<()> {
+        let complete = CompleteMultipartUpload {
+            parts: parts
+                .iter()
+                .map(|part| CompletePart {
+                    part_number: part.number,
+                    etag: part.etag.clone(),
+                })
+                .collect(),
+        };
+
+        let body = quick_xml::se::to_string(&complete).map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to serialize complete multipart upload",
+            )
+        })?;
+
+        let body = self
+            .send(
+                Method::POST,
+                Some(&self.get_key(object)),
+                &[("uploadId", upload_id)],
+                Vec::new(),
+                body.into_bytes(),
+            )
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to complete multipart upload",
+                )
+            })?
+            .text()
+            .await
+            .map_err(|e| {
+                Error::context(
+                    InternalServerError,
+                    e,
+                    "failed to read complete multipart upload response",
+                )
+            })?;
+
+        // s3 may fail after sending the status, the error is in the body then
+        if let Ok(ErrorResponse { code, message }) = quick_xml::de::from_str(&body) {
+            return Err(Error::new(
+                InternalServerError,
+                format!("failed to complete multipart upload: {} {}", code, message),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn abort_multipart_upload(&self, object: &str, upload_id: &str) -> Result<()> {
+        self.send(
+            Method::DELETE,
+            Some(&self.get_key(object)),
+            &[("uploadId", upload_id)],
+            Vec::new(),
+            Vec::new(),
+        )
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to abort multipart upload"))?;
+
+        Ok(())
+    }
+
+    pub async fn put_object(&self, object: &str, data: &[u8]) -> Result<()> {
+        self.send(
+            Method::PUT,
+            Some(&self.get_key(object)),
+            &[],
+            self.get_encryption_headers(),
+            data.to_vec(),
+        )
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to put object"))?;
+
+        Ok(())
+    }
+
+    // parts inherit the encryption of their multipart upload
+    fn get_encryption_headers(&self) -> Vec<(&str, String)> {
+        self.server_side_encryption
+            .as_ref()
+            .map(|server_side_encryption| server_side_encryption.headers())
+            .unwrap_or_default()
+    }
+}
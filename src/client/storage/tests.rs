@@ -5,55 +5,60 @@
 :license: MIT, see LICENSE for more details.
 */
 
+use axum::body::to_bytes;
+use axum::http::HeaderMap;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
 use strum::IntoEnumIterator;
 
-use super::local::tests::{
-    get_storage as get_storage_local, init as init_local, reset as reset_local,
-    upload_data as upload_data_local,
-};
-use super::minio::tests::{
-    get_storage as get_storage_minio, init as init_minio, reset as reset_minio,
-    upload_data as upload_data_minio,
-};
-use super::models::StorageClient;
-use super::utils::tests::fake_data;
+use super::models::ArchiveEntry;
+pub use super::s3::stand_in::get_stand_in_env;
+use super::thumbnail::gen_thumbnail;
+pub use super::utils::tests::{fake_data, fake_image};
+use super::utils::{guess_mime_type, guess_presigned_preview_mime_type};
 use super::Storage;
 use crate::client::storage::models::Part;
-use crate::env::tests::STType;
-use crate::error::Result;
+use crate::crypto::tests::get_crypto;
+use crate::env::tests::{get_env, DBType, STType};
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
 use crate::utils::tests::{sleep, sleep_async};
 
 // s3 minimum allowed size is 5MB
 pub static PART_SIZE: u32 = 5 * 1024 * 1024; // 5MB
 
 pub async fn get_storage(st_type: STType) -> Storage {
-    let client = match st_type {
-        STType::Minio => StorageClient::Minio(get_storage_minio().await),
-        STType::LocalStorage => StorageClient::Local(get_storage_local().await),
-    };
+    let env = get_env(DBType::Sqlite, st_type);
 
-    Storage { client }
+    Storage::new(&env.storage, None).await.unwrap()
 }
 
-pub async fn init(storage: &Storage) -> Result<()> {
-    match &storage.client {
-        StorageClient::Minio(storage) => init_minio(storage).await,
-        StorageClient::Local(storage) => init_local(storage).await,
+pub async fn get_storage_encrypted(st_type: STType) -> Storage {
+    Storage {
+        crypto: Some(get_crypto()),
+        ..get_storage(st_type).await
     }
 }
 
-pub async fn reset(storage: &Storage) {
-    match &storage.client {
-        StorageClient::Minio(storage) => reset_minio(storage).await,
-        StorageClient::Local(storage) => reset_local(storage).await,
+fn without_crypto(storage: &Storage) -> Storage {
+    Storage {
+        crypto: None,
+        ..storage.clone()
     }
 }
 
+pub async fn init(storage: &Storage) -> Result<()> {
+    storage.backend.init().await
+}
+
+pub async fn reset(storage: &Storage) {
+    storage.backend._destroy().await.unwrap();
+}
+
+// bypasses encryption, the data is stored as it is
 pub async fn upload_data(storage: &Storage, remote_path: &str) -> Result<()> {
-    match &storage.client {
-        StorageClient::Minio(storage) => upload_data_minio(storage, remote_path).await,
-        StorageClient::Local(storage) => upload_data_local(storage, remote_path).await,
-    }
+    storage.backend.put_object(remote_path, &fake_data()).await
 }
 
 #[tokio::test]
@@ -120,7 +125,7 @@ async fn test_storage_multipart_upload() {
         let part_number: u16 = 1;
 
         storage
-            .multipart_upload(remote_path, &upload_id, &data, part_number)
+            .multipart_upload(remote_path, &upload_id, &data, part_number, None)
             .await?;
 
         Ok(())
@@ -156,7 +161,7 @@ async fn test_storage_complete_multipart_upload() {
             let part_number = part_number as u16 + 1;
 
             let part = storage
-                .multipart_upload(remote_path, &upload_id, part_data, part_number)
+                .multipart_upload(remote_path, &upload_id, part_data, part_number, None)
                 .await?;
 
             parts.push(part);
@@ -182,6 +187,50 @@ async fn test_storage_complete_multipart_upload() {
     sleep_async(1).await;
 }
 
+#[tokio::test]
+async fn test_storage_abort_multipart_upload() {
+    async fn inner(storage: &Storage) -> Result<(bool, bool)> {
+        let remote_path = "test-abort-multipart-upload.txt";
+
+        init(storage).await?;
+
+        let upload_id = storage.create_multipart_upload_id(remote_path).await?;
+
+        let part = storage
+            .multipart_upload(remote_path, &upload_id, &fake_data(), 1, None)
+            .await?;
+
+        storage
+            .abort_multipart_upload(remote_path, &upload_id)
+            .await?;
+
+        let completed = storage
+            .complete_multipart_upload(remote_path, &upload_id, &[part])
+            .await
+            .is_ok();
+
+        Ok((completed, storage.is_object_exists(remote_path).await?))
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        let (completed, exists) = result.unwrap();
+
+        assert!(!completed);
+        assert!(!exists);
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
 #[tokio::test]
 async fn test_storage_get_download_response() {
     async fn inner(storage: &Storage) -> Result<()> {
@@ -190,7 +239,39 @@ async fn test_storage_get_download_response() {
         init(storage).await?;
 
         upload_data(storage, remote_path).await?;
-        storage.get_download_response(remote_path).await?;
+        storage
+            .get_download_response(remote_path, &HeaderMap::new())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+        result.unwrap();
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_get_preview_response() {
+    async fn inner(storage: &Storage) -> Result<()> {
+        let remote_path = "get_preview_url.txt";
+
+        init(storage).await?;
+
+        upload_data(storage, remote_path).await?;
+        storage
+            .get_preview_response(remote_path, &HeaderMap::new())
+            .await?;
 
         Ok(())
     }
@@ -210,6 +291,157 @@ async fn test_storage_get_download_response() {
     sleep_async(1).await;
 }
 
+#[test]
+fn test_storage_guess_mime_type() {
+    let png_head = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+
+    assert_eq!(guess_mime_type("image.png", None), "image/png");
+    assert_eq!(guess_mime_type("screenshot", Some(png_head)), "image/png");
+    assert_eq!(
+        guess_mime_type("notes.txt", Some(b"hello world!")),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(guess_mime_type("unknown", None), "application/octet-stream");
+}
+
+#[test]
+fn test_storage_guess_presigned_preview_mime_type() {
+    assert_eq!(guess_presigned_preview_mime_type("image.png"), "image/png");
+    assert_eq!(
+        guess_presigned_preview_mime_type("page.html"),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        guess_presigned_preview_mime_type("icon.svg"),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        guess_presigned_preview_mime_type("page.xhtml"),
+        "text/plain; charset=utf-8"
+    );
+}
+
+#[tokio::test]
+async fn test_storage_put_object_get_object() {
+    async fn inner(storage: &Storage) -> Result<Vec<u8>> {
+        let remote_path = "test_put_object.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path, b"hello world!").await?;
+        storage.get_object(remote_path).await
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(result.unwrap(), b"hello world!");
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_create_thumbnail() {
+    async fn inner(storage: &Storage) -> Result<(bool, bool)> {
+        let remote_path_image = "test_create_thumbnail.png";
+        let remote_path_text = "test_create_thumbnail.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path_image, &fake_image()).await?;
+        upload_data(storage, remote_path_text).await?;
+
+        let result_image = storage.create_thumbnail(remote_path_image).await?;
+        let result_text = storage.create_thumbnail(remote_path_text).await?;
+
+        storage
+            .get_thumbnail_response(remote_path_image, &HeaderMap::new())
+            .await?;
+        storage.remove_thumbnail(remote_path_image).await?;
+
+        Ok((result_image, result_text))
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        let (result_image, result_text) = result.unwrap();
+
+        assert!(result_image);
+        assert!(!result_text);
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[test]
+fn test_storage_gen_thumbnail() {
+    let thumbnail = gen_thumbnail(&fake_image()).unwrap().unwrap();
+    let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+
+    assert_eq!(thumbnail.width(), 320);
+    assert_eq!(thumbnail.height(), 240);
+
+    assert_eq!(gen_thumbnail(b"hello world!").unwrap(), None);
+
+    // small once compressed, but taller than the decoder allows
+    let mut tall_image = Cursor::new(Vec::new());
+
+    DynamicImage::ImageRgb8(RgbImage::new(1, 20000))
+        .write_to(&mut tall_image, ImageFormat::Png)
+        .unwrap();
+
+    assert_eq!(gen_thumbnail(tall_image.get_ref()).unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_storage_get_object_size() {
+    async fn inner(storage: &Storage) -> Result<(Option<u64>, Option<u64>)> {
+        let remote_path = "test_get_object_size.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path, b"hello world!").await?;
+
+        let size = storage.get_object_size(remote_path).await?;
+        let size_missing = storage
+            .get_object_size("test_get_object_size_missing")
+            .await?;
+
+        Ok((size, size_missing))
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(result.unwrap(), (Some(12), None));
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
 #[tokio::test]
 async fn test_storage_remove_object() {
     async fn inner(storage: &Storage) -> Result<()> {
@@ -261,3 +493,513 @@ async fn test_storage_remove_objects_all() {
 
     sleep_async(1).await;
 }
+
+#[tokio::test]
+async fn test_storage_get_archive_response() {
+    async fn inner(storage: &Storage) -> Result<Vec<u8>> {
+        let remote_path_1 = "test_archive_1.txt";
+        let remote_path_2 = "test_archive_2.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path_1, b"hello world!").await?;
+        storage.put_object(remote_path_2, b"hello archive!").await?;
+
+        let entries = vec![
+            ArchiveEntry {
+                name: "hello.txt".to_string(),
+                object: remote_path_1.to_string(),
+                timestamp: 0,
+            },
+            ArchiveEntry {
+                name: "archive.txt".to_string(),
+                object: remote_path_2.to_string(),
+                timestamp: 0,
+            },
+        ];
+
+        let response = storage.get_archive_response("test.zip", entries)?;
+
+        let data = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read archive"))?;
+
+        Ok(data.to_vec())
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        let data = result.unwrap();
+
+        // local file header signature
+        assert!(data.starts_with(b"PK\x03\x04"));
+        assert!(data.windows(9).any(|name| name == b"hello.txt"));
+        assert!(data.windows(11).any(|name| name == b"archive.txt"));
+        assert!(data.windows(14).any(|content| content == b"hello archive!"));
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_get_archive_response_missing_object() {
+    async fn inner(storage: &Storage) -> Result<bool> {
+        let remote_path = "test_archive_missing.txt";
+
+        init(storage).await?;
+
+        let entries = vec![ArchiveEntry {
+            name: "missing.txt".to_string(),
+            object: remote_path.to_string(),
+            timestamp: 0,
+        }];
+
+        let response = storage.get_archive_response("test.zip", entries)?;
+
+        let result = to_bytes(response.into_body(), usize::MAX).await;
+
+        Ok(result.is_err())
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert!(result.unwrap());
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_remove_prefix() {
+    async fn inner(storage: &Storage) -> Result<bool> {
+        let prefix = "test_remove_prefix";
+
+        init(storage).await?;
+
+        storage
+            .put_object(&format!("{}/a/a.txt", prefix), b"hello world!")
+            .await?;
+        storage
+            .put_object(&format!("{}/b.txt", prefix), b"hello world!")
+            .await?;
+
+        storage.remove_prefix(prefix).await?;
+
+        Ok(storage
+            .get_object(&format!("{}/b.txt", prefix))
+            .await
+            .is_err())
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert!(result.unwrap());
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_create_object_name() {
+    async fn inner(storage: &Storage) -> Result<(String, String, bool)> {
+        init(storage).await?;
+
+        let object_1 = storage.create_object_name("screenshot.png").await?;
+        storage.put_object(&object_1, b"hello world!").await?;
+
+        let object_2 = storage.create_object_name("screenshot.png").await?;
+
+        let is_exists = storage.is_object_exists(&object_2).await?;
+
+        Ok((object_1, object_2, is_exists))
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        let (object_1, object_2, is_exists) = result.unwrap();
+        assert_ne!(object_1, object_2);
+        assert!(object_1.ends_with("/screenshot.png"));
+        assert!(!is_exists);
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_hash_object() {
+    async fn inner(storage: &Storage) -> Result<String> {
+        let remote_path = "test_hash_object.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path, b"hello world!").await?;
+        storage.hash_object(remote_path).await
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(
+            result.unwrap(),
+            "7509e5bda0c762d2bac7f90d758b5b2263fa01ccbc542ab5e3df163be08e6ca9"
+        );
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_get_usage() {
+    async fn inner(storage: &Storage) -> Result<u64> {
+        init(storage).await?;
+
+        storage
+            .put_object("test_get_usage/a.txt", b"hello world!")
+            .await?;
+        storage.put_object("test_get_usage.txt", b"hello").await?;
+
+        storage.get_usage().await
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(result.unwrap(), 17);
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_list_object_names() {
+    async fn inner(storage: &Storage) -> Result<Vec<String>> {
+        init(storage).await?;
+
+        storage
+            .put_object("test_list_object_names/a.txt", b"hello world!")
+            .await?;
+        storage
+            .put_object("test_list_object_names.txt", b"hello")
+            .await?;
+
+        let mut names = storage.list_object_names().await?;
+        names.sort();
+
+        Ok(names)
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                "test_list_object_names.txt".to_string(),
+                "test_list_object_names/a.txt".to_string(),
+            ]
+        );
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_list_object_sizes() {
+    async fn inner(storage: &Storage) -> Result<Vec<(String, u64)>> {
+        init(storage).await?;
+
+        storage
+            .put_object("test_list_object_sizes/a.txt", b"hello world!")
+            .await?;
+        storage
+            .put_object("test_list_object_sizes.txt", b"hello")
+            .await?;
+
+        let mut sizes = storage.list_object_sizes().await?;
+        sizes.sort();
+
+        Ok(sizes)
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                ("test_list_object_sizes.txt".to_string(), 5),
+                ("test_list_object_sizes/a.txt".to_string(), 12),
+            ]
+        );
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_encryption() {
+    async fn inner(storage: &Storage) -> Result<(Vec<u8>, Vec<u8>, String)> {
+        let remote_path = "test-encryption.txt";
+
+        init(storage).await?;
+
+        let upload_id = storage.create_multipart_upload_id(remote_path).await?;
+        let data = fake_data();
+        let mut parts: Vec<Part> = Vec::new();
+
+        for (part_number, part_data) in data.chunks(PART_SIZE as usize).enumerate() {
+            let part_number = part_number as u16 + 1;
+
+            let part = storage
+                .multipart_upload(
+                    remote_path,
+                    &upload_id,
+                    part_data,
+                    part_number,
+                    Some(data.len() as u64),
+                )
+                .await?;
+
+            parts.push(part);
+        }
+
+        storage
+            .complete_multipart_upload(remote_path, &upload_id, &parts)
+            .await?;
+
+        let raw = without_crypto(storage).get_object(remote_path).await?;
+        let decrypted = storage.get_object(remote_path).await?;
+        let hash = storage.hash_object(remote_path).await?;
+
+        assert_eq!(decrypted, data);
+
+        Ok((raw, decrypted, hash))
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage_encrypted(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        let (raw, decrypted, hash) = result.unwrap();
+
+        assert!(raw.starts_with(b"TFE1"));
+        assert!(raw.len() > decrypted.len());
+        assert_eq!(hash, format!("{:x}", Sha256::digest(&decrypted)));
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_encryption_plain_object() {
+    async fn inner(storage: &Storage) -> Result<Vec<u8>> {
+        let remote_path = "test-encryption-plain-object.txt";
+
+        init(storage).await?;
+
+        // stored before encryption was enabled
+        without_crypto(storage)
+            .put_object(remote_path, b"hello world!")
+            .await?;
+
+        storage.get_object(remote_path).await
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage_encrypted(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(result.unwrap(), b"hello world!");
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_encryption_truncated() {
+    async fn inner(storage: &Storage) -> Result<Vec<u8>> {
+        let remote_path = "test-encryption-truncated.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path, &fake_data()).await?;
+
+        let raw_storage = without_crypto(storage);
+        let mut raw = raw_storage.get_object(remote_path).await?;
+        raw.truncate(raw.len() - 10);
+        raw_storage.put_object(remote_path, &raw).await?;
+
+        storage.get_object(remote_path).await
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage_encrypted(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert!(result.is_err());
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_encryption_missing_part() {
+    async fn inner(storage: &Storage) -> Result<Vec<u8>> {
+        let remote_path = "test-encryption-missing-part.txt";
+
+        init(storage).await?;
+
+        let upload_id = storage.create_multipart_upload_id(remote_path).await?;
+        let mut parts: Vec<Part> = Vec::new();
+
+        for (part_number, part_data) in [b"hello ", b"world!"].into_iter().enumerate() {
+            let part = storage
+                .multipart_upload(
+                    remote_path,
+                    &upload_id,
+                    part_data,
+                    part_number as u16 + 1,
+                    Some(12),
+                )
+                .await?;
+
+            parts.push(part);
+        }
+
+        // every frame of the first part is intact, only the object is cut short
+        storage
+            .complete_multipart_upload(remote_path, &upload_id, &parts[..1])
+            .await?;
+
+        storage.get_object(remote_path).await
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage_encrypted(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert!(result.is_err());
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_storage_get_download_response_encrypted() {
+    async fn inner(storage: &Storage) -> Result<Vec<u8>> {
+        let remote_path = "get_download_response_encrypted.txt";
+
+        init(storage).await?;
+
+        storage.put_object(remote_path, b"hello world!").await?;
+
+        let response = storage
+            .get_download_response(remote_path, &HeaderMap::new())
+            .await?;
+
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read body"))?;
+
+        Ok(body.to_vec())
+    }
+
+    async fn check(st_type: STType) {
+        let storage = get_storage_encrypted(st_type).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+
+        assert_eq!(result.unwrap(), b"hello world!");
+    }
+
+    for st_type in STType::iter() {
+        check(st_type).await;
+    }
+
+    sleep_async(1).await;
+}
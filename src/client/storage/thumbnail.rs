@@ -0,0 +1,133 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::http::HeaderMap;
+use axum::response::Response;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+use super::Storage;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 80;
+
+// decoding needs the whole file and its pixels in memory, larger images get no thumbnail
+const MAX_IMAGE_SIZE: u64 = 32 * 1024 * 1024; // 32 MB
+const MAX_IMAGE_DIMENSION: u32 = 12000;
+const MAX_IMAGE_ALLOC: u64 = 512 * 1024 * 1024; // 512 MB
+
+// every upload gets a new uuid directory, so an object name never points to other content
+// and its thumbnail never goes stale
+pub const THUMBNAIL_CACHE_CONTROL: &str = "private, max-age=604800";
+
+impl Storage {
+    // thumbnails are kept next to their objects
+    pub fn get_thumbnail_name(object: &str) -> String {
+        match object.rsplit_once('/') {
+            Some((dir, name)) => format!("{}/__THUMBNAIL__{}.jpg", dir, name),
+            None => format!("__THUMBNAIL__{}.jpg", object),
+        }
+    }
+
+    // returns whether a thumbnail was created
+    pub async fn create_thumbnail(&self, object: &str) -> Result<bool> {
+        if !is_image(object) {
+            return Ok(false);
+        }
+
+        match self.get_object_size(object).await? {
+            Some(size) if size <= MAX_IMAGE_SIZE => {}
+            size => {
+                tracing::debug!("skipped thumbnail for image of size {:?}", size);
+                return Ok(false);
+            }
+        }
+
+        let data = self.get_object(object).await?;
+
+        let thumbnail = tokio::task::spawn_blocking(move || gen_thumbnail(&data))
+            .await
+            .map_err(|e| {
+                Error::context(InternalServerError, e, "failed to join thumbnail task")
+            })??;
+
+        match thumbnail {
+            Some(thumbnail) => {
+                self.put_object(&Self::get_thumbnail_name(object), &thumbnail)
+                    .await?;
+
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub async fn get_thumbnail_response(
+        &self,
+        object: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response> {
+        let thumbnail_name = Self::get_thumbnail_name(object);
+
+        if self.crypto.is_some() {
+            return self.get_decrypted_thumbnail_response(&thumbnail_name).await;
+        }
+
+        self.backend
+            .get_thumbnail_response(&thumbnail_name, headers)
+            .await
+    }
+
+    pub async fn remove_thumbnail(&self, object: &str) -> Result<()> {
+        let thumbnail_name = Self::get_thumbnail_name(object);
+
+        self.backend.remove_object_if_exists(&thumbnail_name).await
+    }
+}
+
+fn is_image(object: &str) -> bool {
+    match ImageFormat::from_path(object) {
+        Ok(format) => format.reading_enabled(),
+        Err(_) => false,
+    }
+}
+
+// returns None if the data can't be decoded as an image or exceeds the limits
+pub fn gen_thumbnail(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| Error::context(InternalServerError, e, "failed to guess image format"))?;
+
+    // a small file can still claim huge dimensions
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    limits.max_alloc = Some(MAX_IMAGE_ALLOC);
+
+    reader.limits(limits);
+
+    let image = match reader.decode() {
+        Ok(image) => image,
+        Err(e) => {
+            tracing::debug!("skipped thumbnail for undecodable image: {}", e);
+            return Ok(None);
+        }
+    };
+
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8();
+
+    let mut buffer = Vec::new();
+
+    JpegEncoder::new_with_quality(&mut buffer, THUMBNAIL_QUALITY)
+        .encode_image(&thumbnail)
+        .map_err(|e| Error::context(InternalServerError, e, "failed to encode thumbnail"))?;
+
+    Ok(Some(buffer))
+}
@@ -5,8 +5,120 @@
 :license: MIT, see LICENSE for more details.
 */
 
+use mime_guess::mime;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, DuplexStream, ReadBuf};
+use tokio::sync::oneshot;
+
+use crate::error::Result;
+
+// long enough for the signatures recognized by infer
+pub const MIME_SNIFF_SIZE: usize = 8192;
+
+// previewed files may be html or svg, never let them run scripts
+const PREVIEW_CONTENT_SECURITY_POLICY: &str =
+    "default-src 'none'; img-src 'self'; media-src 'self'; style-src 'unsafe-inline'; sandbox";
+
+// browsers refuse to render pdf in a sandboxed document
+const PREVIEW_PDF_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; object-src 'self'";
+
+pub fn get_preview_content_security_policy(content_type: &str) -> &'static str {
+    if content_type == "application/pdf" {
+        PREVIEW_PDF_CONTENT_SECURITY_POLICY
+    } else {
+        PREVIEW_CONTENT_SECURITY_POLICY
+    }
+}
+
+// magic bytes are trusted over the extension, since the extension is given by the uploader
+pub fn guess_mime_type(file_name: &str, head: Option<&[u8]>) -> String {
+    if let Some(kind) = head.and_then(infer::get) {
+        return kind.mime_type().to_string();
+    }
+
+    match mime_guess::from_path(file_name).first() {
+        Some(mime_type) if mime_type.type_() == mime::TEXT => {
+            format!("{}; charset=utf-8", mime_type.essence_str())
+        }
+        Some(mime_type) => mime_type.essence_str().to_string(),
+        None => mime::APPLICATION_OCTET_STREAM.to_string(),
+    }
+}
+
+// presigned previews are served by the bucket without our csp, so documents that can run scripts are shown as text
+pub fn guess_presigned_preview_mime_type(file_name: &str) -> String {
+    let mime_type = guess_mime_type(file_name, None);
+
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+
+    let is_active = matches!(
+        essence,
+        "text/html" | "application/xhtml+xml" | "image/svg+xml" | "text/xml" | "application/xml"
+    );
+
+    if is_active {
+        "text/plain; charset=utf-8".to_string()
+    } else {
+        mime_type
+    }
+}
+
+// ends the body with an error instead of a truncated file when writing fails,
+// so the connection is aborted and the client doesn't keep a broken file
+pub struct ResultReader {
+    reader: DuplexStream,
+    result: oneshot::Receiver<Result<()>>,
+}
+
+impl ResultReader {
+    pub fn new(reader: DuplexStream, result: oneshot::Receiver<Result<()>>) -> Self {
+        Self { reader, result }
+    }
+}
+
+impl AsyncRead for ResultReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+
+        match Pin::new(&mut self.reader).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) if buf.filled().len() == filled => {}
+            poll => return poll,
+        }
+
+        // the writer is dropped once it's done, wait for its result
+        match Pin::new(&mut self.result).poll(cx) {
+            Poll::Ready(Ok(Ok(()))) => Poll::Ready(Ok(())),
+            Poll::Ready(Ok(Err(e))) => Poll::Ready(Err(io::Error::other(e.to_string()))),
+            Poll::Ready(Err(_)) => {
+                Poll::Ready(Err(io::Error::other("writer stopped unexpectedly")))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    pub fn fake_image() -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(640, 480));
+
+        let mut buffer = Cursor::new(Vec::new());
+
+        image.write_to(&mut buffer, ImageFormat::Png).unwrap();
+
+        buffer.into_inner()
+    }
+
     pub fn fake_data() -> Vec<u8> {
         let data = Vec::from("hello world!");
 
@@ -0,0 +1,418 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use chrono::{DateTime, NaiveDate};
+use pico_args::Arguments;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::client::database::models::message::{MessageItem, MessageItemType};
+use crate::client::database::models::token::TokenNewItem;
+use crate::client::{self, Database};
+use crate::crypto::Crypto;
+use crate::env::Env;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::handler::{admin, message};
+use crate::utils::get_current_timestamp;
+
+const DEFAULT_TOKEN_EXPIRATION_DAYS: i64 = 365;
+const DEFAULT_MESSAGE_LIMIT: u64 = 20;
+// longer contents are cut, so that every message stays on one line
+const MAX_CONTENT_LENGTH: usize = 60;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub const USAGE: &str = "\
+commands:
+  device list
+  device revoke <id>
+  token create <name> [--expiration-days 365]
+  token list
+  token revoke <id>
+  message push <content> [--public]
+  message list [--limit 20]
+  message purge --before <yyyy-mm-dd>
+  storage usage";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    DeviceList,
+    DeviceRevoke { id: i64 },
+    TokenCreate { name: String, expiration_days: i64 },
+    TokenList,
+    TokenRevoke { id: i64 },
+    MessagePush { content: String, is_private: bool },
+    MessageList { limit: u64 },
+    // messages before the start of the day in utc are removed
+    MessagePurge { before: NaiveDate },
+    StorageUsage,
+}
+
+fn to_error(e: pico_args::Error) -> Error {
+    Error::new(InternalServerError, format!("{}\n{}", e, USAGE))
+}
+
+// positional values follow the command, options may come anywhere
+fn get_positional(args: &mut Arguments, name: &str) -> Result<String> {
+    args.subcommand().map_err(to_error)?.ok_or_else(|| {
+        Error::new(
+            InternalServerError,
+            format!("missing value for <{}>\n{}", name, USAGE),
+        )
+    })
+}
+
+fn get_id(args: &mut Arguments) -> Result<i64> {
+    let id = get_positional(args, "id")?;
+
+    id.parse::<i64>()
+        .map_err(|e| Error::context(InternalServerError, e, format!("invalid id {}", id)))
+}
+
+fn get_option<T>(args: &mut Arguments, name: &'static str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    args.opt_value_from_str(name).map_err(to_error)
+}
+
+impl Command {
+    pub fn from_env() -> Result<Option<Self>> {
+        Self::parse(std::env::args_os().skip(1).collect())
+    }
+
+    // the command comes before the server flags, which are still read by Env
+    fn parse(args: Vec<OsString>) -> Result<Option<Self>> {
+        let mut args = Arguments::from_vec(args);
+
+        // the docker image always passes --init first
+        args.contains("--init");
+
+        let group = match args.subcommand().map_err(to_error)? {
+            Some(group) => group,
+            None => return Ok(None),
+        };
+
+        let action = args.subcommand().map_err(to_error)?.unwrap_or_default();
+
+        let command = match (group.as_str(), action.as_str()) {
+            ("device", "list") => Self::DeviceList,
+            ("device", "revoke") => Self::DeviceRevoke {
+                id: get_id(&mut args)?,
+            },
+            ("token", "create") => Self::TokenCreate {
+                name: get_positional(&mut args, "name")?,
+                expiration_days: get_option(&mut args, "--expiration-days")?
+                    .unwrap_or(DEFAULT_TOKEN_EXPIRATION_DAYS),
+            },
+            ("token", "list") => Self::TokenList,
+            ("token", "revoke") => Self::TokenRevoke {
+                id: get_id(&mut args)?,
+            },
+            ("message", "push") => Self::MessagePush {
+                content: get_positional(&mut args, "content")?,
+                is_private: !args.contains("--public"),
+            },
+            ("message", "list") => Self::MessageList {
+                limit: get_option(&mut args, "--limit")?.unwrap_or(DEFAULT_MESSAGE_LIMIT),
+            },
+            ("message", "purge") => {
+                let before = get_option::<String>(&mut args, "--before")?.ok_or_else(|| {
+                    Error::new(
+                        InternalServerError,
+                        format!("missing value for --before\n{}", USAGE),
+                    )
+                })?;
+
+                Self::MessagePurge {
+                    before: NaiveDate::parse_from_str(&before, DATE_FORMAT).map_err(|e| {
+                        Error::context(InternalServerError, e, format!("invalid date {}", before))
+                    })?,
+                }
+            }
+            ("storage", "usage") => Self::StorageUsage,
+            _ => {
+                let name = format!("{} {}", group, action);
+
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("unknown command: {}\n{}", name.trim_end(), USAGE),
+                ));
+            }
+        };
+
+        Ok(Some(command))
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    DateTime::from_timestamp_millis(timestamp)
+        .map(|time| time.format(TIME_FORMAT).to_string())
+        .unwrap_or(timestamp.to_string())
+}
+
+fn format_size(size: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = size as f64;
+    let mut index = 0;
+
+    while value >= 1024.0 && index < units.len() - 1 {
+        value /= 1024.0;
+        index += 1;
+    }
+
+    match index {
+        0 => format!("{} B", size),
+        _ => format!("{:.1} {}", value, units[index]),
+    }
+}
+
+fn truncate(content: &str) -> String {
+    let content = content.replace(['\r', '\n'], " ");
+
+    match content.char_indices().nth(MAX_CONTENT_LENGTH) {
+        Some((index, _)) => format!("{}...", &content[..index]),
+        None => content,
+    }
+}
+
+async fn get_crypto(database: &Database) -> Result<Crypto> {
+    let secret_keys = database.get_secret_keys().await?;
+    Crypto::from_secret_keys(secret_keys)
+}
+
+// connected clients aren't notified, they catch up when they reload
+pub async fn run(env: &Env, command: Command) -> Result<()> {
+    let database = client::get_database(env).await;
+
+    match command {
+        Command::DeviceList => {
+            for item in database.query_device_items().await? {
+                println!(
+                    "{}\t{}\t{}\tlast used {}\texpires {}",
+                    item.id,
+                    item.fingerprint,
+                    item.browser,
+                    format_timestamp(item.last_use_timestamp),
+                    format_timestamp(item.expiration_timestamp)
+                );
+            }
+        }
+        Command::DeviceRevoke { id } => {
+            let item = database
+                .query_device_items()
+                .await?
+                .into_iter()
+                .find(|item| item.id == id)
+                .ok_or_else(|| {
+                    Error::new(InternalServerError, format!("device {} not found", id))
+                })?;
+
+            database.remove_device(&item.fingerprint).await?;
+
+            println!("Device {} revoked.", id);
+        }
+        Command::TokenCreate {
+            name,
+            expiration_days,
+        } => {
+            let crypto = get_crypto(&database).await?;
+
+            let expiration_timestamp = get_current_timestamp() + expiration_days * 1000 * 3600 * 24;
+
+            let token = admin::gen_token(env, &crypto, expiration_timestamp)?;
+
+            database
+                .insert_token(TokenNewItem {
+                    token: token.clone(),
+                    name,
+                    expiration_timestamp,
+                })
+                .await?;
+
+            // the token is only shown once, the list never prints it
+            println!("{}", token);
+        }
+        Command::TokenList => {
+            for item in database.query_token_items().await? {
+                println!(
+                    "{}\t{}\tlast used {}\texpires {}",
+                    item.id,
+                    item.name,
+                    format_timestamp(item.last_use_timestamp),
+                    format_timestamp(item.expiration_timestamp)
+                );
+            }
+        }
+        Command::TokenRevoke { id } => {
+            let item = database
+                .query_token_items()
+                .await?
+                .into_iter()
+                .find(|item| item.id == id)
+                .ok_or_else(|| {
+                    Error::new(InternalServerError, format!("token {} not found", id))
+                })?;
+
+            database.remove_token(item.token).await?;
+
+            println!("Token {} revoked.", id);
+        }
+        Command::MessagePush {
+            content,
+            is_private,
+        } => {
+            if content.trim().is_empty() {
+                return Err(Error::new(InternalServerError, "content is empty"));
+            }
+
+            let item = MessageItem::new_text(&content, get_current_timestamp(), is_private);
+            let id = database.insert_message_item(item).await?;
+
+            println!("Message {} pushed.", id);
+        }
+        Command::MessageList { limit } => {
+            for item in database.query_message_items(0, limit, true).await? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    item.id,
+                    format_timestamp(item.timestamp),
+                    item.type_field.to_str(),
+                    if item.is_private { "private" } else { "public" },
+                    truncate(&item.content)
+                );
+            }
+        }
+        Command::MessagePurge { before } => {
+            let storage = client::get_storage(env).await;
+
+            let timestamp = before
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp_millis();
+
+            let items = database.query_message_items_before(timestamp).await?;
+
+            for item in &items {
+                message::remove_message(&database, &storage, item.id).await?;
+            }
+
+            println!(
+                "Removed {} messages before {}.",
+                items.len(),
+                before.format(DATE_FORMAT)
+            );
+        }
+        Command::StorageUsage => {
+            let storage = client::get_storage(env).await;
+
+            let objects = storage.list_object_sizes().await?;
+            let size = objects.iter().map(|(_, size)| size).sum::<u64>();
+
+            match env.quota.max_storage_size {
+                Some(max_storage_size) => println!(
+                    "storage: {} of {} in {} objects",
+                    format_size(size),
+                    format_size(max_storage_size),
+                    objects.len()
+                ),
+                None => println!(
+                    "storage: {} in {} objects",
+                    format_size(size),
+                    objects.len()
+                ),
+            }
+
+            for type_field in [
+                MessageItemType::Text,
+                MessageItemType::File,
+                MessageItemType::Bundle,
+            ] {
+                let count = database
+                    .count_message_items_by_type(type_field.clone())
+                    .await?;
+
+                println!("{} messages: {}", type_field.to_str(), count);
+            }
+        }
+    }
+
+    database.close().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Command>> {
+        Command::parse(args.iter().map(OsString::from).collect())
+    }
+
+    #[test]
+    fn test_command_parse() {
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(parse(&["--port", "8080"]).unwrap(), None);
+        assert_eq!(
+            parse(&["--init", "device", "list", "--username", "u"]).unwrap(),
+            Some(Command::DeviceList)
+        );
+        assert_eq!(
+            parse(&["device", "revoke", "3"]).unwrap(),
+            Some(Command::DeviceRevoke { id: 3 })
+        );
+        assert_eq!(
+            parse(&["token", "create", "phone", "--expiration-days", "7"]).unwrap(),
+            Some(Command::TokenCreate {
+                name: "phone".to_string(),
+                expiration_days: 7
+            })
+        );
+        assert_eq!(
+            parse(&["message", "push", "hello world", "--public"]).unwrap(),
+            Some(Command::MessagePush {
+                content: "hello world".to_string(),
+                is_private: false
+            })
+        );
+        assert_eq!(
+            parse(&["message", "list"]).unwrap(),
+            Some(Command::MessageList {
+                limit: DEFAULT_MESSAGE_LIMIT
+            })
+        );
+        assert_eq!(
+            parse(&["message", "purge", "--before", "2024-05-01"]).unwrap(),
+            Some(Command::MessagePurge {
+                before: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()
+            })
+        );
+
+        assert!(parse(&["device"]).is_err());
+        assert!(parse(&["token", "revoke", "abc"]).is_err());
+        assert!(parse(&["message", "purge"]).is_err());
+        assert!(parse(&["message", "purge", "--before", "05/01/2024"]).is_err());
+    }
+
+    #[test]
+    fn test_command_format() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(truncate("a\nb"), "a b");
+        assert_eq!(
+            truncate(&"测".repeat(MAX_CONTENT_LENGTH + 1)),
+            format!("{}...", "测".repeat(MAX_CONTENT_LENGTH))
+        );
+    }
+}
@@ -13,12 +13,36 @@ use ring::rand::{SecureRandom, SystemRandom};
 use crate::error::Error;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::Result;
+use crate::utils::get_current_timestamp;
 
 const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+// bytes added by encrypt_bytes
+pub const SEAL_OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+// separates the key id from the encrypted text, it never appears in url safe Base64
+const KEY_ID_SEPARATOR: char = '.';
 
 #[derive(Debug, Clone)]
-pub struct Crypto {
+pub struct SecretKey {
+    pub id: i64,
+    pub key: String,
+    // the key still decrypts until then, so that nobody is logged out at once
+    pub retired_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct CryptoKey {
+    id: i64,
     key: LessSafeKey,
+    retired_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Crypto {
+    // sorted by id, the newest key encrypts and every active key decrypts
+    keys: Vec<CryptoKey>,
 }
 
 struct NoncePack {
@@ -26,9 +50,15 @@ struct NoncePack {
     raw: Vec<u8>,
 }
 
-impl Crypto {
-    pub fn new(key_base64: &str) -> Result<Self> {
-        let key_byte = base64.decode(key_base64).map_err(|e| {
+impl CryptoKey {
+    fn new(
+        SecretKey {
+            id,
+            key,
+            retired_timestamp,
+        }: SecretKey,
+    ) -> Result<Self> {
+        let key_byte = base64.decode(key).map_err(|e| {
             Error::context(
                 InternalServerError,
                 e,
@@ -46,49 +76,134 @@ impl Crypto {
 
         let key = LessSafeKey::new(key_unbound);
 
-        Ok(Self { key })
+        Ok(Self {
+            id,
+            key,
+            retired_timestamp,
+        })
     }
 
-    pub fn encrypt(&self, text: &str) -> Result<String> {
+    fn is_active(&self) -> bool {
+        match self.retired_timestamp {
+            Some(retired_timestamp) => get_current_timestamp() < retired_timestamp,
+            None => true,
+        }
+    }
+
+    // the output is the nonce followed by the sealed data and its tag
+    fn seal(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         let NoncePack {
             nonce,
             raw: nonce_raw,
-        } = Self::gen_nonce_pack()?;
+        } = Crypto::gen_nonce_pack()?;
 
-        let mut buffer = text.as_bytes().to_vec();
+        let mut buffer = data.to_vec();
 
         self.key
-            .seal_in_place_append_tag(nonce, Aad::from(b""), &mut buffer)
+            .seal_in_place_append_tag(nonce, Aad::from(aad), &mut buffer)
             .map_err(|e| Error::context(InternalServerError, e, "failed to encrypt in Crypto"))?;
 
         // insert nonce to the head of the output
-        buffer.splice(..0, nonce_raw.into_iter());
-
-        let result = base64.encode(buffer);
+        buffer.splice(..0, nonce_raw);
 
-        Ok(result)
+        Ok(buffer)
     }
 
-    pub fn decrypt(&self, text: &str) -> Result<String> {
-        let mut text_raw = base64.decode(text).map_err(|e| {
-            Error::context(
+    fn open(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if data.len() < NONCE_SIZE {
+            return Err(Error::new(
                 InternalServerError,
-                e,
-                "failed to decode encrypted text from Base64",
-            )
-        })?;
+                "encrypted data is shorter than nonce",
+            ));
+        }
 
-        let mut buffer = text_raw.split_off(NONCE_SIZE);
-        let nonce_raw = text_raw;
+        let (nonce_raw, data) = data.split_at(NONCE_SIZE);
 
-        let nonce = Self::nonce_raw_to_nonce(&nonce_raw)?;
+        let nonce = Crypto::nonce_raw_to_nonce(&nonce_raw.to_vec())?;
+
+        let mut buffer = data.to_vec();
 
         let buffer = self
             .key
-            .open_in_place(nonce, Aad::from(b""), &mut buffer)
+            .open_in_place(nonce, Aad::from(aad), &mut buffer)
             .map_err(|e| Error::context(InternalServerError, e, "failed to decrypt in Crypto"))?
             .to_vec();
 
+        Ok(buffer)
+    }
+}
+
+impl Crypto {
+    pub fn new(key_base64: &str) -> Result<Self> {
+        Self::from_secret_keys(vec![SecretKey {
+            id: 0,
+            key: key_base64.to_string(),
+            retired_timestamp: None,
+        }])
+    }
+
+    pub fn from_secret_keys(secret_keys: Vec<SecretKey>) -> Result<Self> {
+        let mut keys = secret_keys
+            .into_iter()
+            .map(CryptoKey::new)
+            .collect::<Result<Vec<CryptoKey>>>()?;
+
+        if keys.is_empty() {
+            return Err(Error::new(InternalServerError, "no secret key for Crypto"));
+        }
+
+        keys.sort_by_key(|key| key.id);
+
+        Ok(Self { keys })
+    }
+
+    fn current_key(&self) -> &CryptoKey {
+        &self.keys[self.keys.len() - 1]
+    }
+
+    // the output is prefixed by the id of the key
+    pub fn encrypt(&self, text: &str) -> Result<String> {
+        let key = self.current_key();
+
+        let buffer = key.seal(text.as_bytes(), b"")?;
+
+        let result = format!("{}{}{}", key.id, KEY_ID_SEPARATOR, base64.encode(buffer));
+
+        Ok(result)
+    }
+
+    pub fn decrypt(&self, text: &str) -> Result<String> {
+        let buffer = match text.split_once(KEY_ID_SEPARATOR) {
+            Some((id, text)) => {
+                let id = id.parse::<i64>().map_err(|e| {
+                    Error::context(InternalServerError, e, "failed to parse crypto key id")
+                })?;
+
+                let key = self
+                    .keys
+                    .iter()
+                    .find(|key| key.id == id && key.is_active())
+                    .ok_or_else(|| {
+                        Error::new(
+                            InternalServerError,
+                            format!("crypto key {} not found or retired", id),
+                        )
+                    })?;
+
+                key.open(&Self::decode(text)?, b"")?
+            }
+            // encrypted before keys had ids, only the key it was encrypted with can open it
+            None => {
+                let text_raw = Self::decode(text)?;
+
+                self.keys
+                    .iter()
+                    .filter(|key| key.is_active())
+                    .find_map(|key| key.open(&text_raw, b"").ok())
+                    .ok_or_else(|| Error::new(InternalServerError, "failed to decrypt in Crypto"))?
+            }
+        };
+
         let result = String::from_utf8(buffer).map_err(|e| {
             Error::context(
                 InternalServerError,
@@ -100,6 +215,43 @@ impl Crypto {
         Ok(result)
     }
 
+    // certificates encrypted with an older key are replaced before it retires
+    pub fn is_current(&self, text: &str) -> bool {
+        match text.split_once(KEY_ID_SEPARATOR) {
+            Some((id, _)) => id
+                .parse::<i64>()
+                .is_ok_and(|id| id == self.current_key().id),
+            None => false,
+        }
+    }
+
+    pub fn encrypt_bytes(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.current_key().seal(data, aad)
+    }
+
+    // bytes carry no key id, so every active key is tried, the newest first
+    pub fn decrypt_bytes(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.current_key().open(data, aad).or_else(|e| {
+            self.keys
+                .iter()
+                .rev()
+                .skip(1)
+                .filter(|key| key.is_active())
+                .find_map(|key| key.open(data, aad).ok())
+                .ok_or(e)
+        })
+    }
+
+    fn decode(text: &str) -> Result<Vec<u8>> {
+        base64.decode(text).map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to decode encrypted text from Base64",
+            )
+        })
+    }
+
     fn nonce_raw_to_nonce(nonce_raw: &Vec<u8>) -> Result<Nonce> {
         let nonce: [u8; NONCE_SIZE] = nonce_raw[..NONCE_SIZE].try_into().map_err(|e| {
             Error::context(InternalServerError, e, "failed to create nonce in Crypto")
@@ -205,4 +357,85 @@ pub mod tests {
 
         sleep(1);
     }
+
+    #[test]
+    fn test_crypto_encrypt_decrypt_bytes() {
+        let crypto = get_crypto();
+
+        let data = b"This is a test for crypto.";
+        let data_encrypted = crypto.encrypt_bytes(data, b"aad").unwrap();
+
+        assert_eq!(data_encrypted.len(), data.len() + SEAL_OVERHEAD);
+        assert_eq!(crypto.decrypt_bytes(&data_encrypted, b"aad").unwrap(), data);
+        assert!(crypto.decrypt_bytes(&data_encrypted, b"other").is_err());
+
+        sleep(1);
+    }
+
+    #[test]
+    fn test_crypto_rotation() {
+        let old_key = Crypto::gen_secret_key().unwrap();
+        let new_key = Crypto::gen_secret_key().unwrap();
+
+        let text = "This is a test for crypto.";
+
+        let old_crypto = Crypto::from_secret_keys(vec![SecretKey {
+            id: 1,
+            key: old_key.clone(),
+            retired_timestamp: None,
+        }])
+        .unwrap();
+        let text_encrypted = old_crypto.encrypt(text).unwrap();
+        let data_encrypted = old_crypto.encrypt_bytes(text.as_bytes(), b"aad").unwrap();
+
+        let rotated_crypto = |retired_timestamp: i64| {
+            Crypto::from_secret_keys(vec![
+                SecretKey {
+                    id: 2,
+                    key: new_key.clone(),
+                    retired_timestamp: None,
+                },
+                SecretKey {
+                    id: 1,
+                    key: old_key.clone(),
+                    retired_timestamp: Some(retired_timestamp),
+                },
+            ])
+            .unwrap()
+        };
+
+        // within the grace period
+        let crypto = rotated_crypto(get_current_timestamp() + 1000 * 3600);
+        assert_eq!(crypto.decrypt(&text_encrypted).unwrap(), text);
+        assert_eq!(
+            crypto.decrypt_bytes(&data_encrypted, b"aad").unwrap(),
+            text.as_bytes()
+        );
+        assert!(crypto.encrypt(text).unwrap().starts_with("2."));
+        assert!(!crypto.is_current(&text_encrypted));
+        assert!(crypto.is_current(&crypto.encrypt(text).unwrap()));
+
+        // after the grace period
+        let crypto = rotated_crypto(get_current_timestamp() - 1);
+        assert!(crypto.decrypt(&text_encrypted).is_err());
+        assert!(crypto.decrypt_bytes(&data_encrypted, b"aad").is_err());
+
+        sleep(1);
+    }
+
+    #[test]
+    fn test_crypto_decrypt_legacy() {
+        let key = Crypto::gen_secret_key().unwrap();
+        let crypto = Crypto::new(&key).unwrap();
+
+        let text = "This is a test for crypto.";
+
+        // encrypted before key ids were added
+        let text_encrypted = crypto.encrypt(text).unwrap();
+        let (_, text_legacy) = text_encrypted.split_once(KEY_ID_SEPARATOR).unwrap();
+
+        assert_eq!(crypto.decrypt(text_legacy).unwrap(), text);
+
+        sleep(1);
+    }
 }
@@ -6,41 +6,254 @@
 */
 
 use pico_args::Arguments;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
+use toml::{Table, Value};
 
 use crate::error::Error;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::Result;
+use crate::utils::REDACTED;
 
-fn get_arg_value<T>(arg_name: &'static str) -> Result<T>
-where
-    T: FromStr,
-    T::Err: Display,
-{
+const VAR_PREFIX: &str = "TRANSFERY_";
+
+pub fn args_contains(arg_name: &'static str) -> bool {
     let mut args = Arguments::from_env();
-    let value: T = args
-        .value_from_str(arg_name)
-        .map_err(|e| Error::context(InternalServerError, e, "failed to get arg"))?;
+    args.contains(arg_name)
+}
 
-    Ok(value)
+// --minio-endpoint is TRANSFERY_MINIO_ENDPOINT in the environment
+fn to_var_name(arg_name: &str) -> String {
+    format!(
+        "{}{}",
+        VAR_PREFIX,
+        arg_name
+            .trim_start_matches('-')
+            .replace('-', "_")
+            .to_uppercase()
+    )
 }
 
-fn get_arg_value_option<T>(arg_name: &'static str, default: T) -> T
-where
-    T: FromStr,
-    T::Err: Display,
-{
-    let mut args = Arguments::from_env();
-    match args.value_from_str(arg_name) {
-        Ok(value) => return value,
-        Err(_) => return default,
-    };
+// --minio-endpoint is minio_endpoint in the config file
+fn to_key(arg_name: &str) -> String {
+    arg_name.trim_start_matches('-').replace('-', "_")
 }
 
-pub fn args_contains(arg_name: &'static str) -> bool {
-    let mut args = Arguments::from_env();
-    args.contains(arg_name)
+// flags override TRANSFERY_* environment variables, which override the config file
+pub struct ConfigSource {
+    args: Vec<OsString>,
+    vars: HashMap<String, String>,
+    file: Table,
+    // collected instead of returned, so that every problem is reported at once
+    errors: RefCell<Vec<String>>,
+    // options that were read, the rest of the file and variables are typos
+    used: RefCell<HashSet<&'static str>>,
+}
+
+impl ConfigSource {
+    pub fn new(args: Vec<OsString>, vars: HashMap<String, String>, file: Table) -> Self {
+        Self {
+            args,
+            vars,
+            file,
+            errors: RefCell::new(Vec::new()),
+            used: RefCell::new(HashSet::new()),
+        }
+    }
+
+    // the file is given by --config or TRANSFERY_CONFIG
+    fn from_env() -> Result<Self> {
+        let args = std::env::args_os().skip(1).collect::<Vec<OsString>>();
+        let vars = std::env::vars()
+            .filter(|(name, _)| name.starts_with(VAR_PREFIX))
+            .collect::<HashMap<String, String>>();
+
+        let source = Self::new(args, vars, Table::new());
+
+        let file = match source.get_optional::<String>("--config") {
+            Some(path) => {
+                let content = std::fs::read_to_string(&path).map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        format!("failed to read config file {}", path),
+                    )
+                })?;
+
+                content.parse::<Table>().map_err(|e| {
+                    Error::context(
+                        InternalServerError,
+                        e,
+                        format!("failed to parse config file {}", path),
+                    )
+                })?
+            }
+            None => Table::new(),
+        };
+
+        Ok(Self { file, ..source })
+    }
+
+    fn add_error(&self, error: String) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    fn get_raw(&self, arg_name: &'static str) -> Option<String> {
+        self.used.borrow_mut().insert(arg_name);
+
+        let mut args = Arguments::from_vec(self.args.clone());
+
+        match args.opt_value_from_str::<_, String>(arg_name) {
+            Ok(Some(value)) => return Some(value),
+            Ok(None) => {}
+            Err(e) => {
+                self.add_error(format!("{}: {}", arg_name, e));
+                return None;
+            }
+        }
+
+        if let Some(value) = self.vars.get(&to_var_name(arg_name)) {
+            return Some(value.clone());
+        }
+
+        match self.file.get(&to_key(arg_name)) {
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(value) => Some(value.to_string()),
+            None => None,
+        }
+    }
+
+    fn get_optional<T>(&self, arg_name: &'static str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.get_raw(arg_name)?;
+
+        match value.parse::<T>() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.add_error(format!("invalid value for {}: {}", arg_name, e));
+                None
+            }
+        }
+    }
+
+    fn get_or<T>(&self, arg_name: &'static str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.get_optional(arg_name).unwrap_or(default)
+    }
+
+    // a missing value is recorded, the default only keeps the rest of the config going
+    fn get<T>(&self, arg_name: &'static str) -> T
+    where
+        T: FromStr + Default,
+        T::Err: Display,
+    {
+        if self.get_raw(arg_name).is_none() {
+            self.add_error(format!("missing value for {}", arg_name));
+            return T::default();
+        }
+
+        self.get_or(arg_name, T::default())
+    }
+
+    fn contains(&self, arg_name: &'static str) -> bool {
+        self.used.borrow_mut().insert(arg_name);
+
+        let mut args = Arguments::from_vec(self.args.clone());
+
+        if args.contains(arg_name) {
+            return true;
+        }
+
+        if let Some(value) = self.vars.get(&to_var_name(arg_name)) {
+            return match value.as_str() {
+                "true" | "1" => true,
+                "false" | "0" | "" => false,
+                _ => {
+                    self.add_error(format!("invalid value for {}: {}", arg_name, value));
+                    false
+                }
+            };
+        }
+
+        match self.file.get(&to_key(arg_name)) {
+            Some(Value::Boolean(value)) => *value,
+            Some(value) => {
+                self.add_error(format!("invalid value for {}: {}", arg_name, value));
+                false
+            }
+            None => false,
+        }
+    }
+
+    // only one of the options can be chosen
+    fn choose(&self, arg_names: &[&'static str]) -> Option<&'static str> {
+        let chosen = arg_names
+            .iter()
+            .filter(|arg_name| self.contains(arg_name))
+            .copied()
+            .collect::<Vec<&str>>();
+
+        if chosen.len() > 1 {
+            self.add_error(format!("only one of {} can be set", chosen.join(", ")));
+        }
+
+        chosen.first().copied()
+    }
+
+    fn add_unused_errors(&self) {
+        let used = self.used.borrow();
+
+        let var_names = used
+            .iter()
+            .map(|arg_name| to_var_name(arg_name))
+            .collect::<HashSet<String>>();
+        let keys = used
+            .iter()
+            .map(|arg_name| to_key(arg_name))
+            .collect::<HashSet<String>>();
+
+        let mut errors = self
+            .vars
+            .keys()
+            .filter(|name| !var_names.contains(*name))
+            .map(|name| format!("unknown or unused environment variable {}", name))
+            .chain(
+                self.file
+                    .keys()
+                    .filter(|key| !keys.contains(*key))
+                    .map(|key| format!("unknown or unused config key {}", key)),
+            )
+            .collect::<Vec<String>>();
+
+        errors.sort();
+
+        self.errors.borrow_mut().append(&mut errors);
+    }
+
+    pub fn finish(self) -> Result<()> {
+        self.add_unused_errors();
+
+        let errors = self.errors.into_inner();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                InternalServerError,
+                format!("invalid configuration:\n{}", errors.join("\n")),
+            ))
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -84,18 +297,199 @@ impl Display for EnvMode {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "text" {
+            Ok(Self::Text)
+        } else if s == "json" {
+            Ok(Self::Json)
+        } else {
+            Err(Error::new(
+                InternalServerError,
+                "LogFormat must be one of text or json",
+            ))
+        }
+    }
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    // always plain http, tls is left to the proxy in front
+    Unix(String),
+}
+
+impl BindAddress {
+    // a missing port is taken from --port, ipv6 addresses are written as [::]
+    fn parse(value: &str, port: u16) -> Result<Self> {
+        if let Some(path) = value.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(Error::new(InternalServerError, "missing unix socket path"));
+            }
+
+            return Ok(Self::Unix(path.to_string()));
+        }
+
+        if let Ok(addr) = value.parse::<SocketAddr>() {
+            return Ok(Self::Tcp(addr));
+        }
+
+        let ip = value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .unwrap_or(value);
+
+        match ip.parse::<IpAddr>() {
+            Ok(ip) => Ok(Self::Tcp(SocketAddr::new(ip, port))),
+            Err(_) => Err(Error::new(
+                InternalServerError,
+                format!("{} is not an ip address or unix:path", value),
+            )),
+        }
+    }
+}
+
+impl Display for BindAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            Self::Unix(path) => write!(f, "unix:{}", path),
+        }
+    }
+}
+
+// comma separated, so that one option can listen on several addresses
+fn get_bind(source: &ConfigSource, port: u16) -> Vec<BindAddress> {
+    let value = source.get_or("--bind", "0.0.0.0".to_string());
+
+    let values = value
+        .split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<&str>>();
+
+    if values.is_empty() {
+        source.add_error("missing value for --bind".to_string());
+    }
+
+    values
+        .into_iter()
+        .filter_map(|value| match BindAddress::parse(value, port) {
+            Ok(address) => Some(address),
+            Err(e) => {
+                source.add_error(format!("invalid value for --bind: {}", e));
+                None
+            }
+        })
+        .collect()
+}
+
+// /transfery/ and transfery are both stored as /transfery, the root is empty
+fn get_base_path(source: &ConfigSource) -> String {
+    let value = source.get_or("--base-path", String::new());
+    let path = value.trim().trim_matches('/');
+
+    let is_valid = path.split('/').all(|component| {
+        !component.is_empty()
+            && component != "."
+            && component != ".."
+            && component
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+    });
+
+    if path.is_empty() {
+        String::new()
+    } else if is_valid {
+        format!("/{}", path)
+    } else {
+        source.add_error(format!("invalid value for --base-path: {}", value));
+        String::new()
+    }
+}
+
+// octal like chmod, so that nginx in the same group can connect
+fn get_unix_socket_mode(source: &ConfigSource) -> u32 {
+    let value = source.get_or("--unix-socket-mode", "660".to_string());
+
+    match u32::from_str_radix(&value, 8) {
+        Ok(mode) if mode <= 0o777 => mode,
+        _ => {
+            source.add_error(format!("invalid value for --unix-socket-mode: {}", value));
+            0o660
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum DatabaseEnv {
     MySql(MySqlEnv),
+    Postgres(PostgresEnv),
     Sqlite(SqliteEnv),
 }
 
 impl DatabaseEnv {
-    fn new() -> Result<Self> {
-        if args_contains("--mysql") {
-            Ok(Self::MySql(MySqlEnv::new()?))
-        } else {
-            Ok(Self::Sqlite(SqliteEnv::new()?))
+    fn new(source: &ConfigSource) -> Self {
+        match source.choose(&["--mysql", "--postgres"]) {
+            Some("--mysql") => Self::MySql(MySqlEnv::new(source)),
+            Some(_) => Self::Postgres(PostgresEnv::new(source)),
+            None => Self::Sqlite(SqliteEnv::new(source)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MySql(_) => "mysql",
+            Self::Postgres(_) => "postgres",
+            Self::Sqlite(_) => "sqlite",
+        }
+    }
+
+    fn write_config(&self, table: &mut Table) {
+        match self {
+            Self::MySql(config) => {
+                table.insert("mysql".to_string(), Value::Boolean(true));
+                table.insert("mysql_endpoint".to_string(), config.endpoint.clone().into());
+                table.insert("mysql_username".to_string(), config.username.clone().into());
+                table.insert("mysql_password".to_string(), REDACTED.into());
+                table.insert("mysql_database".to_string(), config.database.clone().into());
+            }
+            Self::Postgres(config) => {
+                table.insert("postgres".to_string(), Value::Boolean(true));
+                table.insert(
+                    "postgres_endpoint".to_string(),
+                    config.endpoint.clone().into(),
+                );
+                table.insert(
+                    "postgres_username".to_string(),
+                    config.username.clone().into(),
+                );
+                table.insert("postgres_password".to_string(), REDACTED.into());
+                table.insert(
+                    "postgres_database".to_string(),
+                    config.database.clone().into(),
+                );
+            }
+            Self::Sqlite(config) => {
+                table.insert("sqlite_path".to_string(), config.path.clone().into());
+            }
         }
     }
 }
@@ -109,18 +503,42 @@ pub struct MySqlEnv {
 }
 
 impl MySqlEnv {
-    fn new() -> Result<Self> {
-        let endpoint = get_arg_value::<String>("--mysql-endpoint")?;
-        let username = get_arg_value::<String>("--mysql-username")?;
-        let password = get_arg_value::<String>("--mysql-password")?;
-        let database = get_arg_value::<String>("--mysql-database")?;
+    fn new(source: &ConfigSource) -> Self {
+        let endpoint = source.get::<String>("--mysql-endpoint");
+        let username = source.get::<String>("--mysql-username");
+        let password = source.get::<String>("--mysql-password");
+        let database = source.get::<String>("--mysql-database");
 
-        Ok(Self {
+        Self {
             endpoint,
             username,
             password,
             database,
-        })
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PostgresEnv {
+    pub endpoint: String,
+    pub username: String,
+    pub password: String,
+    pub database: String,
+}
+
+impl PostgresEnv {
+    fn new(source: &ConfigSource) -> Self {
+        let endpoint = source.get::<String>("--postgres-endpoint");
+        let username = source.get::<String>("--postgres-username");
+        let password = source.get::<String>("--postgres-password");
+        let database = source.get::<String>("--postgres-database");
+
+        Self {
+            endpoint,
+            username,
+            password,
+            database,
+        }
     }
 }
 
@@ -130,25 +548,86 @@ pub struct SqliteEnv {
 }
 
 impl SqliteEnv {
-    fn new() -> Result<Self> {
-        Ok(Self {
-            path: "./db.sqlite".to_string(),
-        })
+    fn new(source: &ConfigSource) -> Self {
+        Self {
+            path: source.get_or("--sqlite-path", "./db.sqlite".to_string()),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum StorageEnv {
     Minio(MinioEnv),
+    S3(S3Env),
     LocalStorage(LocalStorageEnv),
+    // files are lost on restart
+    Memory,
 }
 
 impl StorageEnv {
-    fn new() -> Result<Self> {
-        if args_contains("--minio") {
-            Ok(Self::Minio(MinioEnv::new()?))
-        } else {
-            Ok(Self::LocalStorage(LocalStorageEnv::new()?))
+    fn new(source: &ConfigSource) -> Self {
+        match source.choose(&["--minio", "--s3", "--memory-storage"]) {
+            Some("--minio") => Self::Minio(MinioEnv::new(source)),
+            Some("--s3") => Self::S3(S3Env::new(source)),
+            Some(_) => Self::Memory,
+            None => Self::LocalStorage(LocalStorageEnv::new(source)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Minio(_) => "minio",
+            Self::S3(_) => "s3",
+            Self::LocalStorage(_) => "local",
+            Self::Memory => "memory",
+        }
+    }
+
+    fn write_config(&self, table: &mut Table) {
+        match self {
+            Self::Minio(config) => {
+                table.insert("minio".to_string(), Value::Boolean(true));
+                table.insert("minio_endpoint".to_string(), config.endpoint.clone().into());
+                table.insert("minio_username".to_string(), config.username.clone().into());
+                table.insert("minio_password".to_string(), REDACTED.into());
+                table.insert("minio_bucket".to_string(), config.bucket.clone().into());
+            }
+            Self::S3(config) => {
+                table.insert("s3".to_string(), Value::Boolean(true));
+                table.insert("s3_endpoint".to_string(), config.endpoint.clone().into());
+                table.insert("s3_region".to_string(), config.region.clone().into());
+                table.insert(
+                    "s3_access_key".to_string(),
+                    config.access_key.clone().into(),
+                );
+                table.insert("s3_secret_key".to_string(), REDACTED.into());
+                if config.session_token.is_some() {
+                    table.insert("s3_session_token".to_string(), REDACTED.into());
+                }
+                table.insert("s3_bucket".to_string(), config.bucket.clone().into());
+                if let Some(prefix) = &config.prefix {
+                    table.insert("s3_prefix".to_string(), prefix.clone().into());
+                }
+                table.insert(
+                    "s3_path_style".to_string(),
+                    Value::Boolean(config.path_style),
+                );
+                if let Some(server_side_encryption) = &config.server_side_encryption {
+                    table.insert("s3_sse".to_string(), server_side_encryption.clone().into());
+                }
+                if let Some(sse_kms_key_id) = &config.sse_kms_key_id {
+                    table.insert(
+                        "s3_sse_kms_key_id".to_string(),
+                        sse_kms_key_id.clone().into(),
+                    );
+                }
+            }
+            Self::LocalStorage(config) => {
+                table.insert("local_storage_path".to_string(), config.path.clone().into());
+            }
+            Self::Memory => {
+                table.insert("memory_storage".to_string(), Value::Boolean(true));
+            }
         }
     }
 }
@@ -162,18 +641,64 @@ pub struct MinioEnv {
 }
 
 impl MinioEnv {
-    fn new() -> Result<Self> {
-        let endpoint = get_arg_value::<String>("--minio-endpoint")?;
-        let username = get_arg_value::<String>("--minio-username")?;
-        let password = get_arg_value::<String>("--minio-password")?;
-        let bucket = get_arg_value::<String>("--minio-bucket")?;
+    fn new(source: &ConfigSource) -> Self {
+        let endpoint = source.get::<String>("--minio-endpoint");
+        let username = source.get::<String>("--minio-username");
+        let password = source.get::<String>("--minio-password");
+        let bucket = source.get::<String>("--minio-bucket");
 
-        Ok(Self {
+        Self {
             endpoint,
             username,
             password,
             bucket,
-        })
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct S3Env {
+    pub endpoint: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    // temporary credentials come with a session token
+    pub session_token: Option<String>,
+    pub bucket: String,
+    // objects are stored under it, so a bucket can be shared
+    pub prefix: Option<String>,
+    // bucket in the path instead of the host, required by most self-hosted servers
+    pub path_style: bool,
+    // AES256 or aws:kms
+    pub server_side_encryption: Option<String>,
+    pub sse_kms_key_id: Option<String>,
+}
+
+impl S3Env {
+    fn new(source: &ConfigSource) -> Self {
+        let endpoint = source.get::<String>("--s3-endpoint");
+        let region = source.get_or("--s3-region", "us-east-1".to_string());
+        let access_key = source.get::<String>("--s3-access-key");
+        let secret_key = source.get::<String>("--s3-secret-key");
+        let session_token = source.get_optional::<String>("--s3-session-token");
+        let bucket = source.get::<String>("--s3-bucket");
+        let prefix = source.get_optional::<String>("--s3-prefix");
+        let path_style = source.contains("--s3-path-style");
+        let server_side_encryption = source.get_optional::<String>("--s3-sse");
+        let sse_kms_key_id = source.get_optional::<String>("--s3-sse-kms-key-id");
+
+        Self {
+            endpoint,
+            region,
+            access_key,
+            secret_key,
+            session_token,
+            bucket,
+            prefix,
+            path_style,
+            server_side_encryption,
+            sse_kms_key_id,
+        }
     }
 }
 
@@ -183,44 +708,240 @@ pub struct LocalStorageEnv {
 }
 
 impl LocalStorageEnv {
-    fn new() -> Result<Self> {
-        Ok(Self {
-            path: "./uploaded".to_string(),
-        })
+    fn new(source: &ConfigSource) -> Self {
+        Self {
+            path: source.get_or("--local-storage-path", "./uploaded".to_string()),
+        }
+    }
+}
+
+// sizes are in bytes, a missing limit means unlimited
+#[derive(Debug, Clone, Default)]
+pub struct QuotaEnv {
+    pub max_storage_size: Option<u64>,
+    pub max_file_size: Option<u64>,
+    pub max_daily_upload_size: Option<u64>,
+}
+
+impl QuotaEnv {
+    fn new(source: &ConfigSource) -> Self {
+        let max_storage_size = source.get_optional::<u64>("--max-storage-size");
+        let max_file_size = source.get_optional::<u64>("--max-file-size");
+        let max_daily_upload_size = source.get_optional::<u64>("--max-daily-upload-size");
+
+        Self {
+            max_storage_size,
+            max_file_size,
+            max_daily_upload_size,
+        }
+    }
+
+    fn write_config(&self, table: &mut Table) {
+        let limits = [
+            ("max_storage_size", self.max_storage_size),
+            ("max_file_size", self.max_file_size),
+            ("max_daily_upload_size", self.max_daily_upload_size),
+        ];
+
+        for (key, limit) in limits {
+            if let Some(limit) = limit {
+                table.insert(key.to_string(), Value::Integer(limit as i64));
+            }
+        }
+    }
+}
+
+// https is served when both files are set, they're reloaded when changed
+#[derive(Debug, Clone)]
+pub struct TlsEnv {
+    pub cert_path: String,
+    pub key_path: String,
+    // plain http on this port redirects to https
+    pub redirect_port: Option<u16>,
+    // 0 disables the Strict-Transport-Security header
+    pub hsts_max_age: u64,
+}
+
+impl TlsEnv {
+    fn new(source: &ConfigSource) -> Option<Self> {
+        let cert_path = source.get_optional::<String>("--tls-cert");
+        let key_path = source.get_optional::<String>("--tls-key");
+        let redirect_port = source.get_optional::<u16>("--https-redirect-port");
+        let hsts_max_age = source.get_or("--hsts-max-age", 31536000);
+
+        match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Some(Self {
+                cert_path,
+                key_path,
+                redirect_port,
+                hsts_max_age,
+            }),
+            (None, None) => {
+                if redirect_port.is_some() {
+                    source.add_error(
+                        "--https-redirect-port requires --tls-cert and --tls-key".to_string(),
+                    );
+                }
+
+                None
+            }
+            _ => {
+                source.add_error("--tls-cert and --tls-key must be set together".to_string());
+
+                None
+            }
+        }
+    }
+
+    fn write_config(&self, table: &mut Table) {
+        table.insert("tls_cert".to_string(), self.cert_path.clone().into());
+        table.insert("tls_key".to_string(), self.key_path.clone().into());
+        if let Some(redirect_port) = self.redirect_port {
+            table.insert(
+                "https_redirect_port".to_string(),
+                Value::Integer(redirect_port.into()),
+            );
+        }
+        table.insert(
+            "hsts_max_age".to_string(),
+            Value::Integer(self.hsts_max_age as i64),
+        );
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Env {
     pub mode: EnvMode,
+    pub log_format: LogFormat,
+    // the default port of bind addresses
     pub port: u16,
+    pub bind: Vec<BindAddress>,
+    // permissions of unix sockets
+    pub unix_socket_mode: u32,
+    // every route is served under it, empty when served at the root
+    pub base_path: String,
+    // index.html and static are read from it instead of the embedded front end
+    pub front_end_dir: Option<String>,
     pub item_per_page: u64,
     pub username: String,
     pub password: String,
     pub storage: StorageEnv,
     pub database: DatabaseEnv,
+    pub quota: QuotaEnv,
+    pub tls: Option<TlsEnv>,
+    // base64 key for encrypting stored files and private messages, disabled if not set
+    pub encryption_key: Option<String>,
+    // days a rotated secret key still decrypts certificates and tokens
+    pub secret_key_grace_period: u64,
+    // seconds given to running uploads when the server is stopped
+    pub shutdown_grace_period: u64,
 }
 
 impl Env {
-    pub fn new() -> Self {
-        let mode = get_arg_value_option("--mode", EnvMode::Pro);
-        let port = get_arg_value_option("--port", 8080);
-        let item_per_page = get_arg_value_option("--item-per-page", 15);
-        let username = get_arg_value::<String>("--username").unwrap();
-        let password = get_arg_value::<String>("--password").unwrap();
-        let storage = StorageEnv::new().unwrap();
-        let database = DatabaseEnv::new().unwrap();
+    pub fn new() -> Result<Self> {
+        let source = ConfigSource::from_env()?;
+
+        let env = Self::from_source(&source);
+
+        source.finish()?;
+
+        Ok(env)
+    }
+
+    pub fn from_source(source: &ConfigSource) -> Self {
+        let mode = source.get_or("--mode", EnvMode::Pro);
+        let log_format = source.get_or("--log-format", LogFormat::Text);
+        let port = source.get_or("--port", 8080);
+        let bind = get_bind(source, port);
+        let unix_socket_mode = get_unix_socket_mode(source);
+        let base_path = get_base_path(source);
+        let front_end_dir = source.get_optional::<String>("--front-end-dir");
+        let item_per_page = source.get_or("--item-per-page", 15);
+        let username = source.get::<String>("--username");
+        let password = source.get::<String>("--password");
+        let storage = StorageEnv::new(source);
+        let database = DatabaseEnv::new(source);
+        let quota = QuotaEnv::new(source);
+        let tls = TlsEnv::new(source);
+        let encryption_key = source.get_optional::<String>("--encryption-key");
+        let secret_key_grace_period = source.get_or("--secret-key-grace-period", 30);
+        let shutdown_grace_period = source.get_or("--shutdown-grace-period", 30);
 
         Self {
             mode,
+            log_format,
             port,
+            bind,
+            unix_socket_mode,
+            base_path,
+            front_end_dir,
             item_per_page,
             username,
             password,
             storage,
             database,
+            quota,
+            tls,
+            encryption_key,
+            secret_key_grace_period,
+            shutdown_grace_period,
         }
     }
+
+    // printed as a config file, secrets are redacted so that it can be shared
+    pub fn to_redacted_config(&self) -> String {
+        let mut table = Table::new();
+
+        table.insert("mode".to_string(), self.mode.to_string().into());
+        table.insert("log_format".to_string(), self.log_format.to_string().into());
+        table.insert("port".to_string(), Value::Integer(self.port.into()));
+        table.insert(
+            "bind".to_string(),
+            self.bind
+                .iter()
+                .map(|address| address.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+                .into(),
+        );
+        table.insert(
+            "unix_socket_mode".to_string(),
+            format!("{:o}", self.unix_socket_mode).into(),
+        );
+        table.insert("base_path".to_string(), self.base_path.clone().into());
+        if let Some(front_end_dir) = &self.front_end_dir {
+            table.insert("front_end_dir".to_string(), front_end_dir.clone().into());
+        }
+        table.insert(
+            "item_per_page".to_string(),
+            Value::Integer(self.item_per_page as i64),
+        );
+        table.insert("username".to_string(), self.username.clone().into());
+        table.insert("password".to_string(), REDACTED.into());
+
+        self.storage.write_config(&mut table);
+        self.database.write_config(&mut table);
+        self.quota.write_config(&mut table);
+
+        if let Some(tls) = &self.tls {
+            tls.write_config(&mut table);
+        }
+
+        if self.encryption_key.is_some() {
+            table.insert("encryption_key".to_string(), REDACTED.into());
+        }
+
+        table.insert(
+            "secret_key_grace_period".to_string(),
+            Value::Integer(self.secret_key_grace_period as i64),
+        );
+        table.insert(
+            "shutdown_grace_period".to_string(),
+            Value::Integer(self.shutdown_grace_period as i64),
+        );
+
+        table.to_string()
+    }
 }
 
 #[cfg(test)]
@@ -231,16 +952,21 @@ pub mod tests {
     use std::env;
     use strum::EnumIter;
 
+    use crate::client::storage::tests::get_stand_in_env;
+
     #[derive(EnumIter)]
     pub enum DBType {
         MySql,
+        Postgres,
         Sqlite,
     }
 
     #[derive(EnumIter)]
     pub enum STType {
         Minio,
+        S3,
         LocalStorage,
+        Memory,
     }
 
     fn get_env_value<T>(key: &str) -> Result<T>
@@ -261,6 +987,10 @@ pub mod tests {
             Ok(Self::MySql(MySqlEnv::new_test()?))
         }
 
+        fn new_postgres() -> Result<Self> {
+            Ok(Self::Postgres(PostgresEnv::new_test()?))
+        }
+
         fn new_sqlite() -> Result<Self> {
             Ok(Self::Sqlite(SqliteEnv::new_test()?))
         }
@@ -282,6 +1012,22 @@ pub mod tests {
         }
     }
 
+    impl PostgresEnv {
+        fn new_test() -> Result<Self> {
+            let endpoint = get_env_value("POSTGRES_ENDPOINT")?;
+            let username = get_env_value("POSTGRES_USERNAME")?;
+            let password = get_env_value("POSTGRES_PASSWORD")?;
+            let database = get_env_value("POSTGRES_DATABASE")?;
+
+            Ok(Self {
+                endpoint,
+                username,
+                password,
+                database,
+            })
+        }
+    }
+
     impl SqliteEnv {
         fn new_test() -> Result<Self> {
             Ok(Self {
@@ -295,6 +1041,10 @@ pub mod tests {
             Ok(Self::Minio(MinioEnv::new_test()?))
         }
 
+        fn new_s3() -> Result<Self> {
+            Ok(Self::S3(S3Env::new_test()?))
+        }
+
         fn new_local_storage() -> Result<Self> {
             Ok(Self::LocalStorage(LocalStorageEnv::new_test()?))
         }
@@ -316,6 +1066,13 @@ pub mod tests {
         }
     }
 
+    impl S3Env {
+        // served by the stand-in, so no real S3 is needed
+        fn new_test() -> Result<Self> {
+            Ok(get_stand_in_env())
+        }
+    }
+
     impl LocalStorageEnv {
         fn new_test() -> Result<Self> {
             Ok(Self {
@@ -340,21 +1097,231 @@ pub mod tests {
         let password = env::var("PASSWORD").unwrap();
         let storage = match st_type {
             STType::Minio => StorageEnv::new_minio().unwrap(),
+            STType::S3 => StorageEnv::new_s3().unwrap(),
             STType::LocalStorage => StorageEnv::new_local_storage().unwrap(),
+            STType::Memory => StorageEnv::Memory,
         };
         let database = match db_type {
             DBType::MySql => DatabaseEnv::new_mysql().unwrap(),
+            DBType::Postgres => DatabaseEnv::new_postgres().unwrap(),
             DBType::Sqlite => DatabaseEnv::new_sqlite().unwrap(),
         };
+        let quota = QuotaEnv::default();
 
         Env {
             mode,
+            log_format: LogFormat::Text,
             port,
+            bind: vec![BindAddress::Tcp(SocketAddr::from(([0, 0, 0, 0], port)))],
+            unix_socket_mode: 0o660,
+            base_path: String::new(),
+            front_end_dir: None,
             item_per_page,
             username,
             password,
             storage,
             database,
+            quota,
+            tls: None,
+            encryption_key: None,
+            secret_key_grace_period: 30,
+            shutdown_grace_period: 30,
+        }
+    }
+
+    fn get_source(args: &[&str], vars: &[(&str, &str)], file: &str) -> ConfigSource {
+        ConfigSource::new(
+            args.iter().map(OsString::from).collect(),
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            file.parse::<Table>().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_env_config_precedence() {
+        let source = get_source(
+            &["--port", "8000"],
+            &[("TRANSFERY_PORT", "8001"), ("TRANSFERY_USERNAME", "var")],
+            r#"
+            port = 8002
+            username = "file"
+            password = "file"
+            sqlite_path = "./test.sqlite"
+            "#,
+        );
+
+        let env = Env::from_source(&source);
+
+        source.finish().unwrap();
+
+        assert_eq!(env.port, 8000);
+        assert_eq!(env.username, "var");
+        assert_eq!(env.password, "file");
+        assert!(
+            matches!(env.database, DatabaseEnv::Sqlite(SqliteEnv { path }) if path == "./test.sqlite")
+        );
+        assert!(
+            matches!(env.storage, StorageEnv::LocalStorage(LocalStorageEnv { path }) if path == "./uploaded")
+        );
+    }
+
+    #[test]
+    fn test_env_config_errors() {
+        let source = get_source(
+            &["--minio", "--memory-storage"],
+            &[("TRANSFERY_PORT", "port")],
+            "mysql = true\ntls_cert = \"./cert.pem\"",
+        );
+
+        Env::from_source(&source);
+
+        let message = source.finish().unwrap_err().to_string();
+
+        // every problem is reported at once
+        for expected in [
+            "invalid value for --port",
+            "missing value for --username",
+            "missing value for --password",
+            "only one of --minio, --memory-storage can be set",
+            "--tls-cert and --tls-key must be set together",
+            "missing value for --minio-endpoint",
+            "missing value for --mysql-password",
+        ] {
+            assert!(message.contains(expected), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_env_config_unused() {
+        let source = get_source(
+            &["--username", "user", "--password", "password"],
+            &[("TRANSFERY_PROT", "8000"), ("TRANSFERY_PORT", "8001")],
+            "minio_endpoint = \"127.0.0.1:9000\"\nsqlite_path = \"./test.sqlite\"",
+        );
+
+        Env::from_source(&source);
+
+        let message = source.finish().unwrap_err().to_string();
+
+        assert!(message.contains("unknown or unused environment variable TRANSFERY_PROT"));
+        assert!(message.contains("unknown or unused config key minio_endpoint"));
+        assert!(!message.contains("TRANSFERY_PORT"));
+        assert!(!message.contains("sqlite_path"));
+    }
+
+    #[test]
+    fn test_env_config_redacted() {
+        let source = get_source(
+            &[
+                "--username",
+                "user",
+                "--password",
+                "user password",
+                "--encryption-key",
+                "encryption key",
+            ],
+            &[("TRANSFERY_MINIO", "true")],
+            r#"
+            minio_endpoint = "127.0.0.1:9000"
+            minio_username = "minio"
+            minio_password = "minio password"
+            minio_bucket = "transfery"
+            "#,
+        );
+
+        let env = Env::from_source(&source);
+
+        source.finish().unwrap();
+
+        let config = env.to_redacted_config();
+
+        assert!(config.contains("username = \"user\""));
+        assert!(config.contains("minio_endpoint = \"127.0.0.1:9000\""));
+        assert!(!config.contains("user password"));
+        assert!(!config.contains("minio password"));
+        assert!(!config.contains("encryption key"));
+
+        // the printed config can be loaded again
+        let table = config.parse::<Table>().unwrap();
+
+        assert_eq!(table["password"].as_str(), Some(REDACTED));
+        assert_eq!(table["minio"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_env_config_bind() {
+        let source = get_source(
+            &["--port", "8000", "--unix-socket-mode", "600"],
+            &[(
+                "TRANSFERY_BIND",
+                "127.0.0.1, [::], [::1]:9000, unix:/run/transfery.sock",
+            )],
+            r#"
+            username = "user"
+            password = "password"
+            "#,
+        );
+
+        let env = Env::from_source(&source);
+
+        source.finish().unwrap();
+
+        assert_eq!(
+            env.bind,
+            vec![
+                BindAddress::Tcp("127.0.0.1:8000".parse().unwrap()),
+                BindAddress::Tcp("[::]:8000".parse().unwrap()),
+                BindAddress::Tcp("[::1]:9000".parse().unwrap()),
+                BindAddress::Unix("/run/transfery.sock".to_string()),
+            ]
+        );
+        assert_eq!(env.unix_socket_mode, 0o600);
+
+        let source = get_source(
+            &["--bind", "localhost,unix:", "--unix-socket-mode", "rw"],
+            &[],
+            "",
+        );
+
+        Env::from_source(&source);
+
+        let message = source.finish().unwrap_err().to_string();
+
+        for expected in [
+            "localhost is not an ip address or unix:path",
+            "missing unix socket path",
+            "invalid value for --unix-socket-mode: rw",
+        ] {
+            assert!(message.contains(expected), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_env_config_base_path() {
+        let mut base_paths = Vec::new();
+
+        for value in ["", "/", "transfery", "/tools/transfery/"] {
+            let source = get_source(&["--base-path", value], &[], "");
+
+            base_paths.push(Env::from_source(&source).base_path);
+        }
+
+        assert_eq!(base_paths, vec!["", "", "/transfery", "/tools/transfery"]);
+
+        for value in ["/a/../b", "/a//b", "/a?b"] {
+            let source = get_source(&["--base-path", value], &[], "");
+
+            Env::from_source(&source);
+
+            let message = source.finish().unwrap_err().to_string();
+
+            assert!(
+                message.contains(&format!("invalid value for --base-path: {}", value)),
+                "{}",
+                message
+            );
         }
     }
 }
@@ -9,10 +9,13 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use std::fmt::Display;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum ErrorType {
     InternalServerError,
     UnauthorizedError,
+    QuotaExceededError,
+    ServiceUnavailableError,
 }
 
 #[derive(Debug)]
@@ -49,6 +52,10 @@ impl Display for Error {
         match self.error_type {
             ErrorType::InternalServerError => write!(f, "Internal server error: {}", self.message),
             ErrorType::UnauthorizedError => write!(f, "Unauthorized error: {}", self.message),
+            ErrorType::QuotaExceededError => write!(f, "Quota exceeded error: {}", self.message),
+            ErrorType::ServiceUnavailableError => {
+                write!(f, "Service unavailable error: {}", self.message)
+            }
         }
     }
 }
@@ -67,11 +74,23 @@ fn unauthorized_response(error_string: String) -> Response {
     into_response(StatusCode::UNAUTHORIZED, error_string)
 }
 
+fn quota_exceeded_response(error_string: String) -> Response {
+    tracing::debug!("Quota exceeded error: {}", error_string);
+    into_response(StatusCode::PAYLOAD_TOO_LARGE, error_string)
+}
+
+fn service_unavailable_response(error_string: String) -> Response {
+    tracing::debug!("Service unavailable error: {}", error_string);
+    into_response(StatusCode::SERVICE_UNAVAILABLE, error_string)
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         match self.error_type {
             ErrorType::InternalServerError => internal_server_error_response(self.message),
             ErrorType::UnauthorizedError => unauthorized_response(self.message),
+            ErrorType::QuotaExceededError => quota_exceeded_response(self.message),
+            ErrorType::ServiceUnavailableError => service_unavailable_response(self.message),
         }
     }
 }
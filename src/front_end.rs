@@ -0,0 +1,197 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+// without the feature only the disk is served, the helpers are kept for the tests
+#![cfg_attr(not(feature = "embed-front-end"), allow(dead_code))]
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+// hashed file names change with their content, so they never need to be checked again
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+const NO_CACHE_CONTROL: &str = "no-cache";
+
+pub struct Asset {
+    pub path: &'static str,
+    pub etag: &'static str,
+    pub data: &'static [u8],
+    pub gzip: Option<&'static [u8]>,
+}
+
+#[cfg(feature = "embed-front-end")]
+mod embedded {
+    use super::Asset;
+
+    // generated by build.rs from the front end directory
+    include!(concat!(env!("OUT_DIR"), "/front_end.rs"));
+}
+
+#[cfg(feature = "embed-front-end")]
+pub fn get(path: &str) -> Option<&'static Asset> {
+    embedded::ASSETS.iter().find(|asset| asset.path == path)
+}
+
+// index-3f2a1b9c.js or app.3f2a1b9c.js, as named by vite and vue cli
+fn is_hashed(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+
+    let hash = match stem.rsplit_once(['-', '.']) {
+        Some((_, hash)) => hash,
+        None => return false,
+    };
+
+    hash.len() >= 8
+        && hash.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !hash.chars().all(|c| c.is_ascii_lowercase())
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    let accept_encoding = match headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(accept_encoding) => accept_encoding,
+        None => return false,
+    };
+
+    accept_encoding.split(',').any(|encoding| {
+        let mut parts = encoding.split(';').map(|part| part.trim());
+
+        let is_gzip = matches!(parts.next(), Some("gzip") | Some("*"));
+        let is_refused = parts.any(|part| part.replace(' ', "") == "q=0");
+
+        is_gzip && !is_refused
+    })
+}
+
+pub fn serve_asset(asset: &Asset, headers: &HeaderMap) -> Response {
+    let cache_control = match is_hashed(asset.path) {
+        true => IMMUTABLE_CACHE_CONTROL,
+        false => NO_CACHE_CONTROL,
+    };
+
+    let is_fresh = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(',').any(|etag| etag.trim() == asset.etag))
+        .unwrap_or(false);
+
+    let mut response = if is_fresh {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let mime = mime_guess::from_path(asset.path).first_or_octet_stream();
+
+        let (data, encoding) = match asset.gzip {
+            Some(gzip) if accepts_gzip(headers) => (gzip, Some("gzip")),
+            _ => (asset.data, None),
+        };
+
+        let mut response = Body::from(data).into_response();
+
+        if let Ok(value) = HeaderValue::from_str(mime.as_ref()) {
+            response.headers_mut().insert(header::CONTENT_TYPE, value);
+        }
+
+        if let Some(encoding) = encoding {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+
+        response
+    };
+
+    let headers = response.headers_mut();
+
+    headers.insert(header::ETAG, HeaderValue::from_static(asset.etag));
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+
+    if asset.gzip.is_some() {
+        headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::tests::{sleep_async, ResponseExt};
+
+    static ASSET: Asset = Asset {
+        path: "static/js/index-3f2a1b9c.js",
+        etag: "\"0123456789abcdef\"",
+        data: b"console.log('test');",
+        gzip: Some(b"gzip"),
+    };
+
+    fn get_headers(pairs: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_front_end_is_hashed() {
+        assert!(is_hashed("static/js/index-BzKj3H9_.js"));
+        assert!(is_hashed("static/js/app.3f2a1b9c.js"));
+        assert!(!is_hashed("index.html"));
+        assert!(!is_hashed("favicon.ico"));
+        assert!(!is_hashed("static/js/material-icons.js"));
+    }
+
+    #[test]
+    fn test_front_end_accepts_gzip() {
+        assert!(accepts_gzip(&get_headers(&[(
+            header::ACCEPT_ENCODING,
+            "gzip, deflate, br"
+        )])));
+        assert!(!accepts_gzip(&get_headers(&[(
+            header::ACCEPT_ENCODING,
+            "br, gzip;q=0"
+        )])));
+        assert!(!accepts_gzip(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_front_end_serve_asset() {
+        let res = serve_asset(&ASSET, &HeaderMap::new());
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::ETAG], ASSET.etag);
+        assert_eq!(
+            res.headers()[header::CACHE_CONTROL],
+            IMMUTABLE_CACHE_CONTROL
+        );
+        assert!(res.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .contains("javascript"));
+        assert_eq!(res.to_string().await.unwrap(), "console.log('test');");
+
+        let res = serve_asset(&ASSET, &get_headers(&[(header::ACCEPT_ENCODING, "gzip")]));
+
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(res.to_string().await.unwrap(), "gzip");
+
+        let res = serve_asset(
+            &ASSET,
+            &get_headers(&[(header::IF_NONE_MATCH, "\"0123456789abcdef\"")]),
+        );
+
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        sleep_async(1).await;
+    }
+}
@@ -0,0 +1,274 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::client::database::models::message::MessageItemType;
+use crate::client::{self, Database, Storage};
+use crate::env::{args_contains, Env};
+use crate::error::Result;
+use crate::utils::{get_current_timestamp, UPLOAD_EXPIRATION};
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct FsckReport {
+    // objects in storage that no message refers to
+    #[serde(rename = "orphanObjects")]
+    pub orphan_objects: Vec<String>,
+    // messages whose objects are gone or whose upload never completed
+    #[serde(rename = "danglingMessages")]
+    pub dangling_messages: Vec<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FsckOptions {
+    #[serde(rename = "removeOrphans", default)]
+    pub remove_orphans: bool,
+    #[serde(rename = "markDangling", default)]
+    pub mark_dangling: bool,
+}
+
+pub async fn fsck(env: &Env) {
+    let storage = client::get_storage(env).await;
+    let database = client::get_database(env).await;
+
+    let options = FsckOptions {
+        remove_orphans: args_contains("--fsck-remove-orphans"),
+        mark_dangling: args_contains("--fsck-mark-dangling"),
+    };
+
+    println!("Checking storage and database...");
+    let report = check(&storage, &database).await.unwrap();
+
+    for object in &report.orphan_objects {
+        println!("orphan object: {}", object);
+    }
+
+    for id in &report.dangling_messages {
+        println!("dangling message: {}", id);
+    }
+
+    println!(
+        "Found {} orphan objects and {} dangling messages.",
+        report.orphan_objects.len(),
+        report.dangling_messages.len()
+    );
+
+    if options.remove_orphans || options.mark_dangling {
+        println!("Repairing...");
+        repair(&storage, &database, &report, &options)
+            .await
+            .unwrap();
+        println!("Repair completed.");
+    }
+}
+
+pub async fn check(storage: &Storage, database: &Database) -> Result<FsckReport> {
+    // storage is listed first, and every upload inserts its message before the object,
+    // so that objects of messages created meanwhile are never orphans
+    let objects = storage.list_object_names().await?;
+    let messages = database.query_message_files_all().await?;
+    let bundle_files = database.query_bundle_files_all().await?;
+
+    let object_set: HashSet<&str> = objects.iter().map(|object| object.as_str()).collect();
+
+    let mut referenced = HashSet::new();
+    let mut bundle_prefixes = Vec::new();
+    let mut dangling_messages = Vec::new();
+
+    let expiration_timestamp = get_current_timestamp() - UPLOAD_EXPIRATION;
+
+    for message in &messages {
+        let file_name = match &message.file_name {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+
+        let is_complete = message.is_complete.unwrap_or(true);
+
+        match message.type_field {
+            MessageItemType::File => {
+                referenced.insert(file_name.clone());
+                referenced.insert(Storage::get_thumbnail_name(file_name));
+
+                if is_complete && !object_set.contains(file_name.as_str()) {
+                    dangling_messages.push(message.id);
+                    continue;
+                }
+            }
+            MessageItemType::Bundle => {
+                // files of an unfinished bundle are not recorded yet
+                bundle_prefixes.push(format!("{}/", file_name));
+
+                let is_missing = bundle_files.iter().any(|bundle_file| {
+                    bundle_file.message_id == message.id
+                        && !object_set.contains(bundle_file.file_name.as_str())
+                });
+
+                if is_complete && is_missing {
+                    dangling_messages.push(message.id);
+                    continue;
+                }
+            }
+            MessageItemType::Text => continue,
+        }
+
+        // the upload has been abandoned
+        if !is_complete && message.timestamp < expiration_timestamp {
+            dangling_messages.push(message.id);
+        }
+    }
+
+    let orphan_objects = objects
+        .iter()
+        .filter(|object| !referenced.contains(*object))
+        .filter(|object| {
+            !bundle_prefixes
+                .iter()
+                .any(|prefix| object.starts_with(prefix))
+        })
+        .cloned()
+        .collect();
+
+    Ok(FsckReport {
+        orphan_objects,
+        dangling_messages,
+    })
+}
+
+pub async fn repair(
+    storage: &Storage,
+    database: &Database,
+    report: &FsckReport,
+    options: &FsckOptions,
+) -> Result<()> {
+    if options.remove_orphans {
+        for object in &report.orphan_objects {
+            storage.remove_object(object).await?;
+            tracing::debug!("removed orphan object: {}", object);
+        }
+    }
+
+    if options.mark_dangling {
+        for id in &report.dangling_messages {
+            database.update_missing(*id).await?;
+            tracing::debug!("marked dangling message: {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::client::database::models::message::MessageItem;
+    use crate::client::database::tests::{get_database, reset as reset_database};
+    use crate::client::storage::tests::{get_storage, init, reset as reset_storage};
+    use crate::env::tests::{DBType, STType};
+    use crate::utils::tests::sleep_async;
+
+    async fn prepare(storage: &Storage, database: &Database) -> Result<Vec<i64>> {
+        let timestamp = get_current_timestamp();
+        let expired_timestamp = timestamp - UPLOAD_EXPIRATION - 1;
+
+        init(storage).await?;
+        database.create_table_message_if_not_exists().await?;
+        database.create_table_bundle_if_not_exists().await?;
+
+        let stored = "stored/a.txt";
+        storage.put_object(stored, b"hello world!").await?;
+        storage
+            .put_object(&Storage::get_thumbnail_name(stored), b"thumbnail")
+            .await?;
+        storage.put_object("bundle/b.txt", b"hello world!").await?;
+        storage.put_object("orphan/c.txt", b"hello world!").await?;
+
+        let items = vec![
+            MessageItem::new_file("a.txt", timestamp, false, stored, true),
+            MessageItem::new_file("b.txt", timestamp, false, "missing/b.txt", true),
+            MessageItem::new_file("c.txt", expired_timestamp, false, "expired/c.txt", false),
+            MessageItem::new_file("d.txt", timestamp, false, "uploading/d.txt", false),
+            MessageItem::new_bundle("bundle", timestamp, false, "bundle", false),
+        ];
+
+        let mut ids = Vec::new();
+
+        for item in items {
+            ids.push(database.insert_message_item(item).await?);
+        }
+
+        Ok(ids)
+    }
+
+    #[tokio::test]
+    async fn test_fsck_check() {
+        async fn inner(storage: &Storage, database: &Database) -> Result<(FsckReport, Vec<i64>)> {
+            let ids = prepare(storage, database).await?;
+
+            let report = check(storage, database).await?;
+
+            Ok((report, ids))
+        }
+
+        let storage = get_storage(STType::LocalStorage).await;
+        let database = get_database(DBType::Sqlite).await;
+
+        let result = inner(&storage, &database).await;
+        reset_storage(&storage).await;
+        reset_database(database).await;
+
+        let (report, ids) = result.unwrap();
+
+        assert_eq!(
+            report,
+            FsckReport {
+                orphan_objects: vec!["orphan/c.txt".to_string()],
+                dangling_messages: vec![ids[1], ids[2]],
+            }
+        );
+
+        sleep_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_fsck_repair() {
+        async fn inner(storage: &Storage, database: &Database) -> Result<(FsckReport, Vec<i64>)> {
+            let ids = prepare(storage, database).await?;
+
+            let report = check(storage, database).await?;
+
+            let options = FsckOptions {
+                remove_orphans: true,
+                mark_dangling: true,
+            };
+
+            repair(storage, database, &report, &options).await?;
+
+            let message = database.query_message_item(ids[1]).await?;
+            assert_eq!(message.unwrap().is_missing, Some(true));
+
+            check(storage, database).await.map(|report| (report, ids))
+        }
+
+        let storage = get_storage(STType::LocalStorage).await;
+        let database = get_database(DBType::Sqlite).await;
+
+        let result = inner(&storage, &database).await;
+        reset_storage(&storage).await;
+        reset_database(database).await;
+
+        let (report, ids) = result.unwrap();
+
+        // marked messages are still reported until they are removed
+        assert!(report.orphan_objects.is_empty());
+        assert_eq!(report.dangling_messages, vec![ids[1], ids[2]]);
+
+        sleep_async(1).await;
+    }
+}
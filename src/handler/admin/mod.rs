@@ -24,12 +24,13 @@ use std::sync::Arc;
 use crate::auth::{AuthChecker, Authorization, Certificate};
 use crate::client::database::models::device::{self, DeviceItem, DeviceUpdateItem};
 use crate::client::database::models::token::{self, TokenNewItem};
-use crate::client::Database;
+use crate::client::{Database, Storage};
 use crate::crypto::Crypto;
 use crate::env::Env;
 use crate::error::Error;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::Result;
+use crate::fsck::{self, FsckOptions, FsckReport};
 use crate::handler::socket::Room;
 use crate::utils::get_current_timestamp;
 
@@ -44,7 +45,7 @@ pub async fn auth(
     Json(params): Json<AuthParams>,
 ) -> Result<Response> {
     tracing::info!("received auth request");
-    tracing::debug!("auth params: {:#?}", params);
+    tracing::debug!("auth params: {:?}", params);
 
     if params.username == env.username && params.password == env.password {
         let max_age = if params.remember_me {
@@ -70,11 +71,13 @@ pub async fn auth(
                 )
             })?;
 
-            let certificate = crypto.encrypt(&certificate_raw)?;
+            // the certificate is a credential, only its expiration is logged
+            tracing::debug!(
+                "certificate issued, expiration timestamp: {}",
+                expiration_timestamp
+            );
 
-            tracing::debug!("certificate: {}", certificate);
-
-            certificate
+            crypto.encrypt(&certificate_raw)?
         };
 
         let device_item = DeviceItem {
@@ -112,7 +115,11 @@ pub static AUTO_LOGIN_PATH: &str = "/autoLogin";
 #[debug_handler]
 pub async fn auto_login(
     _: AuthChecker,
-    Authorization { fingerprint, .. }: Authorization,
+    Authorization {
+        fingerprint,
+        certificate,
+    }: Authorization,
+    Extension(crypto): Extension<Arc<Crypto>>,
     Extension(database): Extension<Arc<Database>>,
     Extension(socketio): Extension<Arc<SocketIo>>,
     Query(AutoLoginParams { sid }): Query<AutoLoginParams>,
@@ -135,6 +142,15 @@ pub async fn auto_login(
 
     database.update_device(device_item).await?;
 
+    // a certificate from an older key is sent back renewed, so it outlives the grace period
+    if let Some(certificate) = certificate.filter(|certificate| !crypto.is_current(certificate)) {
+        let certificate = crypto.encrypt(&crypto.decrypt(&certificate)?)?;
+
+        tracing::info!("certificate renewed with the current key");
+
+        return Ok(certificate.into_response());
+    }
+
     Ok(StatusCode::OK.into_response())
 }
 
@@ -179,7 +195,7 @@ pub async fn device(
 
     let device_items = database.query_device_items().await?;
 
-    tracing::debug!("device items: {:#?}", device_items);
+    tracing::debug!("device items: {:?}", device_items);
 
     Ok(Json(device_items))
 }
@@ -221,20 +237,9 @@ pub async fn create_token(
     Json(params): Json<CreateTokenParams>,
 ) -> Result<Response> {
     tracing::info!("received create token request");
-    tracing::debug!("new token item: {:#?}", params);
-
-    let token = {
-        let token_raw = TokenRaw::new(&env.username, &env.password, params.expiration_timestamp);
-        let token_json = serde_json::to_string(&token_raw).map_err(|e| {
-            Error::context(
-                InternalServerError,
-                e,
-                "failed to convert token raw to json",
-            )
-        })?;
-
-        crypto.encrypt(&token_json)?
-    };
+    tracing::debug!("new token item: {:?}", params);
+
+    let token = gen_token(&env, &crypto, params.expiration_timestamp)?;
 
     let new_token_item = TokenNewItem {
         token,
@@ -252,6 +257,20 @@ pub async fn create_token(
     Ok(StatusCode::OK.into_response())
 }
 
+// also used by the token create command
+pub fn gen_token(env: &Env, crypto: &Crypto, expiration_timestamp: i64) -> Result<String> {
+    let token_raw = TokenRaw::new(&env.username, &env.password, expiration_timestamp);
+    let token_json = serde_json::to_string(&token_raw).map_err(|e| {
+        Error::context(
+            InternalServerError,
+            e,
+            "failed to convert token raw to json",
+        )
+    })?;
+
+    crypto.encrypt(&token_json)
+}
+
 pub static GET_TOKEN_PATH: &str = "/getToken";
 
 #[debug_handler]
@@ -263,7 +282,7 @@ pub async fn get_token(
 
     let tokens = database.query_token_items().await?;
 
-    tracing::debug!("tokens: {:#?}", tokens);
+    tracing::debug!("tokens: {:?}", tokens);
 
     Ok(Json(tokens))
 }
@@ -275,10 +294,12 @@ pub async fn remove_token(
     _: AuthChecker,
     Extension(database): Extension<Arc<Database>>,
     Extension(socketio): Extension<Arc<SocketIo>>,
-    Json(RemoveTokenParams { token }): Json<RemoveTokenParams>,
+    Json(params): Json<RemoveTokenParams>,
 ) -> Result<Response> {
     tracing::info!("received remove token request");
-    tracing::debug!("remove token: {}", token);
+    tracing::debug!("remove token params: {:?}", params);
+
+    let RemoveTokenParams { token } = params;
 
     database.remove_token(token).await?;
 
@@ -289,3 +310,29 @@ pub async fn remove_token(
 
     Ok(StatusCode::OK.into_response())
 }
+
+pub static FSCK_PATH: &str = "/fsck";
+
+#[debug_handler]
+pub async fn fsck(
+    _: AuthChecker,
+    Extension(storage): Extension<Arc<Storage>>,
+    Extension(database): Extension<Arc<Database>>,
+    Json(options): Json<FsckOptions>,
+) -> Result<Json<FsckReport>> {
+    tracing::info!("received fsck request");
+    tracing::debug!("fsck options: {:?}", options);
+
+    let report = fsck::check(&storage, &database).await?;
+
+    tracing::info!(
+        "found {} orphan objects and {} dangling messages",
+        report.orphan_objects.len(),
+        report.dangling_messages.len()
+    );
+    tracing::debug!("fsck report: {:?}", report);
+
+    fsck::repair(&storage, &database, &report, &options).await?;
+
+    Ok(Json(report))
+}
@@ -9,8 +9,11 @@ use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use socketioxide::socket::Sid;
+use std::fmt::Debug;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::utils::REDACTED;
+
+#[derive(Serialize, Deserialize)]
 pub struct AuthParams {
     pub username: String,
     pub password: String,
@@ -21,6 +24,20 @@ pub struct AuthParams {
     pub sid: Sid,
 }
 
+// the password never reaches the log
+impl Debug for AuthParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthParams")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("remember_me", &self.remember_me)
+            .field("fingerprint", &self.fingerprint)
+            .field("browser", &self.browser)
+            .field("sid", &self.sid)
+            .finish()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AutoLoginParams {
     pub sid: Sid,
@@ -44,12 +61,20 @@ pub struct CreateTokenParams {
     pub expiration_timestamp: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct RemoveTokenParams {
     pub token: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Debug for RemoveTokenParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoveTokenParams")
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct TokenRaw {
     pub username: String,
     pub password: String,
@@ -58,6 +83,17 @@ pub struct TokenRaw {
     pub nonce: String,
 }
 
+impl Debug for TokenRaw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenRaw")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("expiration_timestamp", &self.expiration_timestamp)
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
 impl TokenRaw {
     pub fn new(username: &str, password: &str, expiration_timestamp: i64) -> Self {
         let nonce = thread_rng()
@@ -30,17 +30,20 @@ use crate::client::database::models::device::DeviceItem;
 use crate::client::database::models::token::{TokenItem, TokenNewItem};
 use crate::client::database::tests::{get_database, reset as reset_database};
 use crate::client::database::Database;
+use crate::client::storage::tests::{get_storage, init, reset as reset_storage};
+use crate::client::Storage;
 use crate::crypto::tests::get_crypto;
 use crate::env::tests::{get_env, DBType, STType};
 use crate::error::tests::ServerExt;
 use crate::error::Error;
 use crate::error::Result;
+use crate::fsck::{FsckOptions, FsckReport};
 use crate::handler::admin::models::{
     AutoLoginParams, CreateTokenParams, RemoveTokenParams, SignOutParams,
 };
 use crate::handler::admin::{
-    create_token, get_token, remove_token, sign_out, CREATE_TOKEN_PATH, GET_TOKEN_PATH,
-    REMOVE_TOKEN_PATH, SIGN_OUT_PATH,
+    create_token, fsck, get_token, remove_token, sign_out, CREATE_TOKEN_PATH, FSCK_PATH,
+    GET_TOKEN_PATH, REMOVE_TOKEN_PATH, SIGN_OUT_PATH,
 };
 use crate::utils::tests::{sleep_async, ResponseExt};
 use crate::utils::{get_current_timestamp, into_layer};
@@ -547,3 +550,66 @@ async fn test_admin_remove_token() {
 
     sleep_async(1).await;
 }
+
+#[tokio::test]
+async fn test_admin_fsck() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Response> {
+        init(storage).await?;
+        database.create_table_message_if_not_exists().await?;
+        database.create_table_bundle_if_not_exists().await?;
+
+        storage
+            .put_object("test_admin_fsck.txt", b"hello world!")
+            .await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let router = Router::new()
+            .route(FSCK_PATH, post(fsck))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto));
+
+        let options = FsckOptions {
+            remove_orphans: true,
+            mark_dangling: false,
+        };
+
+        let body = serde_json::to_string(&options).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(FSCK_PATH)
+            .header("Authorization", auth)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        assert!(!storage.is_object_exists("test_admin_fsck.txt").await?);
+
+        Ok(res)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let result = result.unwrap();
+    let status = result.status();
+    let body = result.to_string().await.unwrap();
+    let report = serde_json::from_str::<FsckReport>(&body).unwrap();
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        report.orphan_objects,
+        vec!["test_admin_fsck.txt".to_string()]
+    );
+
+    sleep_async(1).await;
+}
@@ -5,7 +5,7 @@
 :license: MIT, see LICENSE for more details.
 */
 
-mod models;
+pub mod models;
 #[cfg(test)]
 mod tests;
 
@@ -40,7 +40,7 @@ pub async fn push_text(
     params: PushTextParams,
 ) -> Result<Response> {
     tracing::info!("received push text request");
-    tracing::debug!("push text params: {:#?}", params);
+    tracing::debug!("push text params: {:?}", params);
 
     let is_valid = {
         let account = Account::from(&params.token, &crypto)?;
@@ -96,7 +96,7 @@ pub async fn latest_text(
         match database.query_message_latest().await? {
             Some(item) => {
                 tracing::info!("latest text pushed");
-                tracing::debug!("latest text item: {:#?}", item);
+                tracing::debug!("latest text item: {:?}", item);
                 Ok(item.content.into_response())
             }
             None => Ok(StatusCode::NOT_FOUND.into_response()),
@@ -9,6 +9,7 @@ use axum::extract::{FromRequest, Query, Request};
 use axum::http::Method;
 use axum::{async_trait, Json};
 use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
 
 use crate::client::database::models::message::{self, MessageItem};
 use crate::crypto::Crypto;
@@ -16,7 +17,7 @@ use crate::env::Env;
 use crate::error::Error;
 use crate::error::ErrorType::{InternalServerError, UnauthorizedError};
 use crate::error::Result;
-use crate::utils::get_current_timestamp;
+use crate::utils::{get_current_timestamp, REDACTED};
 
 impl From<(i64, MessageItem)> for message::Model {
     fn from(
@@ -41,11 +42,14 @@ impl From<(i64, MessageItem)> for message::Model {
             file_name,
             is_complete,
             type_field,
+            has_thumbnail: None,
+            hash: None,
+            is_missing: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Account {
     pub username: String,
     pub password: String,
@@ -53,6 +57,17 @@ pub struct Account {
     pub expiration_timestamp: i64,
 }
 
+// the password never reaches the log
+impl Debug for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Account")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("expiration_timestamp", &self.expiration_timestamp)
+            .finish()
+    }
+}
+
 impl Account {
     pub fn from(token: &str, crypto: &Crypto) -> Result<Self> {
         let account_json = crypto
@@ -73,12 +88,21 @@ impl Account {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct PushTextParams {
     pub content: String,
     pub token: String,
 }
 
+impl Debug for PushTextParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PushTextParams")
+            .field("content", &self.content)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
 #[async_trait]
 impl<S> FromRequest<S> for PushTextParams
 where
@@ -128,7 +152,15 @@ where
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Deserialize)]
 pub struct LatestTextParams {
     pub token: String,
 }
+
+impl Debug for LatestTextParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LatestTextParams")
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
@@ -171,3 +171,25 @@ async fn test_api_latest_text() {
 
     sleep_async(1).await;
 }
+
+#[test]
+fn test_api_params_debug() {
+    let account = Account {
+        username: "test username".to_string(),
+        password: "test password".to_string(),
+        expiration_timestamp: 0,
+    };
+
+    let params = PushTextParams {
+        content: "test content".to_string(),
+        token: "test token".to_string(),
+    };
+
+    let output = format!("{:?} {:?}", account, params);
+
+    // credentials are redacted, the rest is kept for debugging
+    assert!(output.contains("test username"));
+    assert!(output.contains("test content"));
+    assert!(!output.contains("test password"));
+    assert!(!output.contains("test token"));
+}
@@ -11,28 +11,130 @@ mod tests;
 
 use axum::debug_handler;
 use axum::extract::{Extension, Query};
+use axum::http::HeaderMap;
 use axum::response::Response;
-use models::DownloadUrlQueryParams;
+use models::{
+    into_archive_entries, DownloadUrlQueryParams, DownloadZipQueryParams, DownloadZipSelection,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::client::Storage;
-use crate::error::Result;
+use crate::auth::AuthState;
+use crate::client::database::models::message::MessageItemType;
+use crate::client::{Database, Storage};
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::utils::get_current_timestamp;
 
 pub static DOWNLOAD_PATH: &str = "/download";
 
 #[debug_handler]
 pub async fn download(
     Extension(storage): Extension<Arc<Storage>>,
+    headers: HeaderMap,
     Query(params): Query<DownloadUrlQueryParams>,
 ) -> Result<Response> {
     tracing::info!("received download request");
 
-    let file_name = params.file_name.clone();
+    let file_name = params.into_file_name()?;
 
-    let response = storage.get_download_response(&file_name).await?;
+    let response = storage.get_download_response(&file_name, &headers).await?;
 
     tracing::info!("download response pushed");
-    tracing::debug!("download response: {:#?}", response);
+    tracing::debug!("download response: {:?}", response);
+
+    Ok(response)
+}
+
+pub static PREVIEW_PATH: &str = "/preview";
+
+#[debug_handler]
+pub async fn preview(
+    Extension(storage): Extension<Arc<Storage>>,
+    headers: HeaderMap,
+    Query(params): Query<DownloadUrlQueryParams>,
+) -> Result<Response> {
+    tracing::info!("received preview request");
+
+    let file_name = params.into_file_name()?;
+
+    let response = storage.get_preview_response(&file_name, &headers).await?;
+
+    tracing::info!("preview response pushed");
+    tracing::debug!("preview response: {:?}", response);
+
+    Ok(response)
+}
+
+pub static THUMBNAIL_PATH: &str = "/thumbnail";
+
+#[debug_handler]
+pub async fn thumbnail(
+    Extension(storage): Extension<Arc<Storage>>,
+    headers: HeaderMap,
+    Query(params): Query<DownloadUrlQueryParams>,
+) -> Result<Response> {
+    tracing::info!("received thumbnail request");
+
+    let file_name = params.into_file_name()?;
+
+    let response = storage.get_thumbnail_response(&file_name, &headers).await?;
+
+    tracing::info!("thumbnail response pushed");
+
+    Ok(response)
+}
+
+pub static DOWNLOAD_ZIP_PATH: &str = "/downloadZip";
+
+#[debug_handler]
+pub async fn download_zip(
+    AuthState(is_authorized): AuthState,
+    Extension(storage): Extension<Arc<Storage>>,
+    Extension(database): Extension<Arc<Database>>,
+    Query(params): Query<DownloadZipQueryParams>,
+) -> Result<Response> {
+    tracing::info!("received download zip request");
+
+    let items = match DownloadZipSelection::try_from(params)? {
+        DownloadZipSelection::Ids(ids) => {
+            tracing::debug!("download zip ids: {:?}", ids);
+
+            database
+                .query_message_files_by_ids(ids, is_authorized)
+                .await?
+        }
+        DownloadZipSelection::TimeRange(start, end) => {
+            tracing::debug!("download zip time range: {} - {}", start, end);
+
+            database
+                .query_message_files_by_timestamp(start, end, is_authorized)
+                .await?
+        }
+    };
+
+    let mut bundles = HashMap::new();
+
+    for item in items
+        .iter()
+        .filter(|item| item.type_field == MessageItemType::Bundle)
+    {
+        bundles.insert(item.id, database.query_bundle_files(item.id).await?);
+    }
+
+    let entries = into_archive_entries(items, bundles);
+
+    if entries.is_empty() {
+        return Err(Error::new(InternalServerError, "no file found to download"));
+    }
+
+    tracing::debug!("download zip entries: {:?}", entries);
+
+    let archive_name = format!("transfery_{}.zip", get_current_timestamp());
+
+    let response = storage.get_archive_response(&archive_name, entries)?;
+
+    tracing::info!("download zip response pushed");
 
     Ok(response)
 }
@@ -6,9 +6,154 @@
 */
 
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::client::database::models::bundle;
+use crate::client::database::models::message::{MessageItemType, Model};
+use crate::client::storage::models::ArchiveEntry;
+use crate::client::Storage;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
 
 #[derive(Deserialize)]
 pub struct DownloadUrlQueryParams {
     #[serde[rename = "fileName"]]
     pub file_name: String,
 }
+
+impl DownloadUrlQueryParams {
+    // the routes are public, so the name must not reach outside the storage
+    pub fn into_file_name(self) -> Result<String> {
+        if Storage::is_valid_object_name(&self.file_name) {
+            Ok(self.file_name)
+        } else {
+            Err(Error::new(
+                InternalServerError,
+                format!("invalid file name {}", self.file_name),
+            ))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DownloadZipQueryParams {
+    // comma separated message ids
+    pub ids: Option<String>,
+    #[serde(rename = "startTimestamp")]
+    pub start_timestamp: Option<i64>,
+    #[serde(rename = "endTimestamp")]
+    pub end_timestamp: Option<i64>,
+}
+
+pub enum DownloadZipSelection {
+    Ids(Vec<i64>),
+    TimeRange(i64, i64),
+}
+
+impl TryFrom<DownloadZipQueryParams> for DownloadZipSelection {
+    type Error = Error;
+
+    fn try_from(params: DownloadZipQueryParams) -> Result<Self> {
+        match params {
+            DownloadZipQueryParams { ids: Some(ids), .. } => {
+                let ids = ids
+                    .split(',')
+                    .map(|id| id.trim())
+                    .filter(|id| !id.is_empty())
+                    .map(|id| id.parse::<i64>())
+                    .collect::<std::result::Result<Vec<i64>, _>>()
+                    .map_err(|e| Error::context(InternalServerError, e, "failed to parse ids"))?;
+
+                Ok(Self::Ids(ids))
+            }
+            DownloadZipQueryParams {
+                start_timestamp: Some(start),
+                end_timestamp: Some(end),
+                ..
+            } => Ok(Self::TimeRange(start, end)),
+            _ => Err(Error::new(
+                InternalServerError,
+                "either ids or startTimestamp and endTimestamp is required",
+            )),
+        }
+    }
+}
+
+// names come from user input, keep only the last path component
+fn sanitize_entry_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+
+    if name.is_empty() || name == "." || name == ".." {
+        "file".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// files with the same name would overwrite each other when extracted
+fn dedup_entry_name(name: String, names: &mut HashSet<String>) -> String {
+    if names.insert(name.clone()) {
+        return name;
+    }
+
+    let path = Path::new(&name);
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(&name);
+
+    let extension = path.extension().and_then(|extension| extension.to_str());
+
+    let mut index = 1;
+
+    loop {
+        let new_name = match extension {
+            Some(extension) => format!("{} ({}).{}", stem, index, extension),
+            None => format!("{} ({})", stem, index),
+        };
+
+        if names.insert(new_name.clone()) {
+            return new_name;
+        }
+
+        index += 1;
+    }
+}
+
+// files of a bundle are placed under a directory named after the bundle
+pub fn into_archive_entries(
+    items: Vec<Model>,
+    mut bundles: HashMap<i64, Vec<bundle::Model>>,
+) -> Vec<ArchiveEntry> {
+    let mut names = HashSet::new();
+    let mut entries = Vec::new();
+
+    for item in items {
+        let name = dedup_entry_name(sanitize_entry_name(&item.content), &mut names);
+
+        match item.type_field {
+            MessageItemType::Bundle => {
+                for file in bundles.remove(&item.id).unwrap_or_default() {
+                    entries.push(ArchiveEntry {
+                        name: format!("{}/{}", name, file.path),
+                        object: file.file_name,
+                        timestamp: item.timestamp,
+                    });
+                }
+            }
+            _ => {
+                if let Some(object) = item.file_name {
+                    entries.push(ArchiveEntry {
+                        name,
+                        object,
+                        timestamp: item.timestamp,
+                    });
+                }
+            }
+        }
+    }
+
+    entries
+}
@@ -6,16 +6,24 @@
 */
 
 use axum::body::Body;
-use axum::http::{Method, Request, StatusCode};
+use axum::http::{header, Method, Request, StatusCode};
 use axum::response::Response;
 use axum::routing::get;
 use axum::Router;
 use tower::ServiceExt;
 
-use super::{download, DOWNLOAD_PATH};
-use crate::client::storage::tests::{get_storage, init, reset, upload_data};
+use super::{
+    download, download_zip, preview, thumbnail, DOWNLOAD_PATH, DOWNLOAD_ZIP_PATH, PREVIEW_PATH,
+    THUMBNAIL_PATH,
+};
+use crate::auth::tests::gen_auth;
+use crate::client::database::models::message::MessageItem;
+use crate::client::database::tests::{get_database, reset as reset_database};
+use crate::client::storage::tests::{fake_image, get_storage, init, reset, upload_data};
 use crate::client::storage::Storage;
-use crate::env::tests::STType;
+use crate::client::Database;
+use crate::crypto::tests::get_crypto;
+use crate::env::tests::{DBType, STType};
 use crate::error::tests::ServerExt;
 use crate::error::{Error, Result};
 use crate::utils::into_layer;
@@ -26,8 +34,8 @@ async fn test_download_download_url() {
     async fn inner(storage: &Storage) -> Result<Response> {
         let remote_path = "test.txt";
 
-        init(&storage).await?;
-        upload_data(&storage, remote_path).await?;
+        init(storage).await?;
+        upload_data(storage, remote_path).await?;
 
         let router = Router::new()
             .route(DOWNLOAD_PATH, get(download))
@@ -37,12 +45,9 @@ async fn test_download_download_url() {
             .method(Method::GET)
             .uri(&format!("{}?fileName={}", DOWNLOAD_PATH, remote_path))
             .body(Body::empty())
-            .map_err(|e| Error::req_build_error(e))?;
+            .map_err(Error::req_build_error)?;
 
-        let res = router
-            .oneshot(req)
-            .await
-            .map_err(|e| Error::req_send_error(e))?;
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
 
         Ok(res)
     }
@@ -57,3 +62,230 @@ async fn test_download_download_url() {
 
     sleep_async(1).await;
 }
+
+#[tokio::test]
+async fn test_download_preview() {
+    async fn inner(storage: &Storage) -> Result<Response> {
+        let remote_path = "test_preview.txt";
+
+        init(storage).await?;
+        upload_data(storage, remote_path).await?;
+
+        let router = Router::new()
+            .route(PREVIEW_PATH, get(preview))
+            .layer(into_layer(storage.clone()));
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&format!("{}?fileName={}", PREVIEW_PATH, remote_path))
+            .header(header::RANGE, "bytes=0-4")
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok(res)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&storage).await;
+
+    reset(&storage).await;
+
+    let res = result.unwrap();
+
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        res.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "inline; filename=\"test_preview.txt\""
+    );
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_download_thumbnail() {
+    async fn inner(storage: &Storage) -> Result<Response> {
+        let remote_path = "test_thumbnail.png";
+
+        init(storage).await?;
+        storage.put_object(remote_path, &fake_image()).await?;
+        storage.create_thumbnail(remote_path).await?;
+
+        let router = Router::new()
+            .route(THUMBNAIL_PATH, get(thumbnail))
+            .layer(into_layer(storage.clone()));
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&format!("{}?fileName={}", THUMBNAIL_PATH, remote_path))
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok(res)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&storage).await;
+
+    reset(&storage).await;
+
+    let res = result.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "image/jpeg"
+    );
+    assert!(res.headers().contains_key(header::CACHE_CONTROL));
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_download_invalid_file_name() {
+    async fn inner(storage: &Storage) -> Result<Vec<StatusCode>> {
+        init(storage).await?;
+        upload_data(storage, "test.txt").await?;
+
+        let router = Router::new()
+            .route(DOWNLOAD_PATH, get(download))
+            .route(PREVIEW_PATH, get(preview))
+            .route(THUMBNAIL_PATH, get(thumbnail))
+            .layer(into_layer(storage.clone()));
+
+        let mut statuses = Vec::new();
+
+        for path in [DOWNLOAD_PATH, PREVIEW_PATH, THUMBNAIL_PATH] {
+            // each of them points at the uploaded file through the file system
+            for file_name in ["dir/../test.txt", "./test.txt", "dir//../test.txt"] {
+                let req = Request::builder()
+                    .method(Method::GET)
+                    .uri(&format!("{}?fileName={}", path, file_name))
+                    .body(Body::empty())
+                    .map_err(Error::req_build_error)?;
+
+                let res = router
+                    .clone()
+                    .oneshot(req)
+                    .await
+                    .map_err(Error::req_send_error)?;
+
+                statuses.push(res.status());
+            }
+        }
+
+        Ok(statuses)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&storage).await;
+
+    reset(&storage).await;
+
+    // names outside the storage are rejected before reaching it
+    assert!(result
+        .unwrap()
+        .iter()
+        .all(|status| *status == StatusCode::INTERNAL_SERVER_ERROR));
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_download_download_zip() {
+    async fn inner(database: &Database, storage: &Storage) -> Result<Response> {
+        let crypto = get_crypto();
+        let remote_path = "test_download_zip.txt";
+
+        let item = MessageItem::new_file("test.txt", 0, true, remote_path, true);
+
+        database.create_table_message_if_not_exists().await?;
+        let id = database.insert_message_item(item).await?;
+
+        init(storage).await?;
+        upload_data(storage, remote_path).await?;
+
+        let router = Router::new()
+            .route(DOWNLOAD_ZIP_PATH, get(download_zip))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()));
+
+        let authorization = gen_auth(&crypto);
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&format!("{}?ids={}", DOWNLOAD_ZIP_PATH, id))
+            .header("Authorization", authorization)
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok(res)
+    }
+
+    let database = get_database(DBType::Sqlite).await;
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&database, &storage).await;
+
+    reset_database(database).await;
+    reset(&storage).await;
+
+    let res = result.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/zip"
+    );
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_download_display_name() {
+    async fn inner(storage: &Storage) -> Result<Response> {
+        init(storage).await?;
+
+        let remote_path = storage.create_object_name("test_display_name.txt").await?;
+        storage.put_object(&remote_path, b"hello world!").await?;
+
+        let router = Router::new()
+            .route(DOWNLOAD_PATH, get(download))
+            .layer(into_layer(storage.clone()));
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&format!("{}?fileName={}", DOWNLOAD_PATH, remote_path))
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok(res)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&storage).await;
+
+    reset(&storage).await;
+
+    let res = result.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "attachment; filename=\"test_display_name.txt\""
+    );
+
+    sleep_async(1).await;
+}
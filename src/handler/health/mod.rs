@@ -0,0 +1,89 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+mod models;
+#[cfg(test)]
+mod tests;
+
+use models::{Check, ReadyResponse, VersionResponse};
+
+use axum::debug_handler;
+use axum::extract::{Extension, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::{Database, Storage};
+use crate::env::Env;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::shutdown::Shutdown;
+
+// a probe shouldn't hang on an unreachable backend
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub static HEALTHZ_PATH: &str = "/healthz";
+
+// the process is alive, the backends aren't checked
+pub async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+pub static READYZ_PATH: &str = "/readyz";
+
+#[debug_handler]
+pub async fn readyz(
+    Extension(database): Extension<Arc<Database>>,
+    Extension(storage): Extension<Arc<Storage>>,
+    Extension(shutdown): Extension<Arc<Shutdown>>,
+) -> Response {
+    let (database, storage) = tokio::join!(probe(database.ping()), probe(storage.check()));
+
+    let shutting_down = shutdown.is_draining();
+
+    let result = ReadyResponse {
+        ready: database.ok && storage.ok && !shutting_down,
+        shutting_down,
+        database,
+        storage,
+    };
+
+    let status = if result.ready {
+        StatusCode::OK
+    } else {
+        tracing::warn!("not ready: {:?}", result);
+
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(result)).into_response()
+}
+
+async fn probe<F>(future: F) -> Check
+where
+    F: Future<Output = Result<()>>,
+{
+    let result = tokio::time::timeout(CHECK_TIMEOUT, future)
+        .await
+        .unwrap_or_else(|e| Err(Error::context(InternalServerError, e, "check timed out")));
+
+    Check::from(result)
+}
+
+pub static VERSION_PATH: &str = "/version";
+
+#[debug_handler]
+pub async fn version(Extension(env): Extension<Arc<Env>>) -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        database: env.database.name().to_string(),
+        storage: env.storage.name().to_string(),
+        encryption: env.encryption_key.is_some(),
+    })
+}
@@ -0,0 +1,49 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<Result<()>> for Check {
+    fn from(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => Self {
+                ok: true,
+                error: None,
+            },
+            Err(e) => Self {
+                ok: false,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReadyResponse {
+    pub ready: bool,
+    #[serde(rename = "shuttingDown")]
+    pub shutting_down: bool,
+    pub database: Check,
+    pub storage: Check,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VersionResponse {
+    pub version: String,
+    pub database: String,
+    pub storage: String,
+    pub encryption: bool,
+}
@@ -0,0 +1,186 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{Method, StatusCode};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use tower::ServiceExt;
+
+use super::models::{ReadyResponse, VersionResponse};
+use super::{healthz, readyz, version, HEALTHZ_PATH, READYZ_PATH, VERSION_PATH};
+
+use crate::client::database::tests::{get_database, reset as reset_database};
+use crate::client::storage::tests::{get_storage, init, reset as reset_storage};
+use crate::client::{Database, Storage};
+use crate::env::tests::{get_env, DBType, STType};
+use crate::env::{LocalStorageEnv, StorageEnv};
+use crate::error::tests::ServerExt;
+use crate::error::Error;
+use crate::error::Result;
+use crate::shutdown::tests::get_shutdown;
+use crate::shutdown::Shutdown;
+use crate::utils::into_layer;
+use crate::utils::tests::{sleep_async, ResponseExt};
+
+fn get_router(storage: &Storage, database: &Database, shutdown: Shutdown) -> Router {
+    Router::new()
+        .route(HEALTHZ_PATH, get(healthz))
+        .route(READYZ_PATH, get(readyz))
+        .route(VERSION_PATH, get(version))
+        .layer(into_layer(get_env(DBType::Sqlite, STType::LocalStorage)))
+        .layer(into_layer(storage.clone()))
+        .layer(into_layer(database.clone()))
+        .layer(into_layer(shutdown))
+}
+
+async fn send(router: Router, path: &str) -> Result<Response> {
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(path)
+        .body(Body::empty())
+        .map_err(Error::req_build_error)?;
+
+    router.oneshot(req).await.map_err(Error::req_send_error)
+}
+
+async fn send_readyz(router: Router) -> Result<(StatusCode, ReadyResponse)> {
+    let res = send(router, READYZ_PATH).await?;
+    let status = res.status();
+
+    let result = serde_json::from_str(&res.to_string().await?).map_err(Error::deserialize_error)?;
+
+    Ok((status, result))
+}
+
+#[tokio::test]
+async fn test_health_healthz() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Response> {
+        let router = get_router(storage, database, get_shutdown());
+
+        send(router, HEALTHZ_PATH).await
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_database(database).await;
+
+    assert_eq!(result.unwrap().status(), StatusCode::OK);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_health_readyz() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<(StatusCode, ReadyResponse)> {
+        init(storage).await?;
+
+        let router = get_router(storage, database, get_shutdown());
+
+        send_readyz(router).await
+    }
+
+    for st_type in STType::iter() {
+        let storage = get_storage(st_type).await;
+        let database = get_database(DBType::Sqlite).await;
+
+        let result = inner(&storage, &database).await;
+        reset_storage(&storage).await;
+        reset_database(database).await;
+
+        let (status, result) = result.unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(result.ready);
+        assert!(result.database.ok);
+        assert!(result.storage.ok);
+        assert!(result.storage.error.is_none());
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_health_readyz_not_ready() {
+    async fn inner(database: &Database) -> Result<Vec<(StatusCode, ReadyResponse)>> {
+        // a directory can't be created inside a file
+        let storage = Storage::new(
+            &StorageEnv::LocalStorage(LocalStorageEnv {
+                path: "./Cargo.toml/uploaded".to_string(),
+            }),
+            None,
+        )
+        .await?;
+
+        let unwritable = send_readyz(get_router(&storage, database, get_shutdown())).await?;
+
+        let storage = get_storage(STType::Memory).await;
+        let shutdown = get_shutdown();
+
+        shutdown.drain(Duration::from_secs(1)).await;
+
+        let shutting_down = send_readyz(get_router(&storage, database, shutdown)).await?;
+
+        Ok(vec![unwritable, shutting_down])
+    }
+
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&database).await;
+    reset_database(database).await;
+
+    let result = result.unwrap();
+
+    let (status, unwritable) = &result[0];
+
+    assert_eq!(*status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!unwritable.ready);
+    assert!(unwritable.database.ok);
+    assert!(!unwritable.storage.ok);
+    assert!(unwritable.storage.error.is_some());
+
+    let (status, shutting_down) = &result[1];
+
+    assert_eq!(*status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!shutting_down.ready);
+    assert!(shutting_down.shutting_down);
+    assert!(shutting_down.storage.ok);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_health_version() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<VersionResponse> {
+        let router = get_router(storage, database, get_shutdown());
+
+        let res = send(router, VERSION_PATH).await?;
+
+        serde_json::from_str(&res.to_string().await?).map_err(Error::deserialize_error)
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_database(database).await;
+
+    let result = result.unwrap();
+
+    assert_eq!(result.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(result.database, "sqlite");
+    assert_eq!(result.storage, "local");
+    assert!(!result.encryption);
+
+    sleep_async(1).await;
+}
@@ -5,19 +5,132 @@
 :license: MIT, see LICENSE for more details.
 */
 
-use axum::response::Html;
+use axum::extract::Extension;
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Router;
+use std::path::Path;
+use std::sync::Arc;
 use tokio::fs::read_to_string;
+use tower_http::services::ServeDir;
 
+use crate::env::Env;
 use crate::error::Error;
 use crate::error::ErrorType::InternalServerError;
 use crate::error::Result;
 
 pub static INDEX_PATH: &str = "/";
 
-pub async fn index() -> Result<Html<String>> {
-    let html = read_to_string("./index.html")
+pub static STATIC_PATH: &str = "/static";
+
+// absolute links are moved under the base path, the front end reads it from window.BASE_PATH
+fn with_base_path(html: &str, base_path: &str) -> String {
+    if base_path.is_empty() {
+        return html.to_string();
+    }
+
+    let mut html = html.to_string();
+
+    for attribute in ["src=\"", "href=\"", "action=\""] {
+        let mut result = String::new();
+        let mut rest = html.as_str();
+
+        while let Some(index) = rest.find(attribute) {
+            let (head, tail) = rest.split_at(index + attribute.len());
+            result.push_str(head);
+
+            // protocol relative links point to other hosts
+            if tail.starts_with('/') && !tail.starts_with("//") {
+                result.push_str(base_path);
+            }
+
+            rest = tail;
+        }
+
+        result.push_str(rest);
+        html = result;
+    }
+
+    let script = format!("<script>window.BASE_PATH = \"{}\";</script>", base_path);
+
+    match html.find("<head>") {
+        Some(index) => html.insert_str(index + "<head>".len(), &script),
+        None => html.insert_str(0, &script),
+    }
+
+    html
+}
+
+// the embedded front end is used unless a directory is given
+async fn read_index(front_end_dir: Option<&str>) -> Result<String> {
+    #[cfg(feature = "embed-front-end")]
+    if front_end_dir.is_none() {
+        if let Some(asset) = crate::front_end::get("index.html") {
+            return Ok(String::from_utf8_lossy(asset.data).to_string());
+        }
+    }
+
+    let path = Path::new(front_end_dir.unwrap_or(".")).join("index.html");
+
+    read_to_string(path)
         .await
-        .map_err(|e| Error::context(InternalServerError, e, "failed to read index.html"))?;
+        .map_err(|e| Error::context(InternalServerError, e, "failed to read index.html"))
+}
+
+// links in it change with new builds, so it's always revalidated
+pub async fn index(Extension(env): Extension<Arc<Env>>) -> Result<Response> {
+    let html = read_index(env.front_end_dir.as_deref()).await?;
+
+    Ok((
+        [(header::CACHE_CONTROL, "no-cache")],
+        Html(with_base_path(&html, &env.base_path)),
+    )
+        .into_response())
+}
+
+#[cfg(feature = "embed-front-end")]
+async fn static_file(
+    axum::extract::Path(path): axum::extract::Path<String>,
+    headers: axum::http::HeaderMap,
+) -> Response {
+    let path = format!("{}/{}", STATIC_PATH.trim_start_matches('/'), path);
+
+    match crate::front_end::get(&path) {
+        Some(asset) => crate::front_end::serve_asset(asset, &headers),
+        None => axum::http::StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// files on disk are served with their .gz and .br variants when present
+pub fn static_router(front_end_dir: Option<&str>) -> Router {
+    #[cfg(feature = "embed-front-end")]
+    if front_end_dir.is_none() {
+        return Router::new().route(
+            &format!("{}/*path", STATIC_PATH),
+            axum::routing::get(static_file),
+        );
+    }
+
+    let dir = Path::new(front_end_dir.unwrap_or(".")).join("static");
+
+    Router::new().nest_service(
+        STATIC_PATH,
+        ServeDir::new(dir).precompressed_gzip().precompressed_br(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_with_base_path() {
+        let html = r#"<html><head><script src="/static/index.js"></script><link href="//cdn.example.com/a.css"></head><body><a href="https://example.com/">a</a></body></html>"#;
 
-    Ok(Html(html))
+        assert_eq!(with_base_path(html, ""), html);
+        assert_eq!(
+            with_base_path(html, "/transfery"),
+            r#"<html><head><script>window.BASE_PATH = "/transfery";</script><script src="/transfery/static/index.js"></script><link href="//cdn.example.com/a.css"></head><body><a href="https://example.com/">a</a></body></html>"#
+        );
+    }
 }
@@ -10,8 +10,8 @@ mod models;
 mod tests;
 
 use models::{
-    NewItemParams, NewItemResponse, PageQueryParams, RemoveAllParams, RemoveItemParams,
-    SyncQueryParams,
+    BundleQueryParams, NewItemParams, NewItemResponse, PageQueryParams, RemoveAllParams,
+    RemoveItemParams, SyncQueryParams,
 };
 
 use axum::extract::{Extension, Query};
@@ -22,11 +22,12 @@ use socketioxide::SocketIo;
 use std::sync::Arc;
 
 use crate::auth::{AuthChecker, AuthState};
+use crate::client::database::models::bundle;
 use crate::client::database::models::message::{MessageItem, MessageItemType, Model};
 use crate::client::{Database, Storage};
 use crate::env::Env;
 use crate::error::Error;
-use crate::error::ErrorType::InternalServerError;
+use crate::error::ErrorType::{InternalServerError, UnauthorizedError};
 use crate::error::Result;
 use crate::handler::socket::Room;
 
@@ -47,7 +48,7 @@ pub async fn page(
         .await?;
 
     tracing::info!("new page pushed");
-    tracing::debug!("page result: {:#?}", result);
+    tracing::debug!("page result: {:?}", result);
 
     Ok(Json(result))
 }
@@ -68,7 +69,7 @@ pub async fn sync(
         .await?;
 
     tracing::info!("synced");
-    tracing::debug!("sync result: {:#?}", result);
+    tracing::debug!("sync result: {:?}", result);
 
     Ok(Json(result))
 }
@@ -82,7 +83,7 @@ pub async fn new_item(
     Json(item): Json<NewItemParams>,
 ) -> Result<Json<NewItemResponse>> {
     tracing::info!("received new item request");
-    tracing::debug!("new item: {:#?}", item);
+    tracing::debug!("new item: {:?}", item);
 
     let sid = item.sid;
     let item_id = database
@@ -140,33 +141,11 @@ pub async fn remove_item(
     Json(item): Json<RemoveItemParams>,
 ) -> Result<Response> {
     tracing::info!("received remove item request");
-    tracing::debug!("received item to be removed: {:#?}", item);
+    tracing::debug!("received item to be removed: {:?}", item);
 
     let sid = item.sid;
 
-    database.remove_message_item(item.id).await?;
-
-    tracing::info!("removed item in db");
-
-    match item.type_field {
-        MessageItemType::File => {
-            let file_name = item.file_name;
-
-            match file_name {
-                Some(file_name) => {
-                    storage.remove_object(&file_name).await?;
-                    tracing::info!("removed item in storage");
-                }
-                None => {
-                    return Err(Error::new(
-                        InternalServerError,
-                        "missed field fileName for file type in RemoveItemParams",
-                    ));
-                }
-            }
-        }
-        _ => {}
-    }
+    remove_message(&database, &storage, item.id).await?;
 
     socketio
         .to(Room::Public)
@@ -179,6 +158,47 @@ pub async fn remove_item(
     Ok(StatusCode::OK.into_response())
 }
 
+// removes the message along with the objects that only it refers to
+pub async fn remove_message(database: &Database, storage: &Storage, id: i64) -> Result<()> {
+    // the objects to remove come from the stored message, never from the client
+    let item = match database.query_message_item(id).await? {
+        Some(item) => item,
+        // already removed, e.g. by another client
+        None => return Ok(()),
+    };
+
+    database.remove_message_item(id).await?;
+
+    tracing::info!("removed item in db");
+
+    match item.type_field {
+        MessageItemType::File => {
+            let file_name = item.file_name.ok_or_else(|| {
+                Error::new(InternalServerError, "missed field fileName for file type")
+            })?;
+
+            // deduplicated messages share the object, keep it until the last one goes
+            if database.count_message_file_references(&file_name).await? == 0 {
+                storage.remove_object(&file_name).await?;
+                storage.remove_thumbnail(&file_name).await?;
+                tracing::info!("removed item in storage");
+            }
+        }
+        MessageItemType::Bundle => {
+            let file_name = item.file_name.ok_or_else(|| {
+                Error::new(InternalServerError, "missed field fileName for bundle type")
+            })?;
+
+            database.remove_bundle_files(id).await?;
+            storage.remove_prefix(&file_name).await?;
+            tracing::info!("removed bundle in storage");
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
 pub static REMOVE_ALL_PATH: &str = "/removeAll";
 
 #[debug_handler]
@@ -194,6 +214,7 @@ pub async fn remove_all(
     let sid = item.sid;
 
     database.remove_message_all().await?;
+    database.remove_bundle_all().await?;
 
     tracing::info!("removed all in db");
 
@@ -211,3 +232,31 @@ pub async fn remove_all(
 
     Ok(StatusCode::OK.into_response())
 }
+
+pub static BUNDLE_PATH: &str = "/bundle";
+
+#[debug_handler]
+pub async fn bundle(
+    AuthState(is_authorized): AuthState,
+    Extension(database): Extension<Arc<Database>>,
+    Query(BundleQueryParams { id }): Query<BundleQueryParams>,
+) -> Result<Json<Vec<bundle::Model>>> {
+    tracing::info!("received bundle request");
+    tracing::debug!("bundle id: {}", id);
+
+    let item = database
+        .query_message_item(id)
+        .await?
+        .ok_or_else(|| Error::new(InternalServerError, "bundle not found"))?;
+
+    if item.is_private && !is_authorized {
+        return Err(Error::new(UnauthorizedError, "bundle is private"));
+    }
+
+    let result = database.query_bundle_files(id).await?;
+
+    tracing::info!("bundle pushed");
+    tracing::debug!("bundle result: {:?}", result);
+
+    Ok(Json(result))
+}
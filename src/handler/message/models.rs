@@ -36,6 +36,9 @@ impl From<(i64, NewItemParams)> for Model {
             file_name,
             is_complete,
             type_field,
+            has_thumbnail: None,
+            hash: None,
+            is_missing: None,
         }
     }
 }
@@ -45,6 +48,11 @@ pub struct PageQueryParams {
     pub size: u64,
 }
 
+#[derive(Deserialize)]
+pub struct BundleQueryParams {
+    pub id: i64,
+}
+
 #[derive(Deserialize)]
 pub struct SyncQueryParams {
     #[serde(rename = "latestId")]
@@ -74,7 +82,7 @@ impl From<&NewItemParams> for Result<MessageItem> {
                 new_item.timestamp,
                 new_item.is_private,
             )),
-            MessageItemType::File => {
+            MessageItemType::File | MessageItemType::Bundle => {
                 let file_name = match new_item.file_name.clone() {
                     Some(file_name) => file_name,
                     None => {
@@ -95,7 +103,12 @@ impl From<&NewItemParams> for Result<MessageItem> {
                     }
                 };
 
-                Ok(MessageItem::new_file(
+                let new = match new_item.type_field {
+                    MessageItemType::Bundle => MessageItem::new_bundle,
+                    _ => MessageItem::new_file,
+                };
+
+                Ok(new(
                     &new_item.content,
                     new_item.timestamp,
                     new_item.is_private,
@@ -23,10 +23,11 @@ use tower::ServiceExt;
 
 use super::models::{NewItemParams, NewItemResponse, RemoveAllParams, RemoveItemParams};
 use super::{
-    new_item, page, remove_all, remove_item, sync, NEW_ITEM_PATH, PAGE_PATH, REMOVE_ALL_PATH,
-    REMOVE_ITEM_PATH, SYNC_PATH,
+    bundle, new_item, page, remove_all, remove_item, sync, BUNDLE_PATH, NEW_ITEM_PATH, PAGE_PATH,
+    REMOVE_ALL_PATH, REMOVE_ITEM_PATH, SYNC_PATH,
 };
 use crate::auth::tests::gen_auth;
+use crate::client::database::models::bundle::BundleFileItem;
 use crate::client::database::models::message::{MessageItem, MessageItemType, Model};
 use crate::client::database::tests::{get_database, reset as reset_database};
 use crate::client::storage::tests::{
@@ -59,6 +60,7 @@ async fn fake_file(database: &Database, storage: &Storage, file_name: &str) -> R
     );
 
     database.create_table_message_if_not_exists().await?;
+    database.create_table_bundle_if_not_exists().await?;
     database.insert_message_item(item).await?;
 
     init_storage(storage).await?;
@@ -86,7 +88,10 @@ fn new_item_handler(
                             is_private: true,
                             file_name: Some("file name".to_string()),
                             is_complete: Some(true),
-                            type_field: MessageItemType::File
+                            type_field: MessageItemType::File,
+                            has_thumbnail: None,
+                            hash: None,
+                            is_missing: None,
                         }
                     );
                 }
@@ -407,3 +412,64 @@ async fn test_message_remove_all() {
 
     sleep_async(1).await;
 }
+
+#[tokio::test]
+async fn test_message_bundle() {
+    async fn inner(database: &Database) -> Result<(Response, Response)> {
+        let crypto = get_crypto();
+        let prefix = "test_message_bundle";
+
+        let item = MessageItem::new_bundle(prefix, get_current_timestamp(), true, prefix, true);
+
+        database.create_table_message_if_not_exists().await?;
+        database.create_table_bundle_if_not_exists().await?;
+        let id = database.insert_message_item(item).await?;
+        database
+            .insert_bundle_file(BundleFileItem {
+                message_id: id,
+                path: "a/a.txt".to_string(),
+                file_name: format!("{}/a/a.txt", prefix),
+            })
+            .await?;
+
+        let router = Router::new()
+            .route(BUNDLE_PATH, get(bundle))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()));
+
+        let authorization = gen_auth(&crypto);
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}?id={}", BUNDLE_PATH, id))
+            .header("Authorization", authorization)
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res_authorized = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}?id={}", BUNDLE_PATH, id))
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res_unauthorized = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok((res_authorized, res_unauthorized))
+    }
+
+    let database = get_database(DBType::Sqlite).await;
+    let result = inner(&database).await;
+    reset_database(database).await;
+
+    let (res_authorized, res_unauthorized) = result.unwrap();
+    assert_eq!(res_authorized.status(), StatusCode::OK);
+    assert_eq!(res_unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+    sleep_async(1).await;
+}
@@ -0,0 +1,102 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+#[cfg(test)]
+mod tests;
+
+use axum::debug_handler;
+use axum::extract::Extension;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+
+use super::api::models::Account;
+use super::socket::ConnectionNumber;
+use crate::client::database::models::message::MessageItemType;
+use crate::client::Database;
+use crate::crypto::Crypto;
+use crate::env::Env;
+use crate::error::Result;
+use crate::metrics::{Gauges, Metrics};
+use crate::quota::Quota;
+
+pub static METRICS_PATH: &str = "/metrics";
+
+// the version of the text format understood by prometheus
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[debug_handler]
+pub async fn metrics(
+    Extension(metrics): Extension<Arc<Metrics>>,
+    Extension(crypto): Extension<Arc<Crypto>>,
+    Extension(env): Extension<Arc<Env>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(quota): Extension<Arc<Quota>>,
+    Extension(connection_number): Extension<Arc<ConnectionNumber>>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    tracing::debug!("received metrics request");
+
+    if !is_authorized(&crypto, &env, &database, &headers).await? {
+        return Ok((
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer realm=\"transfery\"")],
+        )
+            .into_response());
+    }
+
+    let mut messages = Vec::new();
+
+    for type_field in [
+        MessageItemType::Text,
+        MessageItemType::File,
+        MessageItemType::Bundle,
+    ] {
+        let name = type_field.to_str().to_string();
+        let count = database.count_message_items_by_type(type_field).await?;
+
+        messages.push((name, count));
+    }
+
+    let gauges = Gauges {
+        multipart_uploads: quota.get_upload_count().await,
+        socket_connections: connection_number.get(),
+        messages,
+        active_tokens: database.count_token_items(true).await?,
+        expired_tokens: database.count_token_items(false).await?,
+        token_last_use_timestamp: database.query_token_last_use_timestamp().await?,
+    };
+
+    let output = metrics.render(&gauges).await;
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], output).into_response())
+}
+
+// scrapers send an api token as a bearer token, its last use isn't updated,
+// otherwise the scrapes would hide when the other tokens were used
+async fn is_authorized(
+    crypto: &Crypto,
+    env: &Env,
+    database: &Database,
+    headers: &HeaderMap,
+) -> Result<bool> {
+    let token = match headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        Some(token) => token.trim(),
+        None => return Ok(false),
+    };
+
+    let is_valid = match Account::from(token, crypto) {
+        Ok(account) => account.is_valid(env),
+        Err(_) => false,
+    };
+
+    Ok(is_valid && database.is_token_exist(token).await?)
+}
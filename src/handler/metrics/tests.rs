@@ -0,0 +1,161 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, Method, StatusCode};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use tower::ServiceExt;
+
+use super::{metrics, METRICS_PATH};
+
+use crate::client::database::models::message::MessageItem;
+use crate::client::database::models::token::TokenNewItem;
+use crate::client::database::tests::{get_database, reset as reset_database};
+use crate::client::storage::tests::get_storage;
+use crate::client::{Database, Storage};
+use crate::crypto::tests::get_crypto;
+use crate::env::tests::{get_env, DBType, STType};
+use crate::error::tests::ServerExt;
+use crate::error::Error;
+use crate::error::Result;
+use crate::handler::api::models::Account;
+use crate::handler::socket::ConnectionNumber;
+use crate::metrics::tests::get_metrics;
+use crate::quota::tests::get_quota;
+use crate::utils::tests::{sleep_async, ResponseExt};
+use crate::utils::{get_current_timestamp, into_layer};
+
+#[tokio::test]
+async fn test_metrics_metrics() {
+    async fn inner(
+        database: &Database,
+        storage: &Storage,
+    ) -> Result<(Vec<StatusCode>, Response, String)> {
+        database.create_table_message_if_not_exists().await?;
+        database.create_table_token_if_not_exists().await?;
+
+        let current_timestamp = get_current_timestamp();
+
+        for item in [
+            MessageItem::new_text("test text", current_timestamp, false),
+            MessageItem::new_text("test text", current_timestamp, false),
+            MessageItem::new_file("test.txt", current_timestamp, false, "test.txt", true),
+        ] {
+            database.insert_message_item(item).await?;
+        }
+
+        let crypto = get_crypto();
+        let env = get_env(DBType::Sqlite, STType::LocalStorage);
+
+        let account = Account {
+            username: env.username.clone(),
+            password: env.password.clone(),
+            expiration_timestamp: current_timestamp + 1000 * 60,
+        };
+
+        let token =
+            crypto.encrypt(&serde_json::to_string(&account).map_err(Error::serialize_error)?)?;
+
+        for (token, expiration_timestamp) in [
+            (token.as_str(), current_timestamp + 1000 * 60),
+            ("expired token", current_timestamp - 1000 * 60),
+        ] {
+            database
+                .insert_token(TokenNewItem {
+                    token: token.to_string(),
+                    name: "test name".to_string(),
+                    expiration_timestamp,
+                })
+                .await?;
+        }
+
+        let quota = get_quota();
+        quota.register_upload(storage, "test upload", None).await?;
+
+        let connection_number = ConnectionNumber::new();
+        connection_number.increase();
+
+        let router = Router::new()
+            .route(METRICS_PATH, get(metrics))
+            .layer(into_layer(get_metrics()))
+            .layer(into_layer(crypto))
+            .layer(into_layer(env))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(quota))
+            .layer(into_layer(connection_number));
+
+        // only api tokens are accepted
+        let mut statuses = Vec::new();
+
+        for auth in [None, Some("Bearer expired token")] {
+            let mut req = Request::builder().method(Method::GET).uri(METRICS_PATH);
+
+            if let Some(auth) = auth {
+                req = req.header(header::AUTHORIZATION, auth);
+            }
+
+            let req = req.body(Body::empty()).map_err(Error::req_build_error)?;
+            let res = router
+                .clone()
+                .oneshot(req)
+                .await
+                .map_err(Error::req_send_error)?;
+
+            statuses.push(res.status());
+        }
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(METRICS_PATH)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let mut res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        let content = std::mem::take(res.body_mut());
+        let content = Response::new(content).to_string().await?;
+
+        Ok((statuses, res, content))
+    }
+
+    let database = get_database(DBType::Sqlite).await;
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&database, &storage).await;
+    reset_database(database).await;
+
+    let (statuses, res, content) = result.unwrap();
+
+    assert_eq!(
+        statuses,
+        vec![StatusCode::UNAUTHORIZED, StatusCode::UNAUTHORIZED]
+    );
+
+    assert!(res.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain; version=0.0.4"));
+
+    for expected in [
+        "transfery_multipart_uploads 1",
+        "transfery_socket_connections 1",
+        "transfery_messages{type=\"text\"} 2",
+        "transfery_messages{type=\"file\"} 1",
+        "transfery_messages{type=\"bundle\"} 0",
+        "transfery_tokens{state=\"active\"} 1",
+        "transfery_tokens{state=\"expired\"} 1",
+        "transfery_token_last_use_timestamp_seconds",
+    ] {
+        assert!(content.contains(expected), "{}", content);
+    }
+
+    sleep_async(1).await;
+}
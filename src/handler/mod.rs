@@ -8,7 +8,10 @@
 pub mod admin;
 pub mod api;
 pub mod download;
+pub mod health;
 pub mod index;
 pub mod message;
+pub mod metrics;
 pub mod socket;
 pub mod upload;
+pub mod webdav;
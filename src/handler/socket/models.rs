@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use socketioxide::operators::RoomParam;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
+use std::sync::Arc;
 
 pub enum Room {
     Public,
@@ -32,11 +33,16 @@ impl RoomParam for Room {
     }
 }
 
-pub struct ConnectionNumber(pub AtomicUsize);
+// cloned into the metrics, so that both see the same number
+#[derive(Clone)]
+pub struct ConnectionNumber(pub Arc<AtomicUsize>);
 
 impl ConnectionNumber {
     pub fn new() -> Self {
-        Self(AtomicUsize::new(0))
+        Self(Arc::new(AtomicUsize::new(0)))
+    }
+    pub fn get(&self) -> usize {
+        self.0.load(SeqCst)
     }
     pub fn increase(&self) -> usize {
         self.0.fetch_add(1, SeqCst) + 1
@@ -10,19 +10,28 @@ mod models;
 mod tests;
 
 use models::{
-    CompleteUploadFormParams, FetchUploadIdJsonParams, FetchUploadIdResponse, UploadPartFormParams,
+    AbortUploadParams, CompleteBundleParams, CompleteUploadFormParams, FetchUploadIdJsonParams,
+    FetchUploadIdResponse, UploadPartFormParams,
 };
 
 use axum::debug_handler;
 use axum::extract::{Extension, Json};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use socketioxide::socket::Sid;
+use socketioxide::SocketIo;
 use std::sync::Arc;
 
+use super::socket::Room;
 use crate::auth::AuthChecker;
+use crate::client::database::models::bundle::BundleFileItem;
+use crate::client::database::models::message::{self, MessageItemType};
 use crate::client::{Database, Storage};
-use crate::error::Result;
-use crate::utils::rename;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::quota::{Quota, Usage};
+use crate::shutdown::Shutdown;
+use crate::utils::{get_current_timestamp, sanitize_path};
 
 pub static FETCH_UPLOAD_ID_PATH: &str = "/fetchUploadId";
 
@@ -30,24 +39,103 @@ pub static FETCH_UPLOAD_ID_PATH: &str = "/fetchUploadId";
 pub async fn fetch_upload_id(
     _: AuthChecker,
     Extension(storage): Extension<Arc<Storage>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(quota): Extension<Arc<Quota>>,
+    Extension(shutdown): Extension<Arc<Shutdown>>,
+    Extension(socketio): Extension<Arc<SocketIo>>,
     Json(params): Json<FetchUploadIdJsonParams>,
 ) -> Result<Json<FetchUploadIdResponse>> {
     tracing::info!("received fetch upload id request");
-    tracing::debug!("fetch upload id params: {:#?}", params);
+    tracing::debug!("fetch upload id params: {:?}", params);
+
+    let _upload = shutdown.begin_upload()?;
+
+    let FetchUploadIdJsonParams {
+        content,
+        path,
+        bundle,
+        hash,
+        size,
+        is_private,
+        sid,
+    } = params;
+
+    // files in a bundle are never shared, so that the bundle can be removed by prefix,
+    // the message is created here so that the object is referenced before anyone knows it
+    if let (None, Some(hash), Some(is_private)) = (&path, hash, is_private) {
+        if let Some(item) =
+            insert_file_by_hash(&storage, &database, &hash, &content, is_private).await?
+        {
+            tracing::info!("file already exists, upload skipped");
+            tracing::debug!("existing file message: {:?}", item);
+
+            emit_new_item(&socketio, sid, &item)?;
+
+            return Ok(Json(FetchUploadIdResponse {
+                upload_id: String::new(),
+                file_name: item.file_name.unwrap_or_default(),
+                bundle: None,
+                exists: true,
+                id: Some(item.id),
+            }));
+        }
+    }
 
-    let content = params.clone().content;
-    let timestamp = params.clone().timestamp;
+    // every part of an encrypted file is bound to the size of the whole file
+    if storage.is_encrypted() && size.is_none() {
+        return Err(Error::new(
+            InternalServerError,
+            "file size is required when encryption is enabled",
+        ));
+    }
+
+    let (file_name, bundle) = match path {
+        Some(path) => {
+            let path = sanitize_path(&path)
+                .ok_or_else(|| Error::new(InternalServerError, "invalid path in bundle"))?;
+
+            let bundle = match bundle {
+                // only accept a prefix that is already sanitized
+                Some(bundle) => sanitize_path(&bundle)
+                    .filter(|sanitized| *sanitized == bundle)
+                    .ok_or_else(|| Error::new(InternalServerError, "invalid bundle"))?,
+                None => storage.create_object_name(&content).await?,
+            };
+
+            let file_name = format!("{}/{}", bundle, path);
+
+            if storage.is_object_exists(&file_name).await? {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("file {} already exists in bundle", path),
+                ));
+            }
+
+            (file_name, Some(bundle))
+        }
+        None => (storage.create_object_name(&content).await?, None),
+    };
 
-    let file_name = rename(&content, timestamp);
     let upload_id = storage.create_multipart_upload_id(&file_name).await?;
 
+    if let Err(e) = quota.register_upload(&storage, &upload_id, size).await {
+        if let Err(e) = storage.abort_multipart_upload(&file_name, &upload_id).await {
+            tracing::warn!("failed to abort upload: {}", e);
+        }
+
+        return Err(e);
+    }
+
     let result = FetchUploadIdResponse {
         file_name,
         upload_id,
+        bundle,
+        exists: false,
+        id: None,
     };
 
     tracing::info!("upload id pushed");
-    tracing::debug!("fetch upload id response: {:#?}", result);
+    tracing::debug!("fetch upload id response: {:?}", result);
 
     Ok(Json(result))
 }
@@ -58,14 +146,29 @@ pub static UPLOAD_PART_PATH: &str = "/uploadPart";
 pub async fn upload_part(
     _: AuthChecker,
     Extension(storage): Extension<Arc<Storage>>,
+    Extension(quota): Extension<Arc<Quota>>,
+    Extension(shutdown): Extension<Arc<Shutdown>>,
     params: UploadPartFormParams,
 ) -> Result<String> {
+    let _upload = shutdown.continue_upload();
+
+    quota
+        .record_part(
+            &params.upload_id,
+            params.part_number,
+            params.file_part.len() as u64,
+        )
+        .await?;
+
+    let size = quota.get_upload_size(&params.upload_id).await;
+
     let etag = storage
         .multipart_upload(
             &params.file_name,
             &params.upload_id,
             &params.file_part,
             params.part_number,
+            size,
         )
         .await?
         .etag;
@@ -82,25 +185,236 @@ pub async fn complete_upload(
     _: AuthChecker,
     Extension(storage): Extension<Arc<Storage>>,
     Extension(database): Extension<Arc<Database>>,
+    Extension(quota): Extension<Arc<Quota>>,
+    Extension(shutdown): Extension<Arc<Shutdown>>,
     Json(params): Json<CompleteUploadFormParams>,
 ) -> Result<Response> {
     tracing::info!("received complete upload request");
-    tracing::debug!("complete upload params: {:#?}", params);
+    tracing::debug!("complete upload params: {:?}", params);
+
+    let _upload = shutdown.continue_upload();
 
     let CompleteUploadFormParams {
         id,
         file_name,
         upload_id,
         parts,
+        path,
     } = params;
 
+    // files in a bundle are named by the server, like in fetch_upload_id
+    let (file_name, path) = match path {
+        Some(path) => {
+            let bundle = query_bundle_prefix(&database, id).await?;
+
+            let path = sanitize_path(&path)
+                .ok_or_else(|| Error::new(InternalServerError, "invalid path in bundle"))?;
+
+            (format!("{}/{}", bundle, path), Some(path))
+        }
+        None => (file_name, None),
+    };
+
     storage
         .complete_multipart_upload(&file_name, &upload_id, &parts)
         .await?;
 
+    quota.finish_upload(&upload_id).await;
+
+    // the bundle is marked complete once all of its files are uploaded
+    if let Some(path) = path {
+        database
+            .insert_bundle_file(BundleFileItem {
+                message_id: id,
+                path,
+                file_name,
+            })
+            .await?;
+
+        tracing::info!("bundle file upload completed");
+
+        return Ok(StatusCode::OK.into_response());
+    }
+
     database.update_complete(id).await?;
 
     tracing::info!("upload completed");
 
+    process_uploaded_file(&storage, &database, id, &file_name).await?;
+
     Ok(StatusCode::OK.into_response())
 }
+
+// hashes the file and creates its thumbnail once the message is complete
+pub async fn process_uploaded_file(
+    storage: &Arc<Storage>,
+    database: &Arc<Database>,
+    id: i64,
+    file_name: &str,
+) -> Result<()> {
+    // a missing hash only disables deduplication for this file
+    match storage.hash_object(file_name).await {
+        Ok(hash) => {
+            database.update_hash(id, &hash).await?;
+            tracing::debug!("file hash: {}", hash);
+        }
+        Err(e) => tracing::warn!("failed to hash file: {}", e),
+    }
+
+    // decoding large images is slow, so the upload doesn't wait for the thumbnail
+    tokio::spawn(create_thumbnail(
+        storage.clone(),
+        database.clone(),
+        id,
+        file_name.to_string(),
+    ));
+
+    Ok(())
+}
+
+// a missing thumbnail shouldn't fail the upload
+async fn create_thumbnail(
+    storage: Arc<Storage>,
+    database: Arc<Database>,
+    id: i64,
+    file_name: String,
+) {
+    match storage.create_thumbnail(&file_name).await {
+        Ok(true) => match database.update_thumbnail(id).await {
+            Ok(_) => tracing::info!("thumbnail created"),
+            Err(e) => tracing::warn!("failed to record thumbnail: {}", e),
+        },
+        Ok(false) => {}
+        Err(e) => tracing::warn!("failed to create thumbnail: {}", e),
+    }
+}
+
+pub static ABORT_UPLOAD_PATH: &str = "/abortUpload";
+
+#[debug_handler]
+pub async fn abort_upload(
+    _: AuthChecker,
+    Extension(storage): Extension<Arc<Storage>>,
+    Extension(quota): Extension<Arc<Quota>>,
+    Json(params): Json<AbortUploadParams>,
+) -> Result<Response> {
+    tracing::info!("received abort upload request");
+    tracing::debug!("abort upload params: {:?}", params);
+
+    let AbortUploadParams {
+        file_name,
+        upload_id,
+    } = params;
+
+    storage
+        .abort_multipart_upload(&file_name, &upload_id)
+        .await?;
+
+    quota.finish_upload(&upload_id).await;
+
+    tracing::info!("upload aborted");
+
+    Ok(StatusCode::OK.into_response())
+}
+
+pub static COMPLETE_BUNDLE_PATH: &str = "/completeBundle";
+
+#[debug_handler]
+pub async fn complete_bundle(
+    _: AuthChecker,
+    Extension(database): Extension<Arc<Database>>,
+    Json(CompleteBundleParams { id }): Json<CompleteBundleParams>,
+) -> Result<Response> {
+    tracing::info!("received complete bundle request");
+    tracing::debug!("complete bundle id: {}", id);
+
+    query_bundle_prefix(&database, id).await?;
+
+    database.update_complete(id).await?;
+
+    tracing::info!("bundle upload completed");
+
+    Ok(StatusCode::OK.into_response())
+}
+
+pub static USAGE_PATH: &str = "/usage";
+
+#[debug_handler]
+pub async fn usage(
+    _: AuthChecker,
+    Extension(storage): Extension<Arc<Storage>>,
+    Extension(quota): Extension<Arc<Quota>>,
+) -> Result<Json<Usage>> {
+    tracing::info!("received usage request");
+
+    let result = quota.get_usage(&storage).await?;
+
+    tracing::debug!("usage response: {:?}", result);
+
+    Ok(Json(result))
+}
+
+async fn query_bundle_prefix(database: &Database, id: i64) -> Result<String> {
+    let item = database
+        .query_message_item(id)
+        .await?
+        .ok_or_else(|| Error::new(InternalServerError, "bundle not found"))?;
+
+    match (item.type_field, item.file_name) {
+        (MessageItemType::Bundle, Some(file_name)) => Ok(file_name),
+        _ => Err(Error::new(
+            InternalServerError,
+            format!("message {} is not a bundle", id),
+        )),
+    }
+}
+
+async fn insert_file_by_hash(
+    storage: &Storage,
+    database: &Database,
+    hash: &str,
+    content: &str,
+    is_private: bool,
+) -> Result<Option<message::Model>> {
+    let item = database
+        .insert_message_file_by_hash(
+            &hash.to_lowercase(),
+            content,
+            get_current_timestamp(),
+            is_private,
+        )
+        .await?;
+
+    let item = match item {
+        Some(item) => item,
+        None => return Ok(None),
+    };
+
+    match &item.file_name {
+        // the object may have been removed by hand, the file is uploaded again then
+        Some(file_name) if storage.is_object_exists(file_name).await? => Ok(Some(item)),
+        _ => {
+            database.remove_message_item(item.id).await?;
+
+            Ok(None)
+        }
+    }
+}
+
+fn emit_new_item(socketio: &SocketIo, sid: Option<Sid>, item: &message::Model) -> Result<()> {
+    let room = match item.is_private {
+        true => Room::Private,
+        false => Room::Public,
+    };
+
+    let operators = match sid {
+        Some(sid) => socketio.to(room).except(sid),
+        None => socketio.to(room),
+    };
+
+    operators
+        .emit("newItem", item)
+        .map_err(|e| Error::context(InternalServerError, e, "failed to emit event newItem"))?;
+
+    Ok(())
+}
@@ -8,6 +8,7 @@
 use axum::async_trait;
 use axum::extract::{FromRequest, Multipart, Request};
 use serde::{Deserialize, Serialize};
+use socketioxide::socket::Sid;
 
 use crate::client::storage::models::Part;
 use crate::error::Error;
@@ -17,7 +18,24 @@ use crate::error::Result;
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FetchUploadIdJsonParams {
     pub content: String,
-    pub timestamp: i64,
+    // relative path of a file in a bundle, content is then the folder name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    // prefix returned for the first file of the bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<String>,
+    // sha256 of the file, announced to skip uploading content that already exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    // size of the file in bytes, required when a quota or encryption is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    // the message of an existing file is created by the server with it
+    #[serde(rename = "isPrivate", skip_serializing_if = "Option::is_none")]
+    pub is_private: Option<bool>,
+    // the client creating the message, it isn't told about it again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Sid>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -26,6 +44,14 @@ pub struct FetchUploadIdResponse {
     pub upload_id: String,
     #[serde(rename = "fileName")]
     pub file_name: String,
+    // prefix shared by all files in the bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<String>,
+    // the file is already stored, no upload is needed
+    pub exists: bool,
+    // message created for an existing file, /newItem isn't needed then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -116,4 +142,19 @@ pub struct CompleteUploadFormParams {
     #[serde(rename = "uploadId")]
     pub upload_id: String,
     pub parts: Vec<Part>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AbortUploadParams {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "uploadId")]
+    pub upload_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CompleteBundleParams {
+    pub id: i64,
 }
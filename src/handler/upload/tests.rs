@@ -9,35 +9,53 @@ use axum::body::Body;
 use axum::extract::Request;
 use axum::http::{header, Method, StatusCode};
 use axum::response::Response;
-use axum::routing::post;
+use axum::routing::{get, post};
 use axum::Router;
+use socketioxide::extract::SocketRef;
+use socketioxide::SocketIo;
+use std::time::Duration;
 use tower::ServiceExt;
 
 use super::models::{
-    CompleteUploadFormParams, FetchUploadIdJsonParams, FetchUploadIdResponse, UploadPartFormParams,
+    AbortUploadParams, CompleteBundleParams, CompleteUploadFormParams, FetchUploadIdJsonParams,
+    FetchUploadIdResponse, UploadPartFormParams,
 };
 use super::{
-    complete_upload, fetch_upload_id, upload_part, COMPLETE_UPLOAD_PATH, FETCH_UPLOAD_ID_PATH,
-    UPLOAD_PART_PATH,
+    abort_upload, complete_bundle, complete_upload, fetch_upload_id, upload_part, usage,
+    ABORT_UPLOAD_PATH, COMPLETE_BUNDLE_PATH, COMPLETE_UPLOAD_PATH, FETCH_UPLOAD_ID_PATH,
+    UPLOAD_PART_PATH, USAGE_PATH,
 };
 
 use crate::auth::tests::gen_auth;
-use crate::client::database::models::message::MessageItem;
+use crate::client::database::models::bundle;
+use crate::client::database::models::message::{self, MessageItem};
 use crate::client::database::tests::{get_database, reset as reset_database};
 use crate::client::storage::models::Part;
 use crate::client::storage::tests::{get_storage, init, reset as reset_storage};
 use crate::client::{Database, Storage};
 use crate::crypto::tests::get_crypto;
 use crate::env::tests::{DBType, STType};
+use crate::env::QuotaEnv;
 use crate::error::tests::ServerExt;
 use crate::error::Error;
 use crate::error::Result;
+use crate::quota::tests::get_quota;
+use crate::quota::{Quota, Usage};
+use crate::shutdown::tests::get_shutdown;
 use crate::utils::tests::sleep_async;
 use crate::utils::tests::ResponseExt;
 use crate::utils::{get_current_timestamp, into_layer};
 
 const BOUNDARY: &str = "------------------------boundary";
 
+fn get_socketio() -> SocketIo {
+    let (_, socketio) = SocketIo::new_layer();
+
+    socketio.ns("/", |_: SocketRef| {});
+
+    socketio
+}
+
 impl UploadPartFormParams {
     fn gen_payload(&self) -> Vec<u8> {
         let mut body = format!(
@@ -78,7 +96,7 @@ impl UploadPartFormParams {
 
 #[tokio::test]
 async fn test_upload_fetch_upload_id() {
-    async fn inner(storage: &Storage) -> Result<Response> {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Response> {
         let content = "test_upload_fetch_upload_id.txt";
         init(&storage).await?;
 
@@ -87,12 +105,21 @@ async fn test_upload_fetch_upload_id() {
 
         let router = Router::new()
             .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
             .layer(into_layer(storage.clone()))
-            .layer(into_layer(crypto.clone()));
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(get_shutdown()));
 
         let data = FetchUploadIdJsonParams {
             content: content.to_string(),
-            timestamp: get_current_timestamp(),
+            path: None,
+            bundle: None,
+            hash: None,
+            size: None,
+            is_private: None,
+            sid: None,
         };
 
         let body = serde_json::to_string(&data).map_err(|e| Error::serialize_error(e))?;
@@ -114,9 +141,11 @@ async fn test_upload_fetch_upload_id() {
     }
 
     let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
 
-    let result = inner(&storage).await;
+    let result = inner(&storage, &database).await;
     reset_storage(&storage).await;
+    reset_database(database).await;
     assert_eq!(result.unwrap().status(), StatusCode::OK);
 
     sleep_async(1).await;
@@ -124,7 +153,7 @@ async fn test_upload_fetch_upload_id() {
 
 #[tokio::test]
 async fn test_upload_upload_part() {
-    async fn inner(storage: &Storage) -> Result<Response> {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Response> {
         let content = "test_upload_upload_part.txt";
         init(&storage).await?;
 
@@ -133,13 +162,22 @@ async fn test_upload_upload_part() {
 
         let router = Router::new()
             .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
             .route(UPLOAD_PART_PATH, post(upload_part))
             .layer(into_layer(storage.clone()))
-            .layer(into_layer(crypto.clone()));
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(get_shutdown()));
 
         let data = FetchUploadIdJsonParams {
             content: content.to_string(),
-            timestamp: get_current_timestamp(),
+            path: None,
+            bundle: None,
+            hash: None,
+            size: None,
+            is_private: None,
+            sid: None,
         };
 
         let body = serde_json::to_string(&data).map_err(|e| Error::serialize_error(e))?;
@@ -165,6 +203,7 @@ async fn test_upload_upload_part() {
         let FetchUploadIdResponse {
             upload_id,
             file_name,
+            ..
         } = res_data;
 
         let data = UploadPartFormParams {
@@ -196,9 +235,11 @@ async fn test_upload_upload_part() {
     }
 
     let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
 
-    let result = inner(&storage).await;
+    let result = inner(&storage, &database).await;
     reset_storage(&storage).await;
+    reset_database(database).await;
     assert_eq!(result.unwrap().status(), StatusCode::OK);
 
     sleep_async(1).await;
@@ -216,15 +257,23 @@ async fn test_upload_complete_upload() {
 
         let router = Router::new()
             .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
             .route(UPLOAD_PART_PATH, post(upload_part))
             .route(COMPLETE_UPLOAD_PATH, post(complete_upload))
             .layer(into_layer(storage.clone()))
             .layer(into_layer(database.clone()))
-            .layer(into_layer(crypto.clone()));
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(get_shutdown()));
 
         let data = FetchUploadIdJsonParams {
             content: content.to_string(),
-            timestamp: get_current_timestamp(),
+            path: None,
+            bundle: None,
+            hash: None,
+            size: None,
+            is_private: None,
+            sid: None,
         };
 
         let body = serde_json::to_string(&data).map_err(|e| Error::serialize_error(e))?;
@@ -250,6 +299,7 @@ async fn test_upload_complete_upload() {
         let FetchUploadIdResponse {
             upload_id,
             file_name,
+            ..
         } = res_data;
 
         let item =
@@ -290,6 +340,7 @@ async fn test_upload_complete_upload() {
             file_name: file_name.clone(),
             upload_id: upload_id.clone(),
             parts: vec![Part { number: 1, etag }],
+            path: None,
         };
 
         let body = serde_json::to_string(&data).map_err(|e| Error::serialize_error(e))?;
@@ -321,3 +372,578 @@ async fn test_upload_complete_upload() {
 
     sleep_async(1).await;
 }
+
+#[tokio::test]
+async fn test_upload_abort_upload() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<(Response, Response)> {
+        let content = "test_upload_abort_upload.txt";
+
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let router = Router::new()
+            .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
+            .route(UPLOAD_PART_PATH, post(upload_part))
+            .route(ABORT_UPLOAD_PATH, post(abort_upload))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(get_shutdown()));
+
+        let data = FetchUploadIdJsonParams {
+            content: content.to_string(),
+            path: None,
+            bundle: None,
+            hash: None,
+            size: None,
+            is_private: None,
+            sid: None,
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(FETCH_UPLOAD_ID_PATH)
+            .header("Authorization", auth.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        let res_content = res.to_string().await?;
+        let res_data: FetchUploadIdResponse =
+            serde_json::from_str(&res_content).map_err(Error::deserialize_error)?;
+
+        let FetchUploadIdResponse {
+            upload_id,
+            file_name,
+            ..
+        } = res_data;
+
+        let data = AbortUploadParams {
+            file_name: file_name.clone(),
+            upload_id: upload_id.clone(),
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(ABORT_UPLOAD_PATH)
+            .header("Authorization", auth.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let abort_res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        // parts of an aborted upload are rejected
+        let data = UploadPartFormParams {
+            file_name,
+            upload_id,
+            part_number: 1,
+            file_part: content.as_bytes().to_vec(),
+        };
+
+        let payload = data.gen_payload();
+
+        let (upload_header_key, upload_header_value) = UploadPartFormParams::gen_header();
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(UPLOAD_PART_PATH)
+            .header("Authorization", auth.clone())
+            .header(upload_header_key, upload_header_value)
+            .body(Body::from(payload))
+            .map_err(Error::req_build_error)?;
+
+        let upload_res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        Ok((abort_res, upload_res))
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let (abort_res, upload_res) = result.unwrap();
+
+    assert_eq!(abort_res.status(), StatusCode::OK);
+    assert_ne!(upload_res.status(), StatusCode::OK);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_upload_complete_bundle() {
+    async fn inner(
+        storage: &Storage,
+        database: &Database,
+    ) -> Result<(
+        Response,
+        Vec<bundle::Model>,
+        Option<message::Model>,
+        String,
+        bool,
+    )> {
+        let content = "test_upload_complete_bundle";
+        let timestamp = get_current_timestamp();
+
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let router = Router::new()
+            .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
+            .route(UPLOAD_PART_PATH, post(upload_part))
+            .route(COMPLETE_UPLOAD_PATH, post(complete_upload))
+            .route(COMPLETE_BUNDLE_PATH, post(complete_bundle))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(get_shutdown()));
+
+        let data = FetchUploadIdJsonParams {
+            content: content.to_string(),
+            path: Some("dir/../a.txt".to_string()),
+            bundle: None,
+            hash: None,
+            size: None,
+            is_private: None,
+            sid: None,
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(FETCH_UPLOAD_ID_PATH)
+            .header("Authorization", auth.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        let res_content = res.to_string().await?;
+        let res_data: FetchUploadIdResponse =
+            serde_json::from_str(&res_content).map_err(Error::deserialize_error)?;
+
+        let FetchUploadIdResponse {
+            upload_id,
+            file_name,
+            bundle,
+            ..
+        } = res_data;
+
+        let bundle = bundle.unwrap();
+        assert_eq!(file_name, format!("{}/dir/a.txt", bundle));
+
+        let item = MessageItem::new_bundle(content, timestamp, false, &bundle, false);
+
+        database.create_table_message_if_not_exists().await?;
+        database.create_table_bundle_if_not_exists().await?;
+        let id = database.insert_message_item(item).await?;
+
+        let data = UploadPartFormParams {
+            file_name: file_name.clone(),
+            upload_id: upload_id.clone(),
+            part_number: 1,
+            file_part: content.as_bytes().to_vec(),
+        };
+
+        let payload = data.gen_payload();
+
+        let (upload_header_key, upload_header_value) = UploadPartFormParams::gen_header();
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(UPLOAD_PART_PATH)
+            .header("Authorization", auth.clone())
+            .header(upload_header_key, upload_header_value)
+            .body(Body::from(payload))
+            .map_err(Error::req_build_error)?;
+
+        let res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        let etag = res.to_string().await?;
+
+        // the name of a file in a bundle is rebuilt by the server
+        let data = CompleteUploadFormParams {
+            id,
+            file_name: "../test_upload_complete_bundle.txt".to_string(),
+            upload_id: upload_id.clone(),
+            parts: vec![Part { number: 1, etag }],
+            path: Some("dir/a.txt".to_string()),
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(COMPLETE_UPLOAD_PATH)
+            .header("Authorization", auth.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        let body =
+            serde_json::to_string(&CompleteBundleParams { id }).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(COMPLETE_BUNDLE_PATH)
+            .header("Authorization", auth.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        let files = database.query_bundle_files(id).await?;
+        let item = database.query_message_item(id).await?;
+
+        let is_exists = storage.is_object_exists(&file_name).await?;
+
+        Ok((res, files, item, file_name, is_exists))
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let (res, files, item, file_name, is_exists) = result.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files.first().unwrap().path, "dir/a.txt");
+    assert_eq!(files.first().unwrap().file_name, file_name);
+    assert!(is_exists);
+    assert_eq!(item.unwrap().is_complete, Some(true));
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_upload_fetch_upload_id_exists() {
+    async fn inner(
+        storage: &Storage,
+        database: &Database,
+        is_private: bool,
+    ) -> Result<FetchUploadIdResponse> {
+        let content = "test_upload_fetch_upload_id_exists.txt";
+        let hash = "7509e5bda0c762d2bac7f90d758b5b2263fa01ccbc542ab5e3df163be08e6ca9";
+
+        init(storage).await?;
+
+        let file_name = storage.create_object_name(content).await?;
+        storage.put_object(&file_name, b"hello world!").await?;
+
+        let item = MessageItem::new_file(
+            content,
+            get_current_timestamp(),
+            is_private,
+            &file_name,
+            true,
+        );
+
+        database.create_table_message_if_not_exists().await?;
+        let id = database.insert_message_item(item).await?;
+        database.update_hash(id, hash).await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let router = Router::new()
+            .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(get_shutdown()));
+
+        // a public message must not reference a private file
+        let data = FetchUploadIdJsonParams {
+            content: content.to_string(),
+            path: None,
+            bundle: None,
+            hash: Some(hash.to_uppercase()),
+            size: None,
+            is_private: Some(false),
+            sid: None,
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(FETCH_UPLOAD_ID_PATH)
+            .header("Authorization", auth)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        let res_content = res.to_string().await?;
+        let res_data: FetchUploadIdResponse =
+            serde_json::from_str(&res_content).map_err(Error::deserialize_error)?;
+
+        if res_data.exists {
+            assert_eq!(res_data.file_name, file_name);
+
+            // the message is created along with the lookup
+            let new_id = res_data.id.unwrap();
+            let item = database.query_message_item(new_id).await?.unwrap();
+            assert_ne!(new_id, id);
+            assert_eq!(item.file_name, Some(file_name.clone()));
+            assert_eq!(item.is_complete, Some(true));
+            assert!(!item.is_private);
+        } else {
+            assert_ne!(res_data.file_name, file_name);
+            assert!(res_data.id.is_none());
+        }
+
+        Ok(res_data)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database, false).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+    assert!(result.unwrap().exists);
+
+    sleep_async(1).await;
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database, true).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+    assert!(!result.unwrap().exists);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_upload_fetch_upload_id_quota_exceeded() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Response> {
+        let content = "test_upload_fetch_upload_id_quota_exceeded.txt";
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let quota = Quota::new(&QuotaEnv {
+            max_file_size: Some(5),
+            ..Default::default()
+        });
+
+        let router = Router::new()
+            .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(quota))
+            .layer(into_layer(get_shutdown()));
+
+        let data = FetchUploadIdJsonParams {
+            content: content.to_string(),
+            path: None,
+            bundle: None,
+            hash: None,
+            size: Some(12),
+            is_private: None,
+            sid: None,
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(FETCH_UPLOAD_ID_PATH)
+            .header("Authorization", auth)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok(res)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+    assert_eq!(result.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_upload_fetch_upload_id_shutting_down() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Response> {
+        let content = "test_upload_fetch_upload_id_shutting_down.txt";
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let shutdown = get_shutdown();
+
+        // nothing is uploading, so draining returns at once
+        shutdown.drain(Duration::from_secs(1)).await;
+
+        let router = Router::new()
+            .route(FETCH_UPLOAD_ID_PATH, post(fetch_upload_id))
+            .layer(into_layer(get_socketio()))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(database.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(get_quota()))
+            .layer(into_layer(shutdown));
+
+        let data = FetchUploadIdJsonParams {
+            content: content.to_string(),
+            path: None,
+            bundle: None,
+            hash: None,
+            size: None,
+            is_private: None,
+            sid: None,
+        };
+
+        let body = serde_json::to_string(&data).map_err(Error::serialize_error)?;
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(FETCH_UPLOAD_ID_PATH)
+            .header("Authorization", auth)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        Ok(res)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+    assert_eq!(result.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_upload_usage() {
+    async fn inner(storage: &Storage) -> Result<Usage> {
+        init(storage).await?;
+
+        storage
+            .put_object("test_usage.txt", b"hello world!")
+            .await?;
+
+        let crypto = get_crypto();
+        let auth = gen_auth(&crypto);
+
+        let quota = Quota::new(&QuotaEnv {
+            max_storage_size: Some(1024),
+            ..Default::default()
+        });
+
+        let router = Router::new()
+            .route(USAGE_PATH, get(usage))
+            .layer(into_layer(storage.clone()))
+            .layer(into_layer(crypto.clone()))
+            .layer(into_layer(quota));
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(USAGE_PATH)
+            .header("Authorization", auth)
+            .body(Body::empty())
+            .map_err(Error::req_build_error)?;
+
+        let res = router.oneshot(req).await.map_err(Error::req_send_error)?;
+
+        let res_content = res.to_string().await?;
+        let res_data: Usage =
+            serde_json::from_str(&res_content).map_err(Error::deserialize_error)?;
+
+        Ok(res_data)
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+
+    let result = inner(&storage).await;
+    reset_storage(&storage).await;
+    assert_eq!(
+        result.unwrap(),
+        Usage {
+            storage_size: 12,
+            max_storage_size: Some(1024),
+            daily_upload_size: 0,
+            max_daily_upload_size: None,
+            max_file_size: None,
+        }
+    );
+
+    sleep_async(1).await;
+}
@@ -0,0 +1,947 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+mod models;
+#[cfg(test)]
+mod tests;
+
+use models::{to_multistatus, Entry, Resource, Target};
+
+use axum::body::Body;
+use axum::debug_handler;
+use axum::extract::{Extension, Path};
+use axum::http::{header, HeaderMap, HeaderName, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::engine::general_purpose::STANDARD as base64;
+use base64::Engine;
+use http_body_util::BodyExt;
+use socketioxide::SocketIo;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use super::api::models::Account;
+use super::message::remove_message;
+use super::socket::Room;
+use super::upload::process_uploaded_file;
+use crate::client::database::models::bundle::{self, BundleFileItem};
+use crate::client::database::models::message::{self, MessageItem, MessageItemType};
+use crate::client::storage::models::Part;
+use crate::client::{Database, Storage};
+use crate::crypto::Crypto;
+use crate::env::Env;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::quota::Quota;
+use crate::shutdown::Shutdown;
+use crate::utils::{get_current_timestamp, sanitize_path, uri_encode};
+
+pub static WEBDAV_PATH: &str = "/webdav";
+
+// bodies are uploaded in parts of this size, minio and s3 need at least 5 MB
+const PART_SIZE: usize = 5 * 1024 * 1024; // 5 MB
+
+const ALLOWED_METHODS: &str = "OPTIONS, PROPFIND, GET, HEAD, PUT, DELETE, MKCOL";
+
+// clients send the token with every request, the last use is only written this often
+const TOKEN_USE_INTERVAL: i64 = 1000 * 60; // 1 minute
+
+#[debug_handler]
+#[allow(clippy::too_many_arguments)]
+pub async fn webdav(
+    Extension(crypto): Extension<Arc<Crypto>>,
+    Extension(env): Extension<Arc<Env>>,
+    Extension(database): Extension<Arc<Database>>,
+    Extension(storage): Extension<Arc<Storage>>,
+    Extension(quota): Extension<Arc<Quota>>,
+    Extension(socketio): Extension<Arc<SocketIo>>,
+    Extension(shutdown): Extension<Arc<Shutdown>>,
+    method: Method,
+    path: Option<Path<String>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response> {
+    let path = path.map(|Path(path)| path).unwrap_or_default();
+
+    tracing::info!("received webdav {} request", method);
+    tracing::debug!("webdav path: {}", path);
+
+    // clients ask for the capabilities before sending credentials
+    if method == Method::OPTIONS {
+        return Ok(options_response());
+    }
+
+    if !is_authorized(&crypto, &env, &database, &headers).await? {
+        return Ok(unauthorized_response());
+    }
+
+    let path = path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .map(|component| component.to_string())
+        .collect::<Vec<String>>();
+
+    let target = resolve(&database, path).await?;
+
+    tracing::debug!("webdav target: {:?}", target);
+
+    match method.as_str() {
+        "PROPFIND" => propfind(&database, &storage, &headers, target, &env.base_path).await,
+        "GET" => get(&storage, target).await,
+        "HEAD" => Ok(head(target)),
+        "PUT" => {
+            let _upload = shutdown.begin_upload()?;
+
+            put(
+                &database, &storage, &quota, &socketio, &headers, target, body,
+            )
+            .await
+        }
+        "DELETE" => delete(&database, &storage, &socketio, target).await,
+        "MKCOL" => mkcol(&database, &storage, &socketio, target).await,
+        _ => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+    }
+}
+
+fn options_response() -> Response {
+    (
+        StatusCode::OK,
+        [
+            (HeaderName::from_static("dav"), "1"),
+            (header::ALLOW, ALLOWED_METHODS),
+        ],
+    )
+        .into_response()
+}
+
+fn unauthorized_response() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Basic realm=\"transfery\"")],
+    )
+        .into_response()
+}
+
+// any user name is accepted, the password is an api token
+async fn is_authorized(
+    crypto: &Crypto,
+    env: &Env,
+    database: &Database,
+    headers: &HeaderMap,
+) -> Result<bool> {
+    let token = match get_basic_password(headers) {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+
+    let is_valid = match Account::from(&token, crypto) {
+        Ok(account) => account.is_valid(env),
+        Err(_) => false,
+    };
+
+    if !is_valid {
+        return Ok(false);
+    }
+
+    // revoked tokens are removed from the table
+    let token_item = match database.query_token_item(&token).await? {
+        Some(token_item) => token_item,
+        None => return Ok(false),
+    };
+
+    let current_timestamp = get_current_timestamp();
+
+    if current_timestamp - token_item.last_use_timestamp > TOKEN_USE_INTERVAL {
+        database.update_token(&token, current_timestamp).await?;
+    }
+
+    Ok(true)
+}
+
+fn get_basic_password(headers: &HeaderMap) -> Option<String> {
+    let credentials = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+
+    let credentials = String::from_utf8(base64.decode(credentials.trim()).ok()?).ok()?;
+
+    credentials
+        .split_once(':')
+        .map(|(_, password)| password.to_string())
+}
+
+// files and bundles are listed by the names of their objects, so that a single one can be
+// looked up by name, later ones get their id when a name is taken
+async fn get_entries(database: &Database) -> Result<Vec<Entry>> {
+    let items = database.query_message_files_complete().await?;
+
+    let mut names = HashSet::new();
+
+    let entries = items
+        .into_iter()
+        .filter_map(|item| {
+            let name = get_object_name(item.file_name.as_deref()?).to_string();
+
+            let name = match names.insert(name.clone()) {
+                true => name,
+                false => append_id(&name, item.id),
+            };
+
+            Some(Entry { name, item })
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+// deduplicated files share the object, and with it the name
+fn get_object_name(file_name: &str) -> &str {
+    file_name.rsplit('/').next().unwrap_or(file_name)
+}
+
+fn append_id(name: &str, id: i64) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{} ({}).{}", stem, id, extension),
+        _ => format!("{} ({})", name, id),
+    }
+}
+
+// the id given by append_id, it's checked against the message it points to
+fn parse_appended_id(name: &str) -> Option<i64> {
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) if stem.ends_with(')') => stem,
+        _ => name,
+    };
+
+    let (_, id) = stem.strip_suffix(')')?.rsplit_once(" (")?;
+
+    id.parse::<i64>().ok()
+}
+
+async fn find_entry(database: &Database, name: &str) -> Result<Option<Entry>> {
+    if let Some(item) = database.query_message_file_by_object_name(name).await? {
+        return Ok(Some(Entry {
+            name: name.to_string(),
+            item,
+        }));
+    }
+
+    let id = match parse_appended_id(name) {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    let item = match database.query_message_item(id).await? {
+        Some(item)
+            if matches!(
+                item.type_field,
+                MessageItemType::File | MessageItemType::Bundle
+            ) && item.is_complete == Some(true) =>
+        {
+            item
+        }
+        _ => return Ok(None),
+    };
+
+    let object_name = match item.file_name.as_deref() {
+        Some(file_name) => get_object_name(file_name).to_string(),
+        None => return Ok(None),
+    };
+
+    if append_id(&object_name, id) != name {
+        return Ok(None);
+    }
+
+    // the id is only appended when an older message holds the name itself
+    match database
+        .query_message_file_by_object_name(&object_name)
+        .await?
+    {
+        Some(first) if first.id != id => Ok(Some(Entry {
+            name: name.to_string(),
+            item,
+        })),
+        _ => Ok(None),
+    }
+}
+
+async fn resolve(database: &Database, path: Vec<String>) -> Result<Target> {
+    let (name, rest) = match path.split_first() {
+        Some(first) => first,
+        None => return Ok(Target::Root),
+    };
+
+    let entry = find_entry(database, name).await?;
+
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return Ok(Target::Missing { parent: None, path }),
+    };
+
+    if rest.is_empty() {
+        return Ok(Target::Entry(entry));
+    }
+
+    // only bundles have children
+    if entry.item.type_field != MessageItemType::Bundle {
+        return Ok(Target::Missing { parent: None, path });
+    }
+
+    let sub_path = rest.join("/");
+    let files = database.query_bundle_files(entry.item.id).await?;
+
+    if let Some(file) = files.iter().find(|file| file.path == sub_path) {
+        return Ok(Target::BundleFile {
+            bundle: entry,
+            file: file.clone(),
+        });
+    }
+
+    // directories only exist through the files in them
+    let dir_prefix = format!("{}/", sub_path);
+
+    if files.iter().any(|file| file.path.starts_with(&dir_prefix)) {
+        return Ok(Target::BundleDir {
+            bundle: entry,
+            path: sub_path,
+        });
+    }
+
+    Ok(Target::Missing {
+        parent: Some(entry),
+        path,
+    })
+}
+
+fn get_href(path: &[&str], is_collection: bool) -> String {
+    let mut href = WEBDAV_PATH.to_string();
+
+    for component in path {
+        href.push('/');
+        href.push_str(&uri_encode(component, true));
+    }
+
+    if is_collection {
+        href.push('/');
+    }
+
+    href
+}
+
+// stored sizes only match the contents when the storage isn't encrypted
+async fn get_sizes(storage: &Storage, objects: &[&str]) -> Result<HashMap<String, u64>> {
+    let mut sizes = HashMap::new();
+
+    if storage.is_encrypted() {
+        return Ok(sizes);
+    }
+
+    for &object in objects {
+        if let Some(size) = storage.get_object_size(object).await? {
+            sizes.insert(object.to_string(), size);
+        }
+    }
+
+    Ok(sizes)
+}
+
+// the root lists most objects, so they're listed at once instead of one by one
+async fn list_sizes(storage: &Storage) -> Result<HashMap<String, u64>> {
+    if storage.is_encrypted() {
+        return Ok(HashMap::new());
+    }
+
+    let sizes = storage.list_object_sizes().await?;
+
+    Ok(sizes.into_iter().collect())
+}
+
+fn get_entry_resource(entry: &Entry, sizes: &HashMap<String, u64>) -> Resource {
+    let is_collection = entry.item.type_field == MessageItemType::Bundle;
+
+    let size = match is_collection {
+        true => None,
+        false => entry
+            .item
+            .file_name
+            .as_ref()
+            .and_then(|file_name| sizes.get(file_name).copied()),
+    };
+
+    Resource {
+        href: get_href(&[&entry.name], is_collection),
+        name: entry.name.clone(),
+        is_collection,
+        size,
+        timestamp: entry.item.timestamp,
+    }
+}
+
+fn get_bundle_resource(
+    bundle: &Entry,
+    path: &str,
+    file: Option<&bundle::Model>,
+    sizes: &HashMap<String, u64>,
+) -> Resource {
+    let mut components = vec![bundle.name.as_str()];
+    components.extend(path.split('/'));
+
+    Resource {
+        href: get_href(&components, file.is_none()),
+        name: components.last().unwrap_or(&"").to_string(),
+        is_collection: file.is_none(),
+        size: file.and_then(|file| sizes.get(&file.file_name).copied()),
+        timestamp: bundle.item.timestamp,
+    }
+}
+
+async fn list_bundle_dir(
+    database: &Database,
+    storage: &Storage,
+    bundle: &Entry,
+    dir: &str,
+) -> Result<Vec<Resource>> {
+    let files = database.query_bundle_files(bundle.item.id).await?;
+
+    // a child without a file is a directory
+    let mut children = BTreeMap::new();
+
+    for file in &files {
+        let rest = match dir.is_empty() {
+            true => Some(file.path.as_str()),
+            false => file
+                .path
+                .strip_prefix(dir)
+                .and_then(|rest| rest.strip_prefix('/')),
+        };
+
+        let rest = match rest {
+            Some(rest) => rest,
+            None => continue,
+        };
+
+        match rest.split_once('/') {
+            Some((child, _)) => {
+                children.entry(child.to_string()).or_insert(None);
+            }
+            None => {
+                children.insert(rest.to_string(), Some(file));
+            }
+        }
+    }
+
+    let objects = children
+        .values()
+        .flatten()
+        .map(|file| file.file_name.as_str())
+        .collect::<Vec<&str>>();
+
+    let sizes = get_sizes(storage, &objects).await?;
+
+    let resources = children
+        .into_iter()
+        .map(|(child, file)| {
+            let path = match dir.is_empty() {
+                true => child,
+                false => format!("{}/{}", dir, child),
+            };
+
+            get_bundle_resource(bundle, &path, file, &sizes)
+        })
+        .collect();
+
+    Ok(resources)
+}
+
+// infinite depth isn't supported, it's served as depth 1
+async fn propfind(
+    database: &Database,
+    storage: &Storage,
+    headers: &HeaderMap,
+    target: Target,
+    base_path: &str,
+) -> Result<Response> {
+    if let Target::Missing { .. } = target {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let is_deep = headers
+        .get("Depth")
+        .map(|depth| depth.as_bytes() != b"0")
+        .unwrap_or(true);
+
+    let mut resources = Vec::new();
+
+    match target {
+        Target::Root => {
+            resources.push(Resource {
+                href: get_href(&[], true),
+                name: String::new(),
+                is_collection: true,
+                size: None,
+                timestamp: get_current_timestamp(),
+            });
+
+            if is_deep {
+                let sizes = list_sizes(storage).await?;
+
+                for entry in get_entries(database).await? {
+                    resources.push(get_entry_resource(&entry, &sizes));
+                }
+            }
+        }
+        Target::Entry(entry) => {
+            let objects = match entry.item.type_field {
+                MessageItemType::File => entry.item.file_name.as_deref().into_iter().collect(),
+                _ => Vec::new(),
+            };
+
+            let sizes = get_sizes(storage, &objects).await?;
+
+            resources.push(get_entry_resource(&entry, &sizes));
+
+            if is_deep && entry.item.type_field == MessageItemType::Bundle {
+                resources.extend(list_bundle_dir(database, storage, &entry, "").await?);
+            }
+        }
+        Target::BundleFile { bundle, file } => {
+            let sizes = get_sizes(storage, &[file.file_name.as_str()]).await?;
+
+            resources.push(get_bundle_resource(
+                &bundle,
+                &file.path,
+                Some(&file),
+                &sizes,
+            ));
+        }
+        Target::BundleDir { bundle, path } => {
+            resources.push(get_bundle_resource(&bundle, &path, None, &HashMap::new()));
+
+            if is_deep {
+                resources.extend(list_bundle_dir(database, storage, &bundle, &path).await?);
+            }
+        }
+        Target::Missing { .. } => {}
+    }
+
+    // hrefs are absolute, so clients need the base path in them
+    for resource in &mut resources {
+        resource.href = format!("{}{}", base_path, resource.href);
+    }
+
+    tracing::info!("webdav properties pushed");
+    tracing::debug!("webdav resources: {:?}", resources);
+
+    Response::builder()
+        .status(StatusCode::MULTI_STATUS)
+        .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(Body::from(to_multistatus(&resources)))
+        .map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                "failed to build response for propfind",
+            )
+        })
+}
+
+fn get_target_object(target: &Target) -> Option<&str> {
+    match target {
+        Target::Entry(entry) if entry.item.type_field == MessageItemType::File => {
+            entry.item.file_name.as_deref()
+        }
+        Target::BundleFile { file, .. } => Some(&file.file_name),
+        _ => None,
+    }
+}
+
+// objects are sent through the server, clients don't follow redirects to the storage
+async fn get(storage: &Storage, target: Target) -> Result<Response> {
+    if let Target::Missing { .. } = target {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    match get_target_object(&target) {
+        Some(object) => {
+            tracing::info!("webdav file pushed");
+            storage.get_proxied_response(object).await
+        }
+        None => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+    }
+}
+
+fn head(target: Target) -> Response {
+    if let Target::Missing { .. } = target {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match get_target_object(&target) {
+        Some(object) => {
+            let content_type = mime_guess::from_path(object).first_or_octet_stream();
+
+            (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, content_type.to_string())],
+            )
+                .into_response()
+        }
+        None => StatusCode::OK.into_response(),
+    }
+}
+
+async fn put(
+    database: &Arc<Database>,
+    storage: &Arc<Storage>,
+    quota: &Quota,
+    socketio: &SocketIo,
+    headers: &HeaderMap,
+    target: Target,
+    body: Body,
+) -> Result<Response> {
+    // the quota and encryption need the size in advance, chunked bodies only pass without them
+    let size = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|size| size.to_str().ok())
+        .and_then(|size| size.parse::<u64>().ok());
+
+    if size.is_none() && (quota.is_limited() || storage.is_encrypted()) {
+        return Ok(StatusCode::LENGTH_REQUIRED.into_response());
+    }
+
+    match target {
+        Target::Missing { parent: None, path } if path.len() == 1 => {
+            put_file(
+                database, storage, quota, socketio, &path[0], size, body, None,
+            )
+            .await?;
+
+            Ok(StatusCode::CREATED.into_response())
+        }
+        // a file of the same name is replaced by a new message
+        Target::Entry(entry) if entry.item.type_field == MessageItemType::File => {
+            let name = entry.name.clone();
+
+            put_file(
+                database,
+                storage,
+                quota,
+                socketio,
+                &name,
+                size,
+                body,
+                Some(entry),
+            )
+            .await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Target::Missing {
+            parent: Some(bundle),
+            path,
+        } => {
+            put_bundle_file(
+                database,
+                storage,
+                quota,
+                &bundle,
+                &path[1..].join("/"),
+                size,
+                body,
+            )
+            .await
+        }
+        Target::BundleFile { bundle, file } => {
+            put_bundle_file(database, storage, quota, &bundle, &file.path, size, body).await
+        }
+        Target::Missing { .. } => Ok(StatusCode::CONFLICT.into_response()),
+        _ => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn put_file(
+    database: &Arc<Database>,
+    storage: &Arc<Storage>,
+    quota: &Quota,
+    socketio: &SocketIo,
+    name: &str,
+    size: Option<u64>,
+    body: Body,
+    replaced: Option<Entry>,
+) -> Result<()> {
+    let file_name = storage.create_object_name(name).await?;
+
+    // the message is inserted before the object like other uploads, so that fsck never
+    // sees the object as an orphan
+    let message_item =
+        MessageItem::new_file(name, get_current_timestamp(), true, &file_name, false);
+
+    let id = database.insert_message_item(message_item.clone()).await?;
+
+    if let Err(e) = upload_body(storage, quota, &file_name, size, body).await {
+        database.remove_message_item(id).await?;
+
+        return Err(e);
+    }
+
+    database.update_complete(id).await?;
+
+    let message_item = MessageItem {
+        is_complete: Some(true),
+        ..message_item
+    };
+
+    tracing::info!("webdav file uploaded");
+    tracing::debug!("webdav file id: {}", id);
+
+    process_uploaded_file(storage, database, id, &file_name).await?;
+
+    emit_new_item(socketio, id, message_item)?;
+
+    // the old file is only removed once the new one is in place
+    if let Some(entry) = replaced {
+        remove_entry(database, storage, socketio, &entry).await?;
+    }
+
+    Ok(())
+}
+
+async fn put_bundle_file(
+    database: &Database,
+    storage: &Storage,
+    quota: &Quota,
+    bundle: &Entry,
+    path: &str,
+    size: Option<u64>,
+    body: Body,
+) -> Result<Response> {
+    let path = sanitize_path(path)
+        .ok_or_else(|| Error::new(InternalServerError, "invalid path in bundle"))?;
+
+    let prefix =
+        bundle.item.file_name.as_deref().ok_or_else(|| {
+            Error::new(InternalServerError, "missed field fileName for bundle type")
+        })?;
+
+    let file_name = format!("{}/{}", prefix, path);
+
+    upload_body(storage, quota, &file_name, size, body).await?;
+
+    let is_new = !database
+        .query_bundle_files(bundle.item.id)
+        .await?
+        .iter()
+        .any(|file| file.path == path);
+
+    if !is_new {
+        tracing::info!("webdav bundle file replaced");
+
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    database
+        .insert_bundle_file(BundleFileItem {
+            message_id: bundle.item.id,
+            path,
+            file_name,
+        })
+        .await?;
+
+    tracing::info!("webdav bundle file uploaded");
+
+    Ok(StatusCode::CREATED.into_response())
+}
+
+// the body is streamed into a multipart upload, so it's never held in memory as a whole
+async fn upload_body(
+    storage: &Storage,
+    quota: &Quota,
+    object: &str,
+    size: Option<u64>,
+    body: Body,
+) -> Result<()> {
+    let upload_id = storage.create_multipart_upload_id(object).await?;
+
+    let result = match quota.register_upload(storage, &upload_id, size).await {
+        Ok(()) => {
+            let result = upload_parts(storage, quota, object, &upload_id, body).await;
+
+            quota.finish_upload(&upload_id).await;
+
+            result
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(parts) => {
+            storage
+                .complete_multipart_upload(object, &upload_id, &parts)
+                .await
+        }
+        Err(e) => {
+            if let Err(e) = storage.abort_multipart_upload(object, &upload_id).await {
+                tracing::warn!("failed to abort webdav upload: {}", e);
+            }
+
+            Err(e)
+        }
+    }
+}
+
+async fn upload_parts(
+    storage: &Storage,
+    quota: &Quota,
+    object: &str,
+    upload_id: &str,
+    mut body: Body,
+) -> Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut buffer = Vec::with_capacity(PART_SIZE);
+
+    while let Some(frame) = body.frame().await {
+        let frame = frame
+            .map_err(|e| Error::context(InternalServerError, e, "failed to read webdav body"))?;
+
+        if let Ok(data) = frame.into_data() {
+            buffer.extend_from_slice(&data);
+        }
+
+        while buffer.len() >= PART_SIZE {
+            let rest = buffer.split_off(PART_SIZE);
+            let part_data = std::mem::replace(&mut buffer, rest);
+
+            let part = upload_part(
+                storage,
+                quota,
+                object,
+                upload_id,
+                &part_data,
+                parts.len() + 1,
+            )
+            .await?;
+
+            parts.push(part);
+        }
+    }
+
+    // an empty file still needs a part
+    if !buffer.is_empty() || parts.is_empty() {
+        let part = upload_part(storage, quota, object, upload_id, &buffer, parts.len() + 1).await?;
+
+        parts.push(part);
+    }
+
+    Ok(parts)
+}
+
+async fn upload_part(
+    storage: &Storage,
+    quota: &Quota,
+    object: &str,
+    upload_id: &str,
+    part_data: &[u8],
+    part_number: usize,
+) -> Result<Part> {
+    let part_number = u16::try_from(part_number)
+        .map_err(|e| Error::context(InternalServerError, e, "too many parts in webdav upload"))?;
+
+    quota
+        .record_part(upload_id, part_number, part_data.len() as u64)
+        .await?;
+
+    let size = quota.get_upload_size(upload_id).await;
+
+    storage
+        .multipart_upload(object, upload_id, part_data, part_number, size)
+        .await
+}
+
+// files in a bundle are only removed along with the bundle
+async fn delete(
+    database: &Database,
+    storage: &Storage,
+    socketio: &SocketIo,
+    target: Target,
+) -> Result<Response> {
+    match target {
+        Target::Entry(entry) => {
+            remove_entry(database, storage, socketio, &entry).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Target::Missing { .. } => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::FORBIDDEN.into_response()),
+    }
+}
+
+async fn mkcol(
+    database: &Database,
+    storage: &Storage,
+    socketio: &SocketIo,
+    target: Target,
+) -> Result<Response> {
+    match target {
+        Target::Missing { parent: None, path } if path.len() == 1 => {
+            let name = &path[0];
+
+            let file_name = storage.create_object_name(name).await?;
+
+            let message_item =
+                MessageItem::new_bundle(name, get_current_timestamp(), true, &file_name, true);
+
+            let id = database.insert_message_item(message_item.clone()).await?;
+
+            tracing::info!("webdav bundle created");
+            tracing::debug!("webdav bundle id: {}", id);
+
+            emit_new_item(socketio, id, message_item)?;
+
+            Ok(StatusCode::CREATED.into_response())
+        }
+        // directories in a bundle show up once a file is put in them
+        Target::Missing {
+            parent: Some(_), ..
+        } => Ok(StatusCode::CREATED.into_response()),
+        Target::Missing { .. } => Ok(StatusCode::CONFLICT.into_response()),
+        _ => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+    }
+}
+
+async fn remove_entry(
+    database: &Database,
+    storage: &Storage,
+    socketio: &SocketIo,
+    entry: &Entry,
+) -> Result<()> {
+    remove_message(database, storage, entry.item.id).await?;
+
+    // sent to the room that received the item, private ones never reach the public room
+    let room = match entry.item.is_private {
+        true => Room::Private,
+        false => Room::Public,
+    };
+
+    socketio
+        .to(room)
+        .emit("removeItem", entry.item.id)
+        .map_err(|e| Error::context(InternalServerError, e, "failed to emit event removeItem"))?;
+
+    tracing::info!("webdav item removed");
+
+    Ok(())
+}
+
+fn emit_new_item(socketio: &SocketIo, id: i64, message_item: MessageItem) -> Result<()> {
+    socketio
+        .to(Room::Private)
+        .emit("newItem", message::Model::from((id, message_item)))
+        .map_err(|e| Error::context(InternalServerError, e, "failed to emit event newItem"))?;
+
+    Ok(())
+}
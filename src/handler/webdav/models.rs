@@ -0,0 +1,103 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use quick_xml::escape::escape;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::client::database::models::bundle;
+use crate::client::database::models::message;
+
+// a file or bundle message as it appears in the root of the mount
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub item: message::Model,
+}
+
+// what a request path points to
+#[derive(Debug)]
+pub enum Target {
+    Root,
+    Entry(Entry),
+    BundleFile {
+        bundle: Entry,
+        file: bundle::Model,
+    },
+    BundleDir {
+        bundle: Entry,
+        path: String,
+    },
+    // the parent is the bundle when the path is inside one
+    Missing {
+        parent: Option<Entry>,
+        path: Vec<String>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Resource {
+    pub href: String,
+    pub name: String,
+    pub is_collection: bool,
+    pub size: Option<u64>,
+    pub timestamp: i64,
+}
+
+impl Resource {
+    fn to_xml(&self) -> String {
+        let mut props = format!(
+            "<D:displayname>{}</D:displayname>",
+            escape(self.name.as_str())
+        );
+
+        if self.is_collection {
+            props.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            props.push_str("<D:resourcetype/>");
+
+            // sizes of encrypted objects are unknown
+            if let Some(size) = self.size {
+                props.push_str(&format!(
+                    "<D:getcontentlength>{}</D:getcontentlength>",
+                    size
+                ));
+            }
+
+            let content_type = mime_guess::from_path(&self.name).first_or_octet_stream();
+
+            props.push_str(&format!(
+                "<D:getcontenttype>{}</D:getcontenttype>",
+                escape(content_type.as_ref())
+            ));
+        }
+
+        let modified = UNIX_EPOCH + Duration::from_millis(self.timestamp.max(0) as u64);
+
+        props.push_str(&format!(
+            "<D:getlastmodified>{}</D:getlastmodified>",
+            httpdate::fmt_http_date(modified)
+        ));
+
+        format!(
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+            escape(self.href.as_str()),
+            props
+        )
+    }
+}
+
+pub fn to_multistatus(resources: &[Resource]) -> String {
+    let responses = resources
+        .iter()
+        .map(|resource| resource.to_xml())
+        .collect::<String>();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?><D:multistatus xmlns:D=\"DAV:\">{}</D:multistatus>",
+        responses
+    )
+}
@@ -0,0 +1,518 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::response::Response;
+use axum::routing::any;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD as base64;
+use base64::Engine;
+use http_body_util::BodyExt;
+use socketioxide::extract::SocketRef;
+use socketioxide::SocketIo;
+use strum::IntoEnumIterator;
+use tower::ServiceExt;
+
+use super::super::api::models::Account;
+use super::{webdav, WEBDAV_PATH};
+use crate::client::database::models::message::{self, MessageItem, MessageItemType};
+use crate::client::database::models::token::TokenNewItem;
+use crate::client::database::tests::{get_database, reset as reset_database};
+use crate::client::storage::tests::{fake_data, get_storage, init, reset as reset_storage};
+use crate::client::{Database, Storage};
+use crate::crypto::tests::get_crypto;
+use crate::crypto::Crypto;
+use crate::env::tests::{get_env, DBType, STType};
+use crate::env::{Env, QuotaEnv};
+use crate::error::tests::ServerExt;
+use crate::error::Error;
+use crate::error::Result;
+use crate::quota::tests::get_quota;
+use crate::quota::Quota;
+use crate::shutdown::tests::get_shutdown;
+use crate::utils::tests::{sleep_async, ResponseExt};
+use crate::utils::{get_current_timestamp, into_layer};
+
+async fn gen_token(database: &Database, crypto: &Crypto) -> Result<String> {
+    let env = get_env(DBType::Sqlite, STType::LocalStorage);
+
+    let account = Account {
+        username: env.username.clone(),
+        password: env.password.clone(),
+        expiration_timestamp: get_current_timestamp() + 1000 * 60,
+    };
+
+    let token =
+        crypto.encrypt(&serde_json::to_string(&account).map_err(Error::serialize_error)?)?;
+
+    database
+        .insert_token(TokenNewItem {
+            token: token.clone(),
+            name: "test name".to_string(),
+            expiration_timestamp: get_current_timestamp() + 1000 * 60,
+        })
+        .await?;
+
+    Ok(token)
+}
+
+fn gen_basic_auth(token: &str) -> String {
+    format!("Basic {}", base64.encode(format!("user:{}", token)))
+}
+
+async fn get_router(storage: &Storage, database: &Database, crypto: &Crypto) -> Result<Router> {
+    let env = get_env(DBType::Sqlite, STType::LocalStorage);
+
+    get_router_with_env(storage, database, crypto, env, get_quota()).await
+}
+
+async fn get_router_with_env(
+    storage: &Storage,
+    database: &Database,
+    crypto: &Crypto,
+    env: Env,
+    quota: Quota,
+) -> Result<Router> {
+    database.create_table_message_if_not_exists().await?;
+    database.create_table_bundle_if_not_exists().await?;
+    database.create_table_token_if_not_exists().await?;
+
+    let (socketio_layer, socketio) = SocketIo::new_layer();
+
+    socketio.ns("/", |_: SocketRef| {});
+
+    let router = Router::new()
+        .route(WEBDAV_PATH, any(webdav))
+        .route(&format!("{}/", WEBDAV_PATH), any(webdav))
+        .route(&format!("{}/*path", WEBDAV_PATH), any(webdav))
+        .layer(socketio_layer)
+        .layer(into_layer(socketio))
+        .layer(into_layer(env))
+        .layer(into_layer(storage.clone()))
+        .layer(into_layer(database.clone()))
+        .layer(into_layer(crypto.clone()))
+        .layer(into_layer(quota))
+        .layer(into_layer(get_shutdown()));
+
+    Ok(router)
+}
+
+async fn send(
+    router: &Router,
+    method: &str,
+    path: &str,
+    auth: &str,
+    body: Vec<u8>,
+) -> Result<Response> {
+    let req = Request::builder()
+        .method(Method::from_bytes(method.as_bytes()).unwrap())
+        .uri(format!("{}{}", WEBDAV_PATH, path))
+        .header(header::AUTHORIZATION, auth)
+        .header("Depth", "1")
+        .body(Body::from(body))
+        .map_err(Error::req_build_error)?;
+
+    router
+        .clone()
+        .oneshot(req)
+        .await
+        .map_err(Error::req_send_error)
+}
+
+async fn to_bytes(response: Response) -> Vec<u8> {
+    response
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes()
+        .to_vec()
+}
+
+#[tokio::test]
+async fn test_webdav_unauthorized() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<(Response, Response)> {
+        let crypto = get_crypto();
+        let router = get_router(storage, database, &crypto).await?;
+
+        let no_auth_res = send(&router, "PROPFIND", "/", "", Vec::new()).await?;
+
+        // a valid token that was never created or has been removed
+        let env = get_env(DBType::Sqlite, STType::LocalStorage);
+
+        let account = Account {
+            username: env.username.clone(),
+            password: env.password.clone(),
+            expiration_timestamp: get_current_timestamp() + 1000 * 60,
+        };
+
+        let token =
+            crypto.encrypt(&serde_json::to_string(&account).map_err(Error::serialize_error)?)?;
+
+        let removed_res = send(
+            &router,
+            "PROPFIND",
+            "/",
+            &gen_basic_auth(&token),
+            Vec::new(),
+        )
+        .await?;
+
+        Ok((no_auth_res, removed_res))
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_database(database).await;
+
+    let (no_auth_res, removed_res) = result.unwrap();
+
+    assert_eq!(no_auth_res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        no_auth_res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        "Basic realm=\"transfery\""
+    );
+    assert_eq!(removed_res.status(), StatusCode::UNAUTHORIZED);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_put_file() {
+    async fn inner(
+        storage: &Storage,
+        database: &Database,
+    ) -> Result<(Response, String, Vec<u8>, Vec<message::Model>)> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let router = get_router(storage, database, &crypto).await?;
+        let auth = gen_basic_auth(&gen_token(database, &crypto).await?);
+
+        let put_res = send(&router, "PUT", "/test%20webdav.txt", &auth, fake_data()).await?;
+
+        let propfind_res = send(&router, "PROPFIND", "/", &auth, Vec::new()).await?;
+        let propfind_content = propfind_res.to_string().await?;
+
+        let get_res = send(&router, "GET", "/test%20webdav.txt", &auth, Vec::new()).await?;
+        let data = to_bytes(get_res).await;
+
+        let items = database.query_message_files_complete().await?;
+
+        Ok((put_res, propfind_content, data, items))
+    }
+
+    for st_type in STType::iter() {
+        let storage = get_storage(st_type).await;
+        let database = get_database(DBType::Sqlite).await;
+
+        let result = inner(&storage, &database).await;
+        reset_storage(&storage).await;
+        reset_database(database).await;
+
+        let (put_res, propfind_content, data, items) = result.unwrap();
+
+        assert_eq!(put_res.status(), StatusCode::CREATED);
+        assert!(propfind_content.contains("<D:href>/webdav/test%20webdav.txt</D:href>"));
+        assert_eq!(data, fake_data());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].content, "test webdav.txt");
+        assert_eq!(items[0].type_field, MessageItemType::File);
+        assert!(items[0].is_private);
+    }
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_base_path() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<String> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+
+        let mut env = get_env(DBType::Sqlite, STType::LocalStorage);
+        env.base_path = "/transfery".to_string();
+
+        let router = get_router_with_env(storage, database, &crypto, env, get_quota()).await?;
+        let auth = gen_basic_auth(&gen_token(database, &crypto).await?);
+
+        send(&router, "PUT", "/test.txt", &auth, fake_data()).await?;
+
+        let propfind_res = send(&router, "PROPFIND", "/", &auth, Vec::new()).await?;
+
+        propfind_res.to_string().await
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let propfind_content = result.unwrap();
+
+    // hrefs carry the base path the server is mounted under
+    assert!(propfind_content.contains("<D:href>/transfery/webdav/</D:href>"));
+    assert!(propfind_content.contains("<D:href>/transfery/webdav/test.txt</D:href>"));
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_replace_file() {
+    async fn inner(
+        storage: &Storage,
+        database: &Database,
+    ) -> Result<(Response, Vec<u8>, Vec<message::Model>)> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let router = get_router(storage, database, &crypto).await?;
+        let auth = gen_basic_auth(&gen_token(database, &crypto).await?);
+
+        send(&router, "PUT", "/test.txt", &auth, b"old".to_vec()).await?;
+        let put_res = send(&router, "PUT", "/test.txt", &auth, b"new".to_vec()).await?;
+
+        let get_res = send(&router, "GET", "/test.txt", &auth, Vec::new()).await?;
+        let data = to_bytes(get_res).await;
+
+        let items = database.query_message_files_complete().await?;
+
+        Ok((put_res, data, items))
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let (put_res, data, items) = result.unwrap();
+
+    assert_eq!(put_res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(data, b"new");
+    assert_eq!(items.len(), 1);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_bundle() {
+    async fn inner(
+        storage: &Storage,
+        database: &Database,
+    ) -> Result<(Vec<StatusCode>, String, Vec<u8>, Vec<message::Model>)> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let router = get_router(storage, database, &crypto).await?;
+        let auth = gen_basic_auth(&gen_token(database, &crypto).await?);
+
+        let mut statuses = Vec::new();
+
+        for (method, path) in [("MKCOL", "/dir"), ("MKCOL", "/dir/sub")] {
+            let res = send(&router, method, path, &auth, Vec::new()).await?;
+            statuses.push(res.status());
+        }
+
+        let res = send(&router, "PUT", "/dir/sub/a.txt", &auth, fake_data()).await?;
+        statuses.push(res.status());
+
+        // only bundles have children
+        let res = send(&router, "PUT", "/missing/a.txt", &auth, fake_data()).await?;
+        statuses.push(res.status());
+
+        let propfind_res = send(&router, "PROPFIND", "/dir/", &auth, Vec::new()).await?;
+        let propfind_content = propfind_res.to_string().await?;
+
+        let get_res = send(&router, "GET", "/dir/sub/a.txt", &auth, Vec::new()).await?;
+        let data = to_bytes(get_res).await;
+
+        let res = send(&router, "DELETE", "/dir", &auth, Vec::new()).await?;
+        statuses.push(res.status());
+
+        let items = database.query_message_files_complete().await?;
+
+        Ok((statuses, propfind_content, data, items))
+    }
+
+    let storage = get_storage(STType::LocalStorage).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let (statuses, propfind_content, data, items) = result.unwrap();
+
+    assert_eq!(
+        statuses,
+        vec![
+            StatusCode::CREATED,
+            StatusCode::CREATED,
+            StatusCode::CREATED,
+            StatusCode::CONFLICT,
+            StatusCode::NO_CONTENT,
+        ]
+    );
+    assert!(propfind_content.contains("<D:href>/webdav/dir/sub/</D:href>"));
+    assert_eq!(data, fake_data());
+    assert!(items.is_empty());
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_put_length_required() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Vec<StatusCode>> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let env = get_env(DBType::Sqlite, STType::LocalStorage);
+        let quota = Quota::new(&QuotaEnv {
+            max_storage_size: None,
+            max_file_size: Some(fake_data().len() as u64),
+            max_daily_upload_size: None,
+        });
+
+        let router = get_router_with_env(storage, database, &crypto, env, quota).await?;
+        let auth = gen_basic_auth(&gen_token(database, &crypto).await?);
+
+        // the quota can't be checked without the size
+        let put_res = send(&router, "PUT", "/test.txt", &auth, fake_data()).await?;
+
+        let req = Request::builder()
+            .method(Method::PUT)
+            .uri(format!("{}/test.txt", WEBDAV_PATH))
+            .header(header::AUTHORIZATION, &auth)
+            .header(header::CONTENT_LENGTH, fake_data().len())
+            .body(Body::from(fake_data()))
+            .map_err(Error::req_build_error)?;
+
+        let put_sized_res = router
+            .clone()
+            .oneshot(req)
+            .await
+            .map_err(Error::req_send_error)?;
+
+        Ok(vec![put_res.status(), put_sized_res.status()])
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    assert_eq!(
+        result.unwrap(),
+        vec![StatusCode::LENGTH_REQUIRED, StatusCode::CREATED]
+    );
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_duplicate_names() {
+    async fn inner(
+        storage: &Storage,
+        database: &Database,
+    ) -> Result<(String, Vec<Vec<u8>>, StatusCode)> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let router = get_router(storage, database, &crypto).await?;
+        let auth = gen_basic_auth(&gen_token(database, &crypto).await?);
+
+        let timestamp = get_current_timestamp();
+
+        for (object, data) in [("a/test.txt", b"a"), ("b/test.txt", b"b")] {
+            storage.put_object(object, data).await?;
+
+            let item = MessageItem::new_file("test.txt", timestamp, true, object, true);
+            database.insert_message_item(item).await?;
+        }
+
+        let propfind_res = send(&router, "PROPFIND", "/", &auth, Vec::new()).await?;
+        let propfind_content = propfind_res.to_string().await?;
+
+        let mut data = Vec::new();
+
+        for path in ["/test.txt", "/test%20(2).txt"] {
+            let get_res = send(&router, "GET", path, &auth, Vec::new()).await?;
+            data.push(to_bytes(get_res).await);
+        }
+
+        // the id must point to a duplicate of the name
+        let get_res = send(&router, "GET", "/other%20(2).txt", &auth, Vec::new()).await?;
+
+        Ok((propfind_content, data, get_res.status()))
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let (propfind_content, data, status) = result.unwrap();
+
+    assert!(propfind_content.contains("<D:href>/webdav/test.txt</D:href>"));
+    assert!(propfind_content.contains("<D:href>/webdav/test%20%282%29.txt</D:href>"));
+    assert_eq!(data, vec![b"a".to_vec(), b"b".to_vec()]);
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    sleep_async(1).await;
+}
+
+#[tokio::test]
+async fn test_webdav_token_last_use() {
+    async fn inner(storage: &Storage, database: &Database) -> Result<Vec<i64>> {
+        init(storage).await?;
+
+        let crypto = get_crypto();
+        let router = get_router(storage, database, &crypto).await?;
+        let token = gen_token(database, &crypto).await?;
+        let auth = gen_basic_auth(&token);
+
+        let mut timestamps = Vec::new();
+
+        // a recent use isn't written again, an old one is
+        for last_use_timestamp in [1, get_current_timestamp()] {
+            database.update_token(&token, last_use_timestamp).await?;
+
+            send(&router, "PROPFIND", "/", &auth, Vec::new()).await?;
+
+            let token_item = database.query_token_item(&token).await?.unwrap();
+            timestamps.push(token_item.last_use_timestamp - last_use_timestamp);
+        }
+
+        Ok(timestamps)
+    }
+
+    let storage = get_storage(STType::Memory).await;
+    let database = get_database(DBType::Sqlite).await;
+
+    let result = inner(&storage, &database).await;
+    reset_storage(&storage).await;
+    reset_database(database).await;
+
+    let timestamps = result.unwrap();
+
+    assert!(timestamps[0] > 0);
+    assert_eq!(timestamps[1], 0);
+
+    sleep_async(1).await;
+}
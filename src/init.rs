@@ -20,6 +20,33 @@ pub async fn init(env: &Env) {
     println!("All initialization completed.");
 }
 
+// certificates and tokens encrypted with older keys stay valid during the grace period,
+// certificates are renewed on auto login but tokens are held by other apps and must be reissued
+pub async fn rotate_secret_key(env: &Env) {
+    let database = client::get_database(env).await;
+
+    database.init().await.unwrap();
+
+    let grace_period = env.secret_key_grace_period as i64 * 1000 * 3600 * 24;
+
+    println!("Rotating secret key...");
+    let id = database.rotate_secret_key(grace_period).await.unwrap();
+    println!(
+        "Secret key {} created, older keys retire in {} days.",
+        id, env.secret_key_grace_period
+    );
+    println!("Restart the server to use the new key.");
+
+    let token_number = database.count_token_items(true).await.unwrap();
+
+    if token_number > 0 {
+        println!(
+            "{} tokens were issued with older keys, create new ones before they retire.",
+            token_number
+        );
+    }
+}
+
 async fn init_storage(env: &Env) {
     let storage = client::get_storage(env).await;
 
@@ -0,0 +1,369 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::Router;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::env::{BindAddress, TlsEnv};
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+use crate::tls;
+
+// connections are served through the grace period, responses still running after it
+// get this long before they're dropped, so that the server always exits
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn bind_tcp(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, format!("failed to bind {}", addr)))
+}
+
+async fn serve_tcp(router: Router, listener: TcpListener, token: CancellationToken) -> Result<()> {
+    axum::serve(listener, router)
+        .with_graceful_shutdown(token.cancelled_owned())
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to serve http"))
+}
+
+#[cfg(unix)]
+mod unix {
+    use axum::Router;
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::service::TowerToHyperService;
+    use std::fs::Permissions;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::time::Duration;
+    use tokio::net::UnixListener;
+    use tokio::task::JoinSet;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::error::ErrorType::InternalServerError;
+    use crate::error::{Error, Result};
+
+    // a socket left by a crashed server is replaced, one still in use is kept
+    pub fn bind(path: &str, mode: u32) -> Result<UnixListener> {
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("{} exists and isn't a socket", path),
+                ));
+            }
+
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("{} is in use", path),
+                ));
+            }
+
+            std::fs::remove_file(path).map_err(|e| {
+                Error::context(InternalServerError, e, format!("failed to remove {}", path))
+            })?;
+        }
+
+        let listener = UnixListener::bind(path).map_err(|e| {
+            Error::context(InternalServerError, e, format!("failed to bind {}", path))
+        })?;
+
+        std::fs::set_permissions(path, Permissions::from_mode(mode)).map_err(|e| {
+            Error::context(
+                InternalServerError,
+                e,
+                format!("failed to set permissions of {}", path),
+            )
+        })?;
+
+        Ok(listener)
+    }
+
+    // axum::serve only takes tcp listeners, so connections are served by hyper directly
+    pub async fn serve(
+        router: Router,
+        listener: UnixListener,
+        path: String,
+        token: CancellationToken,
+    ) -> Result<()> {
+        let mut connections = JoinSet::new();
+
+        loop {
+            let stream = tokio::select! {
+                result = listener.accept() => match result {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        // usually out of file descriptors, waits for some to be closed
+                        tracing::error!("failed to accept connection on {}: {}", path, e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                },
+                _ = token.cancelled() => break,
+            };
+
+            let service = TowerToHyperService::new(router.clone());
+            let token = token.clone();
+
+            connections.spawn(async move {
+                let builder = Builder::new(TokioExecutor::new());
+                // upgrades are needed by websockets
+                let connection =
+                    builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                tokio::pin!(connection);
+
+                let result = tokio::select! {
+                    result = connection.as_mut() => result,
+                    _ = token.cancelled() => {
+                        connection.as_mut().graceful_shutdown();
+                        connection.await
+                    }
+                };
+
+                if let Err(e) = result {
+                    tracing::debug!("unix connection closed: {}", e);
+                }
+            });
+        }
+
+        drop(listener);
+        std::fs::remove_file(&path).ok();
+
+        while connections.join_next().await.is_some() {}
+
+        Ok(())
+    }
+}
+
+// every address is bound before serving, so a bad one stops the server from starting
+pub async fn serve<F>(
+    router: Router,
+    bind: &[BindAddress],
+    unix_socket_mode: u32,
+    tls: Option<TlsEnv>,
+    signal: F,
+) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let token = CancellationToken::new();
+    let mut servers = JoinSet::new();
+
+    let tls = match tls {
+        Some(tls) => {
+            let config = tls::init(&tls).await?;
+            Some((tls, config))
+        }
+        None => None,
+    };
+
+    for address in bind {
+        match (address, &tls) {
+            (BindAddress::Tcp(addr), Some((tls, config))) => {
+                let listener = std::net::TcpListener::bind(addr).map_err(|e| {
+                    Error::context(InternalServerError, e, format!("failed to bind {}", addr))
+                })?;
+
+                tracing::info!("listening on https://{}", addr);
+
+                servers.spawn(tls::serve(
+                    router.clone(),
+                    listener,
+                    config.clone(),
+                    token.clone(),
+                ));
+
+                if let Some(redirect_port) = tls.redirect_port {
+                    let redirect_addr = SocketAddr::new(addr.ip(), redirect_port);
+                    let listener = bind_tcp(redirect_addr).await?;
+
+                    tracing::info!("redirecting http://{} to https", redirect_addr);
+
+                    servers.spawn(tls::serve_redirect(listener, addr.port(), token.clone()));
+                }
+            }
+            (BindAddress::Tcp(addr), None) => {
+                let listener = bind_tcp(*addr).await?;
+
+                tracing::info!("listening on http://{}", addr);
+
+                servers.spawn(serve_tcp(router.clone(), listener, token.clone()));
+            }
+            #[cfg(unix)]
+            (BindAddress::Unix(path), _) => {
+                let listener = unix::bind(path, unix_socket_mode)?;
+
+                tracing::info!("listening on unix:{}", path);
+
+                servers.spawn(unix::serve(
+                    router.clone(),
+                    listener,
+                    path.clone(),
+                    token.clone(),
+                ));
+            }
+            #[cfg(not(unix))]
+            (BindAddress::Unix(path), _) => {
+                let _ = unix_socket_mode;
+
+                return Err(Error::new(
+                    InternalServerError,
+                    format!("unix sockets aren't supported on this platform: {}", path),
+                ));
+            }
+        }
+    }
+
+    let shutdown_token = token.clone();
+
+    // every listener stops accepting once the signal future returns
+    tokio::spawn(async move {
+        signal.await;
+        shutdown_token.cancel();
+    });
+
+    let stopped = async {
+        while let Some(result) = servers.join_next().await {
+            result.map_err(|e| Error::context(InternalServerError, e, "listener panicked"))??;
+        }
+
+        Ok(())
+    };
+    tokio::pin!(stopped);
+
+    tokio::select! {
+        result = &mut stopped => return result,
+        _ = token.cancelled() => {}
+    }
+
+    match tokio::time::timeout(CLOSE_TIMEOUT, stopped).await {
+        Ok(result) => result,
+        Err(_) => {
+            // the listeners are aborted when their set is dropped
+            tracing::warn!("connections still open after shutdown, closing them");
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::oneshot;
+
+    use crate::utils::tests::sleep_async;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_listener_serve() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = "./dev.listener.sock".to_string();
+        let addr = "127.0.0.1:18090".parse::<SocketAddr>().unwrap();
+
+        let router = Router::new().route("/", get(|| async { "ok" }));
+        let (stop, stopped) = oneshot::channel::<()>();
+
+        let server = tokio::spawn({
+            let path = path.clone();
+
+            async move {
+                serve(
+                    router,
+                    &[BindAddress::Tcp(addr), BindAddress::Unix(path.clone())],
+                    0o600,
+                    None,
+                    async {
+                        stopped.await.ok();
+                    },
+                )
+                .await
+            }
+        });
+
+        sleep_async(1).await;
+
+        let res = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("ok"), "{}", response);
+
+        stop.send(()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        // the socket is removed once the server stops
+        assert!(!std::path::Path::new(&path).exists());
+
+        sleep_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_listener_close_timeout() {
+        let addr = "127.0.0.1:18091".parse::<SocketAddr>().unwrap();
+
+        let router = Router::new().route("/", get(std::future::pending::<()>));
+        let (stop, stopped) = oneshot::channel::<()>();
+
+        let server = tokio::spawn(async move {
+            serve(router, &[BindAddress::Tcp(addr)], 0o660, None, async {
+                stopped.await.ok();
+            })
+            .await
+        });
+
+        sleep_async(1).await;
+
+        // the response never finishes, so the connection stays open
+        let request = tokio::spawn(reqwest::get(format!("http://{}/", addr)));
+
+        sleep_async(1).await;
+
+        stop.send(()).unwrap();
+
+        tokio::time::timeout(CLOSE_TIMEOUT + Duration::from_secs(3), server)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        request.abort();
+
+        sleep_async(1).await;
+    }
+}
@@ -7,102 +7,127 @@
 
 mod auth;
 mod client;
+mod command;
 mod crypto;
 mod env;
 mod error;
+mod front_end;
+mod fsck;
 mod handler;
 mod init;
+mod listener;
+mod metrics;
+mod quota;
+mod shutdown;
+mod tls;
+mod trace;
 mod utils;
 
 use client::{get_database, get_storage};
+use command::Command;
 use crypto::Crypto;
 use env::{args_contains, Env};
-use handler::{admin, api, download, index, message, socket, upload};
+use handler::{admin, api, download, health, index, message, socket, upload, webdav};
+use metrics::Metrics;
+use quota::Quota;
+use shutdown::Shutdown;
 use utils::into_layer;
 
-use axum::body::Body;
-use axum::extract::DefaultBodyLimit;
-use axum::http::Request;
-use axum::middleware::{self, Next};
-use axum::response::Response;
-use axum::routing::{get, post};
+use axum::extract::{DefaultBodyLimit, Extension};
+use axum::middleware;
+use axum::routing::{any, get, post};
 use axum::Router;
 use socketioxide::extract::{SocketRef, State};
 use socketioxide::SocketIo;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::Instant;
-use tower_http::services::ServeDir;
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::EnvFilter;
-
-async fn trace_middleware(req: Request<Body>, next: Next) -> Response {
-    let method = req.method().clone();
-    let uri = req.uri().clone();
-    let version = req.version();
-
-    let user_agent = req
-        .headers()
-        .get(axum::http::header::USER_AGENT)
-        .and_then(|value| value.to_str().ok())
-        .unwrap_or("-")
-        .to_string();
-
-    let start = Instant::now();
-    let response = next.run(req).await;
-    let latency = start.elapsed();
-
-    tracing::info!(
-        "method={}, uri={}, version={:?}, latency={:?}, status={}, user-agent={}",
-        method,
-        uri,
-        version,
-        latency,
-        response.status(),
-        user_agent
-    );
-
-    response
-}
 
 #[tokio::main]
 async fn main() {
-    let env = Env::new();
+    // prints a key for --encryption-key
+    if args_contains("--gen-encryption-key") {
+        println!("{}", Crypto::gen_secret_key().unwrap());
+        return;
+    }
+
+    let command = match Command::from_env() {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // every problem in the configuration is listed before exiting
+    let env = match Env::new() {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if args_contains("--print-config") {
+        print!("{}", env.to_redacted_config());
+        return;
+    }
 
     if args_contains("--init") {
         init::init(&env).await;
     }
 
+    if args_contains("--rotate-secret-key") {
+        init::rotate_secret_key(&env).await;
+        return;
+    }
+
+    if args_contains("--fsck") {
+        fsck::fsck(&env).await;
+        return;
+    }
+
+    if let Some(command) = command {
+        if let Err(e) = command::run(&env, command).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     server(env).await;
 }
 
 async fn server(env: Env) {
-    let port = env.port;
-    const HOST: &str = "0.0.0.0";
-
-    tracing_subscriber::registry()
-        .with(
-            EnvFilter::from_default_env().add_directive(
-                format!("transfery={}", env.mode.tracing_level())
-                    .parse()
-                    .unwrap(),
-            ),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let bind = env.bind.clone();
+    let unix_socket_mode = env.unix_socket_mode;
+    let shutdown_grace_period = env.shutdown_grace_period;
+    let tls = env.tls.clone();
+    let base_path = env.base_path.clone();
+    let static_router = index::static_router(env.front_end_dir.as_deref());
 
-    tracing::info!("listening on http://{}:{}", HOST, port);
+    trace::init(&env);
 
     let storage = get_storage(&env).await;
     let database = get_database(&env).await;
 
-    let secret_key = database.get_secret_key().await.unwrap();
-    let crypto = Crypto::new(&secret_key).unwrap();
+    let secret_keys = database.get_secret_keys().await.unwrap();
+    let crypto = Crypto::from_secret_keys(secret_keys).unwrap();
+
+    let quota = Quota::new(&env.quota);
+
+    // shared with the shutdown signal, so it isn't wrapped by into_layer
+    let shutdown = Arc::new(Shutdown::new());
+
+    // shared with the middleware, so it isn't wrapped by into_layer
+    let metrics = Arc::new(Metrics::new(&base_path));
+
+    let connection_number = socket::ConnectionNumber::new();
 
     let (socketio_layer, socketio) = SocketIo::builder()
         .ping_interval(Duration::from_secs(3))
         .ping_timeout(Duration::from_secs(2))
-        .with_state(socket::ConnectionNumber::new())
+        .with_state(connection_number.clone())
         .build_layer();
 
     socketio.ns(
@@ -114,18 +139,25 @@ async fn server(env: Env) {
         },
     );
 
-    let router = Router::new()
-        .nest_service("/static", ServeDir::new("./static"))
+    let mut router = Router::new()
+        .merge(static_router)
         .route(index::INDEX_PATH, get(index::index))
         .route(download::DOWNLOAD_PATH, get(download::download))
+        .route(download::PREVIEW_PATH, get(download::preview))
+        .route(download::THUMBNAIL_PATH, get(download::thumbnail))
+        .route(download::DOWNLOAD_ZIP_PATH, get(download::download_zip))
         .route(message::PAGE_PATH, get(message::page))
         .route(message::SYNC_PATH, get(message::sync))
         .route(message::NEW_ITEM_PATH, post(message::new_item))
         .route(message::REMOVE_ITEM_PATH, post(message::remove_item))
         .route(message::REMOVE_ALL_PATH, get(message::remove_all))
+        .route(message::BUNDLE_PATH, get(message::bundle))
         .route(upload::FETCH_UPLOAD_ID_PATH, post(upload::fetch_upload_id))
         .route(upload::UPLOAD_PART_PATH, post(upload::upload_part))
         .route(upload::COMPLETE_UPLOAD_PATH, post(upload::complete_upload))
+        .route(upload::ABORT_UPLOAD_PATH, post(upload::abort_upload))
+        .route(upload::COMPLETE_BUNDLE_PATH, post(upload::complete_bundle))
+        .route(upload::USAGE_PATH, get(upload::usage))
         .route(admin::AUTH_PATH, post(admin::auth))
         .route(admin::AUTO_LOGIN_PATH, get(admin::auto_login))
         .route(admin::SIGN_OUT_PATH, get(admin::sign_out))
@@ -134,21 +166,67 @@ async fn server(env: Env) {
         .route(admin::CREATE_TOKEN_PATH, post(admin::create_token))
         .route(admin::GET_TOKEN_PATH, get(admin::get_token))
         .route(admin::REMOVE_TOKEN_PATH, post(admin::remove_token))
+        .route(admin::FSCK_PATH, post(admin::fsck))
         .route(
             api::PUSH_TEXT_PATH,
             get(api::push_text).post(api::push_text),
         )
         .route(api::LATEST_TEXT_PATH, get(api::latest_text))
-        .layer(middleware::from_fn(trace_middleware))
+        .route(webdav::WEBDAV_PATH, any(webdav::webdav))
+        .route(&format!("{}/", webdav::WEBDAV_PATH), any(webdav::webdav))
+        .route(
+            &format!("{}/*path", webdav::WEBDAV_PATH),
+            any(webdav::webdav),
+        )
+        .layer(middleware::from_fn(trace::trace_middleware))
+        .layer(middleware::from_fn_with_state(
+            metrics.clone(),
+            metrics::metrics_middleware,
+        ))
+        // probed every few seconds, kept out of the request log
+        .route(health::HEALTHZ_PATH, get(health::healthz))
+        .route(health::READYZ_PATH, get(health::readyz))
+        .route(health::VERSION_PATH, get(health::version))
+        .route(
+            handler::metrics::METRICS_PATH,
+            get(handler::metrics::metrics),
+        )
         .layer(DefaultBodyLimit::max(1024 * 1024 * 10)) // 10 MB, must larger than 5 MB for minio
         .layer(socketio_layer)
-        .layer(into_layer(socketio))
+        .layer(into_layer(socketio.clone()))
         .layer(into_layer(env))
         .layer(into_layer(storage))
-        .layer(into_layer(database))
-        .layer(into_layer(crypto));
+        .layer(into_layer(database.clone()))
+        .layer(into_layer(crypto))
+        .layer(into_layer(quota))
+        .layer(Extension(shutdown.clone()))
+        .layer(Extension(metrics))
+        .layer(into_layer(connection_number));
+
+    if !base_path.is_empty() {
+        tracing::info!("serving under {}", base_path);
+
+        // as a service the inner router sees paths without the prefix, so /base/ serves the index
+        router = Router::new().nest_service(&base_path, router);
+    }
+
+    let signal = shutdown::graceful_shutdown(shutdown, socketio, shutdown_grace_period);
+
+    if let Some(tls) = &tls {
+        if tls.hsts_max_age > 0 {
+            router = router.layer(middleware::from_fn_with_state(
+                tls.hsts_max_age,
+                tls::hsts_middleware,
+            ));
+        }
+    }
+
+    listener::serve(router, &bind, unix_socket_mode, tls, signal)
+        .await
+        .unwrap();
 
-    let listener = tokio::net::TcpListener::bind((HOST, port)).await.unwrap();
+    // flushes pending writes, sqlite checkpoints its journal on close
+    database.close().await.unwrap();
 
-    axum::serve(listener, router).await.unwrap();
+    tracing::info!("server stopped");
 }
@@ -0,0 +1,467 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use http_body_util::BodyExt;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::handler::download::{DOWNLOAD_PATH, DOWNLOAD_ZIP_PATH, PREVIEW_PATH};
+use crate::handler::upload::UPLOAD_PART_PATH;
+use crate::handler::webdav::WEBDAV_PATH;
+
+// upper bounds in seconds
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+    // cumulative, each bucket counts every value up to its bound
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct RouteStats {
+    statuses: BTreeMap<u16, u64>,
+    latency: Histogram,
+}
+
+// values read from other parts of the server when the metrics are scraped
+pub struct Gauges {
+    pub multipart_uploads: usize,
+    pub socket_connections: usize,
+    pub messages: Vec<(String, u64)>,
+    pub active_tokens: u64,
+    pub expired_tokens: u64,
+    pub token_last_use_timestamp: Option<i64>,
+}
+
+// counters are kept in memory and start over when the server restarts
+pub struct Metrics {
+    // keyed by method and route template, so that ids in paths don't add series
+    routes: Mutex<BTreeMap<(String, String), RouteStats>>,
+    uploaded_bytes: AtomicU64,
+    downloaded_bytes: AtomicU64,
+    base_path: String,
+}
+
+impl Metrics {
+    pub fn new(base_path: &str) -> Self {
+        Self {
+            routes: Mutex::new(BTreeMap::new()),
+            uploaded_bytes: AtomicU64::new(0),
+            downloaded_bytes: AtomicU64::new(0),
+            base_path: base_path.to_string(),
+        }
+    }
+
+    // under a base path the matched path starts with it, and the index doubles the slash
+    fn get_route(&self, matched_path: &str) -> String {
+        let route = matched_path
+            .strip_prefix(&self.base_path)
+            .unwrap_or(matched_path);
+
+        let route = route
+            .strip_prefix('/')
+            .filter(|route| route.starts_with('/'))
+            .unwrap_or(route);
+
+        match route.is_empty() {
+            true => "/".to_string(),
+            false => route.to_string(),
+        }
+    }
+
+    async fn record_request(&self, method: &Method, route: &str, status: u16, latency: f64) {
+        let mut routes = self.routes.lock().await;
+
+        let stats = routes
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+
+        *stats.statuses.entry(status).or_default() += 1;
+        stats.latency.observe(latency);
+    }
+
+    pub async fn render(&self, gauges: &Gauges) -> String {
+        let mut output = String::new();
+        let routes = self.routes.lock().await;
+
+        write_header(
+            &mut output,
+            "transfery_http_requests_total",
+            "counter",
+            "HTTP requests by route and status.",
+        );
+
+        for ((method, route), stats) in routes.iter() {
+            for (status, count) in &stats.statuses {
+                writeln!(
+                    output,
+                    "transfery_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    method,
+                    escape(route),
+                    status,
+                    count
+                )
+                .ok();
+            }
+        }
+
+        write_header(
+            &mut output,
+            "transfery_http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency by route.",
+        );
+
+        for ((method, route), stats) in routes.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape(route));
+            let latency = &stats.latency;
+
+            for (bucket, bound) in latency.buckets.iter().zip(LATENCY_BUCKETS) {
+                writeln!(
+                    output,
+                    "transfery_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, bucket
+                )
+                .ok();
+            }
+
+            writeln!(
+                output,
+                "transfery_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, latency.count
+            )
+            .ok();
+            writeln!(
+                output,
+                "transfery_http_request_duration_seconds_sum{{{}}} {}",
+                labels, latency.sum
+            )
+            .ok();
+            writeln!(
+                output,
+                "transfery_http_request_duration_seconds_count{{{}}} {}",
+                labels, latency.count
+            )
+            .ok();
+        }
+
+        write_value(
+            &mut output,
+            "transfery_uploaded_bytes_total",
+            "counter",
+            "Bytes received by file uploads.",
+            self.uploaded_bytes.load(Ordering::SeqCst),
+        );
+        write_value(
+            &mut output,
+            "transfery_downloaded_bytes_total",
+            "counter",
+            "Bytes sent by file downloads through the server.",
+            self.downloaded_bytes.load(Ordering::SeqCst),
+        );
+        write_value(
+            &mut output,
+            "transfery_multipart_uploads",
+            "gauge",
+            "Multipart uploads in progress.",
+            gauges.multipart_uploads,
+        );
+        write_value(
+            &mut output,
+            "transfery_socket_connections",
+            "gauge",
+            "Connected socket clients.",
+            gauges.socket_connections,
+        );
+
+        write_header(
+            &mut output,
+            "transfery_messages",
+            "gauge",
+            "Stored messages by type.",
+        );
+
+        for (type_field, count) in &gauges.messages {
+            writeln!(
+                output,
+                "transfery_messages{{type=\"{}\"}} {}",
+                type_field, count
+            )
+            .ok();
+        }
+
+        write_header(
+            &mut output,
+            "transfery_tokens",
+            "gauge",
+            "API tokens by state.",
+        );
+
+        writeln!(
+            output,
+            "transfery_tokens{{state=\"active\"}} {}",
+            gauges.active_tokens
+        )
+        .ok();
+        writeln!(
+            output,
+            "transfery_tokens{{state=\"expired\"}} {}",
+            gauges.expired_tokens
+        )
+        .ok();
+
+        if let Some(timestamp) = gauges.token_last_use_timestamp {
+            write_value(
+                &mut output,
+                "transfery_token_last_use_timestamp_seconds",
+                "gauge",
+                "Last time any API token was used.",
+                timestamp / 1000,
+            );
+        }
+
+        output
+    }
+}
+
+fn write_header(output: &mut String, name: &str, metric_type: &str, help: &str) {
+    writeln!(output, "# HELP {} {}", name, help).ok();
+    writeln!(output, "# TYPE {} {}", name, metric_type).ok();
+}
+
+fn write_value<T>(output: &mut String, name: &str, metric_type: &str, help: &str, value: T)
+where
+    T: std::fmt::Display,
+{
+    write_header(output, name, metric_type, help);
+    writeln!(output, "{} {}", name, value).ok();
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn is_upload(method: &Method, route: &str) -> bool {
+    route == UPLOAD_PART_PATH || (method == Method::PUT && route.starts_with(WEBDAV_PATH))
+}
+
+fn is_download(method: &Method, route: &str) -> bool {
+    [DOWNLOAD_PATH, PREVIEW_PATH, DOWNLOAD_ZIP_PATH].contains(&route)
+        || (method == Method::GET && route.starts_with(WEBDAV_PATH))
+}
+
+// bodies are counted as they're streamed, downloads redirected to the storage aren't seen
+fn count_bytes(body: Body, metrics: Arc<Metrics>, is_upload: bool) -> Body {
+    Body::new(body.map_frame(move |frame| {
+        if let Some(data) = frame.data_ref() {
+            let counter = if is_upload {
+                &metrics.uploaded_bytes
+            } else {
+                &metrics.downloaded_bytes
+            };
+
+            counter.fetch_add(data.len() as u64, Ordering::SeqCst);
+        }
+
+        frame
+    }))
+}
+
+pub async fn metrics_middleware(
+    State(metrics): State<Arc<Metrics>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let method = req.method().clone();
+
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| metrics.get_route(path.as_str()))
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let req = if is_upload(&method, &route) {
+        req.map(|body| count_bytes(body, metrics.clone(), true))
+    } else {
+        req
+    };
+
+    let start = Instant::now();
+    let response = next.run(req).await;
+    let latency = start.elapsed().as_secs_f64();
+
+    metrics
+        .record_request(&method, &route, response.status().as_u16(), latency)
+        .await;
+
+    if is_download(&method, &route) {
+        response.map(|body| count_bytes(body, metrics.clone(), false))
+    } else {
+        response
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use axum::middleware;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use crate::utils::tests::{sleep_async, ResponseExt};
+
+    pub fn get_metrics() -> Metrics {
+        Metrics::new("")
+    }
+
+    fn get_gauges() -> Gauges {
+        Gauges {
+            multipart_uploads: 0,
+            socket_connections: 0,
+            messages: Vec::new(),
+            active_tokens: 0,
+            expired_tokens: 0,
+            token_last_use_timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_middleware() {
+        let metrics = Arc::new(get_metrics());
+
+        let router = Router::new()
+            .route(UPLOAD_PART_PATH, post(|body: String| async move { body }))
+            .route(DOWNLOAD_PATH, get(|| async { "downloaded" }))
+            .layer(middleware::from_fn_with_state(
+                metrics.clone(),
+                metrics_middleware,
+            ));
+
+        let mut statuses = Vec::new();
+
+        for (method, uri, body) in [
+            (Method::POST, UPLOAD_PART_PATH.to_string(), "uploaded"),
+            (Method::GET, format!("{}?fileName=a.txt", DOWNLOAD_PATH), ""),
+            (Method::GET, format!("{}?fileName=b.txt", DOWNLOAD_PATH), ""),
+            (Method::GET, "/missing".to_string(), ""),
+        ] {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::from(body))
+                .unwrap();
+
+            let res = router.clone().oneshot(req).await.unwrap();
+
+            statuses.push(res.status());
+
+            // downloaded bytes are counted as the body is read
+            res.to_string().await.unwrap();
+        }
+
+        let output = metrics.render(&get_gauges()).await;
+
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::NOT_FOUND
+            ]
+        );
+        assert!(output.contains(
+            "transfery_http_requests_total{method=\"GET\",route=\"/download\",status=\"200\"} 2"
+        ));
+        assert!(output.contains(
+            "transfery_http_request_duration_seconds_count{method=\"POST\",route=\"/uploadPart\"} 1"
+        ));
+        assert!(output.contains("transfery_uploaded_bytes_total 8"));
+        assert!(output.contains("transfery_downloaded_bytes_total 20"));
+
+        sleep_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_metrics_render() {
+        let metrics = get_metrics();
+
+        metrics
+            .record_request(&Method::GET, "/page", 200, 0.02)
+            .await;
+
+        let gauges = Gauges {
+            multipart_uploads: 2,
+            socket_connections: 3,
+            messages: vec![("text".to_string(), 4), ("file".to_string(), 5)],
+            active_tokens: 1,
+            expired_tokens: 2,
+            token_last_use_timestamp: Some(1_700_000_000_000),
+        };
+
+        let output = metrics.render(&gauges).await;
+
+        for expected in [
+            "# TYPE transfery_http_request_duration_seconds histogram",
+            "transfery_http_request_duration_seconds_bucket{method=\"GET\",route=\"/page\",le=\"0.01\"} 0",
+            "transfery_http_request_duration_seconds_bucket{method=\"GET\",route=\"/page\",le=\"0.025\"} 1",
+            "transfery_http_request_duration_seconds_bucket{method=\"GET\",route=\"/page\",le=\"+Inf\"} 1",
+            "transfery_multipart_uploads 2",
+            "transfery_socket_connections 3",
+            "transfery_messages{type=\"file\"} 5",
+            "transfery_tokens{state=\"expired\"} 2",
+            "transfery_token_last_use_timestamp_seconds 1700000000",
+        ] {
+            assert!(output.contains(expected), "{}", output);
+        }
+
+        sleep_async(1).await;
+    }
+
+    #[test]
+    fn test_metrics_get_route() {
+        let metrics = Metrics::new("/transfery");
+
+        assert_eq!(metrics.get_route("/transfery/"), "/");
+        assert_eq!(metrics.get_route("/transfery//"), "/");
+        assert_eq!(metrics.get_route("/transfery/uploadPart"), "/uploadPart");
+        assert_eq!(get_metrics().get_route("/uploadPart"), "/uploadPart");
+    }
+}
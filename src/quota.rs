@@ -0,0 +1,402 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+use crate::client::Storage;
+use crate::env::QuotaEnv;
+use crate::error::ErrorType::{InternalServerError, QuotaExceededError};
+use crate::error::{Error, Result};
+use crate::utils::{get_current_timestamp, UPLOAD_EXPIRATION};
+
+struct DailyUsage {
+    date: NaiveDate,
+    size: u64,
+}
+
+impl DailyUsage {
+    fn roll_over(&mut self) {
+        let today = Utc::now().date_naive();
+
+        if self.date != today {
+            self.date = today;
+            self.size = 0;
+        }
+    }
+}
+
+struct UploadUsage {
+    declared_size: Option<u64>,
+    // keyed by part number, so that a retried part replaces the one before it
+    parts: HashMap<u16, u64>,
+    expiration_timestamp: i64,
+}
+
+impl UploadUsage {
+    fn get_written_size(&self) -> u64 {
+        self.parts.values().sum()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Usage {
+    #[serde(rename = "storageSize")]
+    pub storage_size: u64,
+    #[serde(rename = "maxStorageSize")]
+    pub max_storage_size: Option<u64>,
+    #[serde(rename = "dailyUploadSize")]
+    pub daily_upload_size: u64,
+    #[serde(rename = "maxDailyUploadSize")]
+    pub max_daily_upload_size: Option<u64>,
+    #[serde(rename = "maxFileSize")]
+    pub max_file_size: Option<u64>,
+}
+
+// daily usage is kept in memory and starts over when the server restarts
+pub struct Quota {
+    config: QuotaEnv,
+    daily: Mutex<DailyUsage>,
+    uploads: Mutex<HashMap<String, UploadUsage>>,
+}
+
+impl Quota {
+    pub fn new(config: &QuotaEnv) -> Self {
+        Self {
+            config: config.clone(),
+            daily: Mutex::new(DailyUsage {
+                date: Utc::now().date_naive(),
+                size: 0,
+            }),
+            uploads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.config.max_storage_size.is_some()
+            || self.config.max_file_size.is_some()
+            || self.config.max_daily_upload_size.is_some()
+    }
+
+    async fn get_daily_upload_size(&self) -> u64 {
+        let mut daily = self.daily.lock().await;
+
+        daily.roll_over();
+
+        daily.size
+    }
+
+    // space promised to uploads that haven't finished yet
+    fn get_pending_size(uploads: &HashMap<String, UploadUsage>) -> u64 {
+        uploads
+            .values()
+            .map(|upload| {
+                upload
+                    .declared_size
+                    .unwrap_or_default()
+                    .saturating_sub(upload.get_written_size())
+            })
+            .sum()
+    }
+
+    async fn check_upload(
+        &self,
+        storage_size: Option<u64>,
+        uploads: &HashMap<String, UploadUsage>,
+        size: Option<u64>,
+    ) -> Result<()> {
+        if !self.is_limited() {
+            return Ok(());
+        }
+
+        let size = size.ok_or_else(|| {
+            Error::new(
+                InternalServerError,
+                "file size is required when quota is enabled",
+            )
+        })?;
+
+        if let Some(max_file_size) = self.config.max_file_size {
+            if size > max_file_size {
+                return Err(Error::new(
+                    QuotaExceededError,
+                    format!("file size {} exceeds limit {}", size, max_file_size),
+                ));
+            }
+        }
+
+        if let Some(max_daily_upload_size) = self.config.max_daily_upload_size {
+            let daily_upload_size =
+                self.get_daily_upload_size().await + Self::get_pending_size(uploads);
+
+            if daily_upload_size + size > max_daily_upload_size {
+                return Err(Error::new(
+                    QuotaExceededError,
+                    format!("daily upload size exceeds limit {}", max_daily_upload_size),
+                ));
+            }
+        }
+
+        if let (Some(max_storage_size), Some(storage_size)) =
+            (self.config.max_storage_size, storage_size)
+        {
+            let storage_size = storage_size + Self::get_pending_size(uploads);
+
+            if storage_size + size > max_storage_size {
+                return Err(Error::new(
+                    QuotaExceededError,
+                    format!("storage size exceeds limit {}", max_storage_size),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // checked under the same lock as the registration, so that concurrent uploads
+    // can't both pass the check with the same free space, the storage is listed
+    // before the lock so that parts of other uploads aren't held up by it
+    pub async fn register_upload(
+        &self,
+        storage: &Storage,
+        upload_id: &str,
+        size: Option<u64>,
+    ) -> Result<()> {
+        let storage_size = match self.config.max_storage_size {
+            Some(_) => Some(storage.get_usage().await?),
+            None => None,
+        };
+
+        let mut uploads = self.uploads.lock().await;
+
+        let current_timestamp = get_current_timestamp();
+
+        // abandoned uploads would hold their space forever
+        uploads.retain(|_, upload| upload.expiration_timestamp > current_timestamp);
+
+        self.check_upload(storage_size, &uploads, size).await?;
+
+        uploads.insert(
+            upload_id.to_string(),
+            UploadUsage {
+                declared_size: size,
+                parts: HashMap::new(),
+                expiration_timestamp: current_timestamp + UPLOAD_EXPIRATION,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub async fn record_part(
+        &self,
+        upload_id: &str,
+        part_number: u16,
+        part_size: u64,
+    ) -> Result<()> {
+        let mut uploads = self.uploads.lock().await;
+
+        if !self.is_limited() && !uploads.contains_key(upload_id) {
+            return Ok(());
+        }
+
+        // uploads started before a restart are unknown, they're counted without a declared size
+        let upload = uploads
+            .entry(upload_id.to_string())
+            .or_insert_with(|| UploadUsage {
+                declared_size: None,
+                parts: HashMap::new(),
+                expiration_timestamp: get_current_timestamp() + UPLOAD_EXPIRATION,
+            });
+
+        let previous_size = upload.parts.get(&part_number).copied().unwrap_or_default();
+
+        if let Some(declared_size) = upload.declared_size {
+            let written_size = upload.get_written_size() - previous_size + part_size;
+
+            if written_size > declared_size {
+                return Err(Error::new(
+                    QuotaExceededError,
+                    format!("upload exceeds declared size {}", declared_size),
+                ));
+            }
+        }
+
+        let mut daily = self.daily.lock().await;
+
+        daily.roll_over();
+
+        let daily_upload_size = daily.size.saturating_sub(previous_size) + part_size;
+
+        if let Some(max_daily_upload_size) = self.config.max_daily_upload_size {
+            if daily_upload_size > max_daily_upload_size {
+                return Err(Error::new(
+                    QuotaExceededError,
+                    format!("daily upload size exceeds limit {}", max_daily_upload_size),
+                ));
+            }
+        }
+
+        // nothing is committed until every limit has passed
+        daily.size = daily_upload_size;
+        upload.parts.insert(part_number, part_size);
+
+        Ok(())
+    }
+
+    pub async fn get_upload_size(&self, upload_id: &str) -> Option<u64> {
+        self.uploads
+            .lock()
+            .await
+            .get(upload_id)
+            .and_then(|upload| upload.declared_size)
+    }
+
+    // uploads that have started and haven't finished or expired
+    pub async fn get_upload_count(&self) -> usize {
+        let current_timestamp = get_current_timestamp();
+
+        self.uploads
+            .lock()
+            .await
+            .values()
+            .filter(|upload| upload.expiration_timestamp > current_timestamp)
+            .count()
+    }
+
+    pub async fn finish_upload(&self, upload_id: &str) {
+        self.uploads.lock().await.remove(upload_id);
+    }
+
+    pub async fn get_usage(&self, storage: &Storage) -> Result<Usage> {
+        let storage_size = storage.get_usage().await?;
+        let daily_upload_size = self.get_daily_upload_size().await;
+
+        Ok(Usage {
+            storage_size,
+            max_storage_size: self.config.max_storage_size,
+            daily_upload_size,
+            max_daily_upload_size: self.config.max_daily_upload_size,
+            max_file_size: self.config.max_file_size,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    use crate::client::storage::tests::{get_storage, init, reset};
+    use crate::env::tests::STType;
+    use crate::utils::tests::sleep_async;
+
+    pub fn get_quota() -> Quota {
+        Quota::new(&QuotaEnv::default())
+    }
+
+    #[tokio::test]
+    async fn test_quota_check_upload() {
+        async fn inner(storage: &Storage) -> Result<Vec<bool>> {
+            init(storage).await?;
+
+            storage
+                .put_object("test_check_upload.txt", b"hello")
+                .await?;
+
+            let quota = Quota::new(&QuotaEnv {
+                max_storage_size: Some(20),
+                max_file_size: Some(10),
+                max_daily_upload_size: None,
+            });
+
+            quota.register_upload(storage, "upload", Some(8)).await?;
+
+            Ok(vec![
+                quota.register_upload(storage, "1", None).await.is_err(),
+                quota.register_upload(storage, "2", Some(11)).await.is_err(),
+                quota.register_upload(storage, "3", Some(8)).await.is_err(),
+                quota.register_upload(storage, "4", Some(7)).await.is_err(),
+                // the space of the last upload is taken now
+                quota.register_upload(storage, "5", Some(1)).await.is_err(),
+                quota.get_upload_count().await == 2,
+            ])
+        }
+
+        let storage = get_storage(STType::LocalStorage).await;
+
+        let result = inner(&storage).await;
+        reset(&storage).await;
+        assert_eq!(result.unwrap(), vec![true, true, true, false, true, true]);
+
+        sleep_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_quota_record_part() {
+        async fn inner(storage: &Storage, quota: &Quota) -> Result<Vec<Option<StatusCode>>> {
+            init(storage).await?;
+
+            let mut results = Vec::new();
+
+            quota.register_upload(storage, "upload", Some(6)).await?;
+            quota.record_part("upload", 1, 4).await?;
+
+            for (upload_id, part_number, part_size) in [
+                ("upload", 2, 4),
+                // a retried part is only counted once
+                ("upload", 1, 4),
+                ("unknown", 1, 1),
+            ] {
+                let result = quota.record_part(upload_id, part_number, part_size).await;
+                results.push(result.err().map(|e| e.into_response().status()));
+            }
+
+            quota.finish_upload("upload").await;
+
+            quota.register_upload(storage, "other", Some(6)).await?;
+            quota.record_part("other", 1, 6).await?;
+
+            let result = quota.record_part("other", 2, 1).await;
+            results.push(result.err().map(|e| e.into_response().status()));
+
+            Ok(results)
+        }
+
+        let storage = get_storage(STType::LocalStorage).await;
+        let quota = Quota::new(&QuotaEnv {
+            max_daily_upload_size: Some(11),
+            ..Default::default()
+        });
+
+        let result = inner(&storage, &quota).await;
+        reset(&storage).await;
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Some(StatusCode::PAYLOAD_TOO_LARGE),
+                None,
+                None,
+                Some(StatusCode::PAYLOAD_TOO_LARGE),
+            ]
+        );
+        assert_eq!(quota.get_daily_upload_size().await, 11);
+
+        // without limits nothing is tracked for unknown uploads
+        let quota = get_quota();
+
+        assert!(quota.record_part("unknown", 1, 1).await.is_ok());
+        assert_eq!(quota.get_upload_count().await, 0);
+
+        sleep_async(1).await;
+    }
+}
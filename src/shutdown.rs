@@ -0,0 +1,178 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use socketioxide::SocketIo;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal;
+use tokio::sync::Notify;
+
+use crate::error::ErrorType::ServiceUnavailableError;
+use crate::error::{Error, Result};
+
+pub static SERVER_SHUTDOWN_EVENT: &str = "serverShutdown";
+
+// upload requests are counted so that they can finish before the server exits
+pub struct Shutdown {
+    is_draining: AtomicBool,
+    uploads: AtomicUsize,
+    idle: Notify,
+}
+
+// held while an upload request is handled
+pub struct UploadGuard<'a> {
+    shutdown: &'a Shutdown,
+}
+
+impl Drop for UploadGuard<'_> {
+    fn drop(&mut self) {
+        if self.shutdown.uploads.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            is_draining: AtomicBool::new(false),
+            uploads: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.is_draining.load(Ordering::SeqCst)
+    }
+
+    // new uploads are refused once the server is shutting down
+    pub fn begin_upload(&self) -> Result<UploadGuard<'_>> {
+        if self.is_draining() {
+            return Err(Error::new(
+                ServiceUnavailableError,
+                "server is shutting down",
+            ));
+        }
+
+        Ok(self.continue_upload())
+    }
+
+    // parts of an upload that has already started are still accepted
+    pub fn continue_upload(&self) -> UploadGuard<'_> {
+        self.uploads.fetch_add(1, Ordering::SeqCst);
+
+        UploadGuard { shutdown: self }
+    }
+
+    // returns false if uploads are still running when the grace period ends
+    pub async fn drain(&self, grace_period: Duration) -> bool {
+        self.is_draining.store(true, Ordering::SeqCst);
+
+        let wait = async {
+            loop {
+                // created before the check, so that a notification in between isn't missed
+                let idle = self.idle.notified();
+
+                if self.uploads.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+
+                idle.await;
+            }
+        };
+
+        tokio::time::timeout(grace_period, wait).await.is_ok()
+    }
+}
+
+async fn wait_for_signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.unwrap();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .unwrap()
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
+
+// the server stops accepting connections once this returns
+pub async fn graceful_shutdown(shutdown: Arc<Shutdown>, socketio: SocketIo, grace_period: u64) {
+    wait_for_signal().await;
+
+    tracing::info!("shutting down, grace period {}s", grace_period);
+
+    socketio.emit(SERVER_SHUTDOWN_EVENT, grace_period).ok();
+
+    if shutdown.drain(Duration::from_secs(grace_period)).await {
+        tracing::info!("uploads drained");
+    } else {
+        tracing::warn!("uploads still in progress after grace period");
+    }
+
+    socketio.close().await;
+
+    tracing::info!("sockets closed");
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    use crate::utils::tests::sleep_async;
+
+    pub fn get_shutdown() -> Shutdown {
+        Shutdown::new()
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drain() {
+        let shutdown = Arc::new(get_shutdown());
+
+        let guard = shutdown.begin_upload().unwrap();
+
+        let handle = tokio::spawn({
+            let shutdown = shutdown.clone();
+
+            async move { shutdown.drain(Duration::from_secs(5)).await }
+        });
+
+        sleep_async(1).await;
+
+        // started uploads continue, new ones are refused
+        let continued = shutdown.continue_upload();
+        let refused = shutdown.begin_upload().is_err();
+
+        drop(continued);
+        drop(guard);
+
+        let drained = handle.await.unwrap();
+
+        // an upload that never finishes doesn't hold the server forever
+        let stuck = get_shutdown();
+        let _guard = stuck.begin_upload().unwrap();
+        let timed_out = !stuck.drain(Duration::from_millis(100)).await;
+
+        assert!(refused);
+        assert!(drained);
+        assert!(timed_out);
+
+        sleep_async(1).await;
+    }
+}
@@ -0,0 +1,244 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use crate::env::TlsEnv;
+use crate::error::ErrorType::InternalServerError;
+use crate::error::{Error, Result};
+
+// how often the certificate files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+async fn load_config(tls: &TlsEnv) -> Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to load tls certificate"))
+}
+
+// the config is shared by every listener and follows the files on disk
+pub async fn init(tls: &TlsEnv) -> Result<RustlsConfig> {
+    let config = load_config(tls).await?;
+
+    tokio::spawn(watch(config.clone(), tls.clone()));
+
+    Ok(config)
+}
+
+async fn get_modified(tls: &TlsEnv) -> Option<(SystemTime, SystemTime)> {
+    let cert = tokio::fs::metadata(&tls.cert_path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?;
+    let key = tokio::fs::metadata(&tls.key_path)
+        .await
+        .ok()?
+        .modified()
+        .ok()?;
+
+    Some((cert, key))
+}
+
+// renewed certificates are picked up without a restart
+async fn watch(config: RustlsConfig, tls: TlsEnv) {
+    let mut last_modified = get_modified(&tls).await;
+    let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified = get_modified(&tls).await;
+
+        if modified.is_none() || modified == last_modified {
+            continue;
+        }
+
+        last_modified = modified;
+
+        // a half written pair fails here and is tried again once the other file changes
+        match config
+            .reload_from_pem_file(&tls.cert_path, &tls.key_path)
+            .await
+        {
+            Ok(_) => tracing::info!("tls certificate reloaded"),
+            Err(e) => tracing::error!(
+                "failed to reload tls certificate, keeping the old one: {}",
+                e
+            ),
+        }
+    }
+}
+
+pub async fn hsts_middleware(State(max_age): State<u64>, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+
+    if let Ok(value) = HeaderValue::from_str(&format!("max-age={}", max_age)) {
+        response
+            .headers_mut()
+            .insert(header::STRICT_TRANSPORT_SECURITY, value);
+    }
+
+    response
+}
+
+fn get_redirect_uri(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Option<String> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+    // the port of the plain listener is dropped, ipv6 hosts keep their brackets
+    let host = host.parse::<Uri>().ok()?.host()?.to_string();
+
+    let path = uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    if https_port == 443 {
+        Some(format!("https://{}{}", host, path))
+    } else {
+        Some(format!("https://{}:{}{}", host, https_port, path))
+    }
+}
+
+async fn redirect(State(https_port): State<u16>, headers: HeaderMap, uri: Uri) -> Response {
+    match get_redirect_uri(&headers, &uri, https_port) {
+        // permanent and keeps the method, so form posts aren't turned into gets
+        Some(location) => Redirect::permanent(&location).into_response(),
+        None => (StatusCode::BAD_REQUEST, "missing host").into_response(),
+    }
+}
+
+fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(redirect).with_state(https_port)
+}
+
+pub async fn serve_redirect(
+    listener: TcpListener,
+    https_port: u16,
+    token: CancellationToken,
+) -> Result<()> {
+    axum::serve(listener, redirect_router(https_port))
+        .with_graceful_shutdown(token.cancelled_owned())
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to serve redirect"))
+}
+
+pub async fn serve(
+    router: Router,
+    listener: std::net::TcpListener,
+    config: RustlsConfig,
+    token: CancellationToken,
+) -> Result<()> {
+    let handle = Handle::new();
+    let shutdown_handle = handle.clone();
+
+    tokio::spawn(async move {
+        token.cancelled().await;
+        shutdown_handle.graceful_shutdown(None);
+    });
+
+    axum_server::from_tcp_rustls(listener, config)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await
+        .map_err(|e| Error::context(InternalServerError, e, "failed to serve https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use axum::middleware;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    use crate::utils::tests::sleep_async;
+
+    #[tokio::test]
+    async fn test_tls_redirect() {
+        let mut locations = Vec::new();
+
+        for (host, https_port) in [
+            ("example.com:8080", 8443),
+            ("example.com", 443),
+            ("[::1]:8080", 8443),
+        ] {
+            let req = Request::builder()
+                .uri("/page?size=1")
+                .header(header::HOST, host)
+                .body(Body::empty())
+                .unwrap();
+
+            let res = redirect_router(https_port).oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+
+            locations.push(
+                res.headers()[header::LOCATION]
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+
+        assert_eq!(
+            locations,
+            vec![
+                "https://example.com:8443/page?size=1",
+                "https://example.com/page?size=1",
+                "https://[::1]:8443/page?size=1",
+            ]
+        );
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = redirect_router(443).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        sleep_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_tls_hsts_middleware() {
+        let router = Router::new()
+            .route("/", get(|| async { StatusCode::OK }))
+            .layer(middleware::from_fn_with_state(3600, hsts_middleware));
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = router.oneshot(req).await.unwrap();
+
+        assert_eq!(
+            res.headers()[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=3600"
+        );
+
+        sleep_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_tls_load_config_missing() {
+        let tls = TlsEnv {
+            cert_path: "./dev.missing.cert.pem".to_string(),
+            key_path: "./dev.missing.key.pem".to_string(),
+            redirect_port: None,
+            hsts_max_age: 0,
+        };
+
+        assert!(load_config(&tls).await.is_err());
+
+        sleep_async(1).await;
+    }
+}
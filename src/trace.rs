@@ -0,0 +1,200 @@
+/*
+:project: transfery
+:author: L-ING
+:copyright: (C) 2024 L-ING <hlf01@icloud.com>
+:license: MIT, see LICENSE for more details.
+*/
+
+use axum::body::Body;
+use axum::extract::OriginalUri;
+use axum::http::{header, HeaderName, HeaderValue, Request, Uri};
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::time::Instant;
+use tracing::Instrument;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::env::{Env, LogFormat};
+use crate::utils::REDACTED;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// ids from clients are only kept if they can't break a log line
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+// query parameters whose values never reach the log
+const SENSITIVE_PARAMS: [&str; 3] = ["token", "password", "certificate"];
+
+pub fn init(env: &Env) {
+    let filter = EnvFilter::from_default_env().add_directive(
+        format!("transfery={}", env.mode.tracing_level())
+            .parse()
+            .unwrap(),
+    );
+
+    let (text, json) = match env.log_format {
+        LogFormat::Text => (Some(tracing_subscriber::fmt::layer()), None),
+        // one object per line, the request id comes with the current span
+        LogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false),
+            ),
+        ),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json)
+        .init();
+}
+
+fn get_request_id<B>(req: &Request<B>) -> String {
+    req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= MAX_REQUEST_ID_LENGTH
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+fn redact_uri(uri: &Uri) -> String {
+    let query = match uri.query() {
+        Some(query) => query,
+        None => return uri.to_string(),
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SENSITIVE_PARAMS.contains(&key) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("&");
+
+    format!("{}?{}", uri.path(), query)
+}
+
+// everything logged while handling the request carries its id
+pub async fn trace_middleware(req: Request<Body>, next: Next) -> Response {
+    let request_id = get_request_id(&req);
+
+    let method = req.method().clone();
+    // nested routers only see the path under the base path
+    let uri = match req.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => redact_uri(uri),
+        None => redact_uri(req.uri()),
+    };
+    let version = req.version();
+
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("-")
+        .to_string();
+
+    let span = tracing::info_span!("request", request_id = %request_id);
+
+    let start = Instant::now();
+    let mut response = next.run(req).instrument(span.clone()).await;
+    let latency = start.elapsed();
+
+    span.in_scope(|| {
+        tracing::info!(
+            "method={}, uri={}, version={:?}, latency={:?}, status={}, user-agent={}",
+            method,
+            uri,
+            version,
+            latency,
+            response.status(),
+            user_agent
+        );
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), value);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use axum::middleware;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use crate::utils::tests::sleep_async;
+
+    #[tokio::test]
+    async fn test_trace_request_id() {
+        let router = Router::new()
+            .route("/", get(|| async { StatusCode::OK }))
+            .layer(middleware::from_fn(trace_middleware));
+
+        let mut request_ids = Vec::new();
+
+        for request_id in [Some("test-request.id_1"), Some("bad id\""), None] {
+            let mut req = Request::builder().uri("/");
+
+            if let Some(request_id) = request_id {
+                req = req.header(&REQUEST_ID_HEADER, request_id);
+            }
+
+            let res = router
+                .clone()
+                .oneshot(req.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            request_ids.push(
+                res.headers()[&REQUEST_ID_HEADER]
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+
+        assert_eq!(request_ids[0], "test-request.id_1");
+        // invalid or missing ids are replaced by generated ones
+        assert!(Uuid::parse_str(&request_ids[1]).is_ok());
+        assert!(Uuid::parse_str(&request_ids[2]).is_ok());
+
+        sleep_async(1).await;
+    }
+
+    #[test]
+    fn test_trace_redact_uri() {
+        let uri = "/pushText?content=hello&token=secret&password=secret"
+            .parse::<Uri>()
+            .unwrap();
+
+        assert_eq!(
+            redact_uri(&uri),
+            "/pushText?content=hello&token=******&password=******"
+        );
+        assert_eq!(redact_uri(&"/page?size=1".parse().unwrap()), "/page?size=1");
+        assert_eq!(redact_uri(&"/download".parse().unwrap()), "/download");
+    }
+}
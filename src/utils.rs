@@ -9,28 +9,60 @@ use axum::extract::Extension;
 use chrono::Utc;
 use sanitize_filename::sanitize;
 use std::sync::Arc;
+use uuid::Uuid;
+
+// unfinished uploads are given up after 1 day
+pub const UPLOAD_EXPIRATION: i64 = 1000 * 24 * 3600;
+
+// printed in place of passwords, keys and tokens
+pub const REDACTED: &str = "******";
 
 pub fn get_current_timestamp() -> i64 {
     Utc::now().timestamp_millis()
 }
 
-pub fn rename(filename: &str, timestamp: i64) -> String {
-    let timestamp_str = timestamp.to_string();
-    let timestamp_second = &timestamp_str[..timestamp_str.len() - 3];
+// the id keeps object names unique while the file name stays readable
+pub fn gen_object_name(filename: &str) -> String {
+    // prevent path issues
+    let filename = match sanitize(filename) {
+        filename if filename.is_empty() => "file".to_string(),
+        filename => filename,
+    };
 
-    let last_dot_pos = filename.rfind('.'); // the last dot position
+    format!("{}/{}", Uuid::new_v4().simple(), filename)
+}
+
+// keeps the hierarchy of a relative path while sanitizing every component
+pub fn sanitize_path(path: &str) -> Option<String> {
+    let components = path
+        .split(['/', '\\'])
+        .filter(|component| !matches!(*component, "" | "." | ".."))
+        .map(sanitize)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<String>>();
 
-    let new_filename = if let Some(pos) = last_dot_pos {
-        // the file has extension
-        let name = &filename[..pos];
-        let extension = &filename[pos + 1..];
-        format!("{}_{}.{}", name, timestamp_second, extension)
+    if components.is_empty() {
+        None
     } else {
-        format!("{}_{}", filename, timestamp_second)
-    };
+        Some(components.join("/"))
+    }
+}
 
-    // prevent path issues
-    sanitize(new_filename)
+// unreserved characters are kept, slashes are kept in paths but not in query values
+pub fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(byte as char)
+            }
+            b'/' if !encode_slash => result.push('/'),
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    result
 }
 
 pub fn into_layer<T>(data: T) -> Extension<Arc<T>> {